@@ -0,0 +1,46 @@
+// Standard material fragment shader with a soft fade of particles that cut into the
+// scene. The fade reads the depth prepass, so it only works with a DepthPrepass on
+// the camera. Particles are transparent, so this only runs in the forward pass.
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
+}
+
+#ifdef DEPTH_PREPASS
+#import bevy_pbr::{
+    prepass_utils::prepass_depth,
+    view_transformations::depth_ndc_to_view_z,
+}
+#endif
+
+@group(2) @binding(100) var<uniform> softness: f32;
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+#ifdef DEPTH_PREPASS
+    if softness > 0.0 {
+        // view z is negative in front of the camera, so the scene behind is smaller
+        let scene_z = depth_ndc_to_view_z(prepass_depth(in.position, 0u));
+        let particle_z = depth_ndc_to_view_z(in.position.z);
+        pbr_input.material.base_color.a *= saturate((particle_z - scene_z) / softness);
+    }
+#endif
+
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+        out.color = apply_pbr_lighting(pbr_input);
+    } else {
+        out.color = pbr_input.material.base_color;
+    }
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+
+// Wolfram's elementary cellular automaton: a row of cells, each one looks at itself
+// and its two neighbours. The three cells form a number 0..7 and that bit of the rule
+// number is the new state. The row wraps around at its ends.
+pub struct Elementary {
+    pub rule: u8,
+    pub cells: Vec<bool>
+}
+
+impl Elementary {
+    // starts with a single living cell in the middle
+    pub fn new(rule: u8, width: usize) -> Self {
+        let mut cells = vec![false; width];
+        cells[width / 2] = true;
+        Elementary { rule, cells }
+    }
+
+    pub fn step(&mut self) {
+        let width = self.cells.len();
+        self.cells = (0..width).map(|index| {
+            let left = self.cells[(index + width - 1) % width] as u8;
+            let center = self.cells[index] as u8;
+            let right = self.cells[(index + 1) % width] as u8;
+            self.rule >> (left << 2 | center << 1 | right) & 1 == 1
+        }).collect();
+    }
+}
+
+// A rule of a Life-like automaton in three dimensions: a dead cell is born with one of
+// the birth counts of living neighbours and a living one survives with one of the
+// survive counts. Each cell has 26 neighbours, the counts are bit masks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LifeRule {
+    pub birth: u32,
+    pub survive: u32
+}
+
+impl LifeRule {
+    // "B5/S45" with one digit per count, or "B14,15/S5,6" with commas for counts
+    // above nine
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut birth = None;
+        let mut survive = None;
+        for part in text.trim().split('/') {
+            let part = part.trim();
+            let (target, counts) = match part.chars().next() {
+                Some('B') | Some('b') => (&mut birth, &part[1..]),
+                Some('S') | Some('s') => (&mut survive, &part[1..]),
+                _ => return Err(format!("'{part}' does not start with B or S")),
+            };
+            *target = Some(parse_counts(counts)?);
+        }
+        match (birth, survive) {
+            (Some(birth), Some(survive)) => Ok(LifeRule { birth, survive }),
+            _ => Err(format!("'{text}' needs a B and an S part")),
+        }
+    }
+
+    fn next(&self, alive: bool, neighbours: u32) -> bool {
+        let counts = if alive { self.survive } else { self.birth };
+        counts >> neighbours & 1 == 1
+    }
+}
+
+fn parse_counts(counts: &str) -> Result<u32, String> {
+    let numbers: Vec<&str> = if counts.contains(',') {
+        counts.split(',').map(str::trim).filter(|number| !number.is_empty()).collect()
+    } else {
+        counts.split("").filter(|digit| !digit.is_empty()).collect()
+    };
+    numbers.into_iter().try_fold(0, |mask, number| {
+        match number.parse::<u32>() {
+            Ok(count) if count <= 26 => Ok(mask | 1 << count),
+            _ => Err(format!("'{number}' is no neighbour count 0..26")),
+        }
+    })
+}
+
+impl fmt::Display for LifeRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |mask: u32| {
+            let counts: Vec<String> = (0..=26).filter(|count| mask >> count & 1 == 1).map(|count| count.to_string()).collect();
+            if counts.iter().all(|count| count.len() == 1) { counts.concat() } else { counts.join(",") }
+        };
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survive))
+    }
+}
+
+// a cube of cells, with wrap the opposite faces touch each other
+pub struct Life3d {
+    pub size: usize,
+    pub cells: Vec<bool>,
+    pub rule: LifeRule,
+    pub wrap: bool
+}
+
+impl Life3d {
+    pub fn new(size: usize, rule: LifeRule) -> Self {
+        Life3d {
+            size,
+            cells: vec![false; size * size * size],
+            rule,
+            wrap: true
+        }
+    }
+
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (x * self.size + y) * self.size + z
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, alive: bool) {
+        let index = self.index(x, y, z);
+        self.cells[index] = alive;
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> bool {
+        self.cells[self.index(x, y, z)]
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|alive| **alive).count()
+    }
+
+    // places the cells relative to offset
+    pub fn insert(&mut self, offset: [usize; 3], pattern: &[[usize; 3]]) {
+        for [x, y, z] in pattern {
+            self.set(offset[0] + x, offset[1] + y, offset[2] + z, true);
+        }
+    }
+
+    fn neighbours(&self, x: usize, y: usize, z: usize) -> u32 {
+        let size = self.size as i32;
+        let mut count = 0;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if dx == 0 && dy == 0 && dz == 0 {
+                        continue;
+                    }
+                    let mut position = [x as i32 + dx, y as i32 + dy, z as i32 + dz];
+                    if self.wrap {
+                        position = position.map(|value| value.rem_euclid(size));
+                    } else if position.iter().any(|value| *value < 0 || *value >= size) {
+                        continue;
+                    }
+                    let [x, y, z] = position.map(|value| value as usize);
+                    count += self.get(x, y, z) as u32;
+                }
+            }
+        }
+        count
+    }
+
+    pub fn step(&mut self) {
+        let size = self.size;
+        let mut next = vec![false; self.cells.len()];
+        for x in 0..size {
+            for y in 0..size {
+                for z in 0..size {
+                    let index = self.index(x, y, z);
+                    next[index] = self.rule.next(self.cells[index], self.neighbours(x, y, z));
+                }
+            }
+        }
+        self.cells = next;
+    }
+
+    // the number of steps after which the cells are the same again, up to max_steps
+    pub fn period(&mut self, max_steps: usize) -> Option<usize> {
+        let start = self.cells.clone();
+        (1..=max_steps).find(|_| {
+            self.step();
+            self.cells == start
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 64;
+
+    #[test]
+    fn rule_90_grows_pascals_triangle_modulo_two() {
+        let mut elementary = Elementary::new(90, WIDTH);
+        let center = WIDTH / 2;
+        let mut pascal = vec![1_u32];
+        for generation in 0..WIDTH / 2 {
+            for (index, value) in pascal.iter().enumerate() {
+                let column = center - generation + 2 * index;
+                assert_eq!(elementary.cells[column], value % 2 == 1, "generation {generation}");
+            }
+            assert_eq!(elementary.cells.iter().filter(|alive| **alive).count(),
+                       pascal.iter().filter(|value| *value % 2 == 1).count());
+            pascal = (0..=pascal.len()).map(|index| {
+                let left = if index > 0 { pascal[index - 1] } else { 0 };
+                let right = pascal.get(index).copied().unwrap_or(0);
+                (left + right) % 2
+            }).collect();
+            elementary.step();
+        }
+    }
+
+    #[test]
+    fn rule_30_center_column() {
+        let expected = [1, 1, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 1];
+        let mut elementary = Elementary::new(30, WIDTH);
+        for (generation, bit) in expected.iter().enumerate() {
+            assert_eq!(elementary.cells[WIDTH / 2] as u8, *bit, "generation {generation}");
+            elementary.step();
+        }
+    }
+
+    #[test]
+    fn rules_are_written_the_way_they_are_read() {
+        for text in ["B5/S45", "B4/S4", "B14,15/S5,6"] {
+            let rule = LifeRule::parse(text).expect("rule");
+            assert_eq!(LifeRule::parse(&rule.to_string()), Ok(rule), "{text}");
+        }
+        //commas only where a count has two digits
+        assert_eq!(LifeRule::parse("B14,15/S5,6").expect("rule").to_string(), "B14,15/S56");
+        assert_eq!(LifeRule::parse("b5 / s4,5"), LifeRule::parse("B5/S45"));
+    }
+
+    #[test]
+    fn broken_rules_are_rejected() {
+        assert!(LifeRule::parse("B5").is_err());
+        assert!(LifeRule::parse("B3,27/S4").is_err());
+        assert!(LifeRule::parse("X5/S4").is_err());
+    }
+
+    fn assert_period(rule: &str, cells: &[[usize; 3]], period: usize) {
+        for wrap in [true, false] {
+            let mut life = Life3d::new(16, LifeRule::parse(rule).expect("rule"));
+            life.wrap = wrap;
+            life.insert([7; 3], cells);
+            let population = life.population();
+            assert_eq!(life.period(20), Some(period), "wrap {wrap}");
+            assert_eq!(life.population(), population);
+        }
+    }
+
+    #[test]
+    fn tetrahedron_oscillates_with_period_2() {
+        assert_period("B4/S4", &[[0, 0, 1], [0, 1, 0], [1, 0, 0], [1, 1, 1]], 2);
+    }
+
+    #[test]
+    fn ten_cells_oscillate_with_period_4() {
+        let cells = [[0, 0, 0], [0, 0, 1], [0, 1, 0], [0, 1, 1], [1, 0, 0], [1, 0, 2], [1, 1, 0], [1, 1, 2], [2, 0, 1], [2, 1, 1]];
+        assert_period("B5/S45", &cells, 4);
+    }
+
+    #[test]
+    fn a_lonely_cell_dies() {
+        let mut life = Life3d::new(8, LifeRule::parse("B5/S45").expect("rule"));
+        life.set(4, 4, 4, true);
+        life.step();
+        assert_eq!(life.population(), 0);
+    }
+}
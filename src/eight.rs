@@ -0,0 +1,405 @@
+use bevy::color::palettes::basic::{BLUE, GREEN, MAROON, OLIVE};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use oscillator::{Oscillator, OscillatorPlugin};
+use lsystem::{LSystem, Turtle};
+use rand::Rng;
+
+use std::f32::consts::{PI, TAU};
+
+mod orbitcamera;
+mod mesh;
+mod oscillator;
+mod lsystem;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 8.0;
+const MIN_LIMIT: f32 = -8.0;
+
+const SCENES: [&str; 3] = ["Koch curve", "Fractal plant", "Recursive tree"];
+
+// an L-system with the turtle angle in degrees and the iterations it starts with
+struct Preset {
+    axiom: &'static str,
+    //symbol, replacement and weight
+    rules: &'static [(char, &'static str, f32)],
+    angle: f32,
+    iterations: u32,
+    max_iterations: u32
+}
+
+const PRESETS: [Preset; 2] = [
+    Preset {
+        axiom: "F--F--F",
+        rules: &[('F', "F+F--F+F", 1.0)],
+        angle: 60.0,
+        iterations: 4,
+        max_iterations: 5
+    },
+    //the second rule grows the branches out of the plane, so every plant looks different
+    Preset {
+        axiom: "X",
+        rules: &[('X', "F+[[X]-X]-F[-FX]+X", 1.0), ('X', "F/[[X]&X]^F[&FX]\\X", 1.0), ('F', "FF", 1.0)],
+        angle: 25.0,
+        iterations: 5,
+        max_iterations: 6
+    }
+];
+
+const TUBE_SIDES: u32 = 6;
+const TRUNK_LENGTH: f32 = 4.0;
+
+#[derive(Resource)]
+struct UiValues{
+    scene: usize,
+    restart: bool,
+    //l-system, the sentence is only generated again for a new scene, new iterations
+    //or a restart, the angle and the tubes just interpret it again
+    regenerate: bool,
+    sentence: String,
+    iterations: u32,
+    angle: f32,
+    tubes: bool,
+    symbols: usize,
+    segments: usize,
+    //recursive tree
+    depth: usize,
+    branches: usize,
+    spread: f32,
+    length_ratio: f32,
+    sway: f32,
+    wind_frequency: f32
+}
+
+#[derive(Component)]
+struct FractalScene;
+
+// a branch of the recursive tree, level 0 is the trunk
+#[derive(Component)]
+struct TreeBranch {
+    level: usize
+}
+
+// what every branch of the recursive tree shares
+struct TreeShape {
+    mesh: Handle<Mesh>,
+    bark: Handle<StandardMaterial>,
+    leaf_mesh: Handle<Mesh>,
+    leaf: Handle<StandardMaterial>,
+    depth: usize,
+    branches: usize,
+    spread: f32,
+    length_ratio: f32,
+    sway: f32,
+    wind_frequency: f32
+}
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(UiValues{
+            scene: 0,
+            restart: true,
+            regenerate: true,
+            sentence: String::new(),
+            iterations: PRESETS[0].iterations,
+            angle: PRESETS[0].angle,
+            tubes: false,
+            symbols: 0,
+            segments: 0,
+            depth: 5,
+            branches: 3,
+            spread: 30.0,
+            length_ratio: 0.7,
+            sway: 0.05,
+            wind_frequency: 0.4
+        })
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 8".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin,
+            OscillatorPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              restart,
+                              apply_ui_values).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 28.0,
+            ..default()
+        }
+    ));
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<FractalScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let mut rng = rand::rng();
+
+    if ui_values.scene < PRESETS.len() {
+        let preset = &PRESETS[ui_values.scene];
+        let lsystem = preset.rules.iter().fold(LSystem::new(preset.axiom), |lsystem, (symbol, replacement, weight)| {
+            lsystem.with_rule(*symbol, replacement, *weight)
+        });
+        if ui_values.regenerate {
+            ui_values.regenerate = false;
+            ui_values.sentence = lsystem.generate(ui_values.iterations, &mut rng);
+        }
+        let turtle = Turtle {
+            angle: ui_values.angle.to_radians(),
+            width: 0.5,
+            ..default()
+        };
+        let mut segments = turtle.interpret(&ui_values.sentence);
+        lsystem::fit(&mut segments, MAX_LIMIT - MIN_LIMIT - 2.0);
+        ui_values.symbols = ui_values.sentence.chars().count();
+        ui_values.segments = segments.len();
+
+        let color = if ui_values.scene == 0 { BLUE } else { GREEN };
+        let (mesh, material) = if ui_values.tubes {
+            (lsystem::tube_mesh(&segments, TUBE_SIDES), StandardMaterial {
+                base_color: Color::Srgba(color),
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            })
+        } else {
+            (lsystem::line_mesh(&segments), StandardMaterial {
+                base_color: Color::Srgba(color),
+                unlit: true,
+                ..default()
+            })
+        };
+        commands.spawn((
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(materials.add(material)),
+            Transform::default(),
+            FractalScene
+        ));
+        return;
+    }
+
+    //the recursive tree grows from the floor, every branch is a child of the one
+    //it grows out of, so the sway of the trunk moves the whole tree
+    let shape = TreeShape {
+        mesh: meshes.add(Mesh::from(Cylinder { radius: 1.0, half_height: 0.5 })),
+        bark: materials.add(StandardMaterial {
+            base_color: Color::Srgba(MAROON),
+            ..default()
+        }),
+        leaf_mesh: meshes.add(Mesh::from(Sphere { radius: 0.25 })),
+        leaf: materials.add(StandardMaterial {
+            base_color: Color::Srgba(OLIVE),
+            ..default()
+        }),
+        depth: ui_values.depth,
+        branches: ui_values.branches,
+        spread: ui_values.spread.to_radians(),
+        length_ratio: ui_values.length_ratio,
+        sway: ui_values.sway,
+        wind_frequency: ui_values.wind_frequency
+    };
+    commands
+        .spawn((Transform::default(), Visibility::default(), FractalScene))
+        .with_children(|parent| {
+            spawn_branch(parent, &shape, 0, TRUNK_LENGTH, Vec3::new(0.0, MIN_LIMIT, 0.0), Quat::IDENTITY, &mut rng);
+        });
+}
+
+// the sway of a branch grows with its level, the thin twigs move most
+fn sway_amplitude(sway: f32, level: usize) -> Vec3 {
+    let amplitude = sway * (1.0 + level as f32);
+    Vec3::new(amplitude, 0.0, amplitude * 0.5)
+}
+
+fn sway_frequency(wind_frequency: f32) -> Vec3 {
+    Vec3::new(wind_frequency, 0.0, wind_frequency * 1.3)
+}
+
+fn spawn_branch(
+    parent: &mut ChildBuilder,
+    shape: &TreeShape,
+    level: usize,
+    length: f32,
+    translation: Vec3,
+    rotation: Quat,
+    rng: &mut impl Rng
+){
+    //the waves of the wind reach the higher branches a little later
+    let phase = Vec3::splat(level as f32 * 0.6 + rng.random_range(0.0..0.3));
+    parent
+        .spawn((
+            Transform::from_translation(translation).with_rotation(rotation),
+            Visibility::default(),
+            Oscillator::new(sway_amplitude(shape.sway, level), sway_frequency(shape.wind_frequency))
+                .with_phase(phase)
+                .with_rotation(rotation),
+            TreeBranch { level }
+        ))
+        .with_children(|branch| {
+            let radius = length * 0.06;
+            branch.spawn((
+                Mesh3d(shape.mesh.clone()),
+                MeshMaterial3d(shape.bark.clone()),
+                Transform::from_xyz(0.0, length / 2.0, 0.0).with_scale(Vec3::new(radius, length, radius))
+            ));
+            if level == shape.depth {
+                branch.spawn((
+                    Mesh3d(shape.leaf_mesh.clone()),
+                    MeshMaterial3d(shape.leaf.clone()),
+                    Transform::from_xyz(0.0, length, 0.0)
+                ));
+                return;
+            }
+            for index in 0..shape.branches {
+                let heading = index as f32 / shape.branches as f32 * TAU + rng.random_range(-0.3..0.3);
+                let tilt = shape.spread * rng.random_range(0.8..1.2);
+                let child_length = length * shape.length_ratio * rng.random_range(0.85..1.1);
+                spawn_branch(
+                    branch,
+                    shape,
+                    level + 1,
+                    child_length,
+                    Vec3::new(0.0, length, 0.0),
+                    Quat::from_rotation_y(heading) * Quat::from_rotation_x(tilt),
+                    rng
+                );
+            }
+        });
+}
+
+// the wind changes the swaying tree, its shape needs a restart
+fn apply_ui_values(
+    ui_values: Res<UiValues>,
+    mut query: Query<(&mut Oscillator, &TreeBranch)>
+){
+    for (mut oscillator, branch) in query.iter_mut() {
+        oscillator.amplitude = sway_amplitude(ui_values.sway, branch.level);
+        oscillator.frequency = sway_frequency(ui_values.wind_frequency);
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+){
+    let mut scene = ui_values.scene;
+    let mut iterations = ui_values.iterations;
+    let mut angle = ui_values.angle;
+    let mut tubes = ui_values.tubes;
+    let mut depth = ui_values.depth;
+    let mut branches = ui_values.branches;
+    let mut spread = ui_values.spread;
+    let mut length_ratio = ui_values.length_ratio;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Scene")
+            .selected_text(SCENES[scene])
+            .show_ui(ui, |ui| {
+                for (index, name) in SCENES.iter().enumerate() {
+                    ui.selectable_value(&mut scene, index, *name);
+                }
+            });
+        if let Some(preset) = PRESETS.get(ui_values.scene) {
+            ui.add(egui::Slider::new(&mut iterations, 0..=preset.max_iterations).text("iterations"));
+            ui.add(egui::Slider::new(&mut angle, 1.0..=120.0).text("angle"));
+            ui.checkbox(&mut tubes, "tubes");
+            ui.label(format!("axiom: {}", preset.axiom));
+            for (symbol, replacement, _) in preset.rules {
+                ui.label(format!("{symbol} → {replacement}"));
+            }
+            ui.label(format!("symbols: {}", ui_values.symbols));
+            ui.label(format!("segments: {}", ui_values.segments));
+        } else {
+            ui.add(egui::Slider::new(&mut depth, 1..=6).text("depth"));
+            ui.add(egui::Slider::new(&mut branches, 2..=4).text("branches"));
+            ui.add(egui::Slider::new(&mut spread, 5.0..=80.0).text("spread"));
+            ui.add(egui::Slider::new(&mut length_ratio, 0.4..=0.9).text("length ratio"));
+            ui.add(egui::Slider::new(&mut ui_values.sway, 0.0..=0.2).text("sway"));
+            ui.add(egui::Slider::new(&mut ui_values.wind_frequency, 0.05..=2.0).text("wind frequency"));
+        }
+        if ui.button("Restart").clicked() {
+            ui_values.regenerate = true;
+            ui_values.restart = true;
+        }
+    });
+    if scene != ui_values.scene {
+        ui_values.scene = scene;
+        if let Some(preset) = PRESETS.get(scene) {
+            iterations = preset.iterations;
+            angle = preset.angle;
+        }
+        ui_values.regenerate = true;
+        ui_values.restart = true;
+    }
+    if iterations != ui_values.iterations {
+        ui_values.regenerate = true;
+    }
+    if iterations != ui_values.iterations || angle != ui_values.angle || tubes != ui_values.tubes
+        || depth != ui_values.depth || branches != ui_values.branches
+        || spread != ui_values.spread || length_ratio != ui_values.length_ratio {
+        ui_values.iterations = iterations;
+        ui_values.angle = angle;
+        ui_values.tubes = tubes;
+        ui_values.depth = depth;
+        ui_values.branches = branches;
+        ui_values.spread = spread;
+        ui_values.length_ratio = length_ratio;
+        ui_values.restart = true;
+    }
+}
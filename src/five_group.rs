@@ -1,10 +1,14 @@
-use bevy::color::palettes::css::YELLOW;
+use bevy::color::palettes::css::{STEEL_BLUE, YELLOW};
 use bevy::prelude::*;
 use bevy::window::WindowResolution;
 use bevy_egui::EguiPlugin;
 use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use trail::{Trail, TrailPlugin};
+use preset::{SteeringPreset, PRESET_PATH};
 mod orbitcamera;
 mod mesh;
+mod preset;
+mod trail;
 
 use rand::Rng;
 
@@ -143,7 +147,8 @@ fn main() {
             ..default()
         }))
         .add_plugins((OrbitCameraPlugin,
-                             EguiPlugin))
+                             EguiPlugin,
+                             TrailPlugin))
         .add_systems(Startup, (spawn_camera,
                                spawn_scene,
                                mesh::spawn_limit_cube))
@@ -184,6 +189,8 @@ fn spawn_scene(
         color: Color::WHITE,
         brightness: 0.02,
     });
+    //the weights evolved by nine_flocking, if there are any
+    let preset = SteeringPreset::load_optional(PRESET_PATH);
     //vehicle
     for _ in 0..NUM_MOVEABLE{
         let mut rng = rand::thread_rng();
@@ -200,16 +207,19 @@ fn spawn_scene(
             },
             Moveable {
                 velocity: velocity,
+                maximum_speed: preset.map_or(MAX_SPEED, |preset| preset.maximum_speed),
+                maximum_force: preset.map_or(MAX_FORCE, |preset| preset.maximum_force),
                 ..default() },
             Align {
-                weight: 1.0
+                weight: preset.map_or(1.0, |preset| preset.align)
             },
             Separate{
-                weight: 1.0
+                weight: preset.map_or(1.0, |preset| preset.separate)
             },
             Cohesion{
-                weight: 1.0
-            }
+                weight: preset.map_or(1.0, |preset| preset.cohesion)
+            },
+            Trail::new(20, Color::Srgba(STEEL_BLUE)).with_spacing(0.5)
         )).id();
 
         mover.0.push(entity.index());
@@ -4,9 +4,16 @@ use bevy::prelude::*;
 use bevy::window::WindowResolution;
 use bevy_egui::EguiPlugin;
 use orbitcamera::{OrbitCameraPlugin,OrbitCamera};
+use particle::{ParicleEmiter, ParticlePlugin};
+use particle::collision::ParticleBounds;
+use preset::{SteeringPreset, PRESET_PATH};
+use ray::{AabBox, Ray};
 use skybox::SkyboxPlugin;
 
 mod orbitcamera;
+mod particle;
+mod preset;
+mod ray;
 mod skybox;
 
 use rand::Rng;
@@ -24,97 +31,6 @@ struct Mover(Vec<u32>);
 const MAX_SPEED:f32=32.0;
 const MAX_FORCE:f32=16.0;
 
-struct AabBox {
-    min:Vec3,
-    max:Vec3
-}
-
-impl AabBox {
-    fn new(origin:Vec3, x_length:f32, y_length:f32, z_lenght:f32, distance:f32)->Self{
-        AabBox{
-            min: Vec3::new(
-                origin.x-x_length/2.0-distance,
-                origin.y-y_length/2.0-distance,
-                origin.z-z_lenght/2.0-distance
-            ),
-            max:Vec3::new(
-                origin.x+x_length/2.0+distance,
-                origin.y+y_length/2.0+distance,
-                origin.z+z_lenght/2.0+distance
-            )
-        }
-    }
-}
-
-struct Ray{
-    origin:Vec3,
-    direction:Vec3
-}
-
-impl Ray {
-    fn new(orign:Vec3, direction:Vec3)->Self{
-        Ray {
-            origin:orign,
-            direction
-        }
-    }
-
-    fn intersect_box(&self, aabb:&AabBox)->Option<(Vec3,Vec3)>{
-        let mut tmin = (aabb.min.x - self.origin.x) / self.direction.x;
-        let mut tmax = (aabb.max.x - self.origin.x) / self.direction.x;
-
-        if tmin > tmax {
-            let k = tmin;
-            tmin = tmax;
-            tmax = k;
-        }
-
-        let mut tymin = (aabb.min.y - self.origin.y) / self.direction.y;
-        let mut tymax = (aabb.max.y - self.origin.y) / self.direction.y;
-
-        if tymin > tymax {
-            let k = tymin;
-            tymin = tymax;
-            tymax = k;
-        }
-
-        if tmin > tymax || tymin > tmax {
-            return None
-        };
-
-        if tymin > tmin {
-            tmin = tymin;
-        }
-
-        if tymax < tmax {
-            tmax = tymax;
-        }
-
-        let mut tzmin = (aabb.min.z - self.origin.z) / self.direction.z;
-        let mut tzmax = (aabb.max.z - self.origin.z) / self.direction.z;
-
-        if tzmin > tzmax {
-            let k = tzmin;
-            tzmin = tzmax;
-            tzmax = k;
-        }
-
-        if tmin > tzmax || tzmin > tmax {
-            return None;
-        }
-
-        if tzmin > tmin {
-            tmin = tzmin;
-        }
-
-        if tzmax < tmax {
-            tmax = tzmax;
-        }
-
-        Some((Vec3::new(tmin,tymin,tzmin),Vec3::new(tmax,tymax,tzmax)))
-    }
-}
-
 #[derive(Component)]
 struct SpawnLaser {
     cooldown:f32
@@ -122,9 +38,13 @@ struct SpawnLaser {
 
 #[derive(Component)]
 struct Laser{
-    time:f32
+    time:f32,
+    team:TeamType
 }
 
+#[derive(Component)]
+struct Explosion;
+
 #[derive(Component)]
 struct Moveable {
     force:Vec3,
@@ -267,7 +187,8 @@ fn main() {
         }))
         .add_plugins((OrbitCameraPlugin,
                       SkyboxPlugin,
-                      EguiPlugin))
+                      EguiPlugin,
+                      ParticlePlugin { bounds: ParticleBounds::cube(MIN_LIMIT, MAX_LIMIT) }))
         .add_systems(Startup, (spawn_camera,
                                spawn_scene))
         .add_systems(Update, (update_seek.before(moving),
@@ -278,6 +199,7 @@ fn main() {
                               update_collision.before(moving),
                               spawn_laser,
                               move_laser,
+                              hit_fighter.after(move_laser),
                               moving))
         .run();
 }
@@ -309,11 +231,20 @@ fn spawn_scene(
         brightness: 0.2,
     });
 
+    //explosions of destroyed fighters
+    commands.spawn((
+        ParicleEmiter::triggered(asset_server.load("effects/explosion.emitter.ron")),
+        Transform::default(),
+        Explosion
+    ));
+
     //cruiser
     let cruiser_position = vec![Vec3::ZERO,
                                            Vec3::new(0.0, 0.0,60.0)];
     let teams = vec![TeamType::Blue, TeamType::Pink];
     let fighters = vec!["models/cone_blue.glb#Scene0","models/cone_pink.glb#Scene0"];
+    //the weights evolved by nine_flocking, if there are any
+    let preset = SteeringPreset::load_optional(PRESET_PATH);
 
     for i in 0..2 {
 
@@ -342,22 +273,24 @@ fn spawn_scene(
                 },
                 Moveable {
                     velocity,
+                    maximum_speed: preset.map_or(MAX_SPEED, |preset| preset.maximum_speed),
+                    maximum_force: preset.map_or(MAX_FORCE, |preset| preset.maximum_force),
                     ..default()
                 },
                 Align {
-                    weight: 1.0
+                    weight: preset.map_or(1.0, |preset| preset.align)
                 },
                 Separate {
-                    weight: 4.0
+                    weight: preset.map_or(4.0, |preset| preset.separate)
                 },
                 Cohesion {
-                    weight: 0.5
+                    weight: preset.map_or(0.5, |preset| preset.cohesion)
                 },
                 Random{
                     weight:0.5
                 },
                 Seek{
-                    weight:5.0,
+                    weight: preset.map_or(5.0, |preset| preset.seek),
                     target: cruiser_position[i]
                 },
                 Team{
@@ -472,15 +405,15 @@ fn update_align(
 ) {
     let neighbor_distance = 8.0;
 
-    for i in 0..NUM_MOVEABLE {
+    for i in 0..mover.0.len() {
 
-        let mover_id = mover.0[i as usize];
+        let mover_id = mover.0[i];
         let mut sum = Vec3::ZERO;
         let mut count = 0;
 
-        for j in 0..NUM_MOVEABLE {
+        for j in 0..mover.0.len() {
 
-            let other_mover_id = mover.0[j as usize];
+            let other_mover_id = mover.0[j];
 
             if mover_id == other_mover_id {
                 continue;
@@ -526,15 +459,15 @@ fn update_separate(
     mover: Res<Mover>,
     mut query: Query<(Entity, &mut Moveable, &Transform, &mut Separate)>
 ) {
-    for i in 0..NUM_MOVEABLE {
+    for i in 0..mover.0.len() {
 
-        let mover_id = mover.0[i as usize];
+        let mover_id = mover.0[i];
         let mut sum = Vec3::ZERO;
         let mut count = 0;
 
-        for j in 0..NUM_MOVEABLE {
+        for j in 0..mover.0.len() {
 
-            let other_mover_id = mover.0[j as usize];
+            let other_mover_id = mover.0[j];
 
             if mover_id == other_mover_id {
                 continue;
@@ -579,15 +512,15 @@ fn update_cohesion(
 ) {
     let neighbor_distance = 8.0;
 
-    for i in 0..NUM_MOVEABLE {
+    for i in 0..mover.0.len() {
 
-        let mover_id = mover.0[i as usize];
+        let mover_id = mover.0[i];
         let mut sum = Vec3::ZERO;
         let mut count = 0;
 
-        for j in 0..NUM_MOVEABLE {
+        for j in 0..mover.0.len() {
 
-            let other_mover_id = mover.0[j as usize];
+            let other_mover_id = mover.0[j];
 
             if mover_id == other_mover_id {
                 continue;
@@ -705,10 +638,10 @@ fn spawn_laser(
     time:Res<Time>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut query: Query<(&Transform,&mut SpawnLaser)>
+    mut query: Query<(&Transform,&mut SpawnLaser,&Team)>
 )
 {
-    for (transform, mut spawnlaser) in query.iter_mut(){
+    for (transform, mut spawnlaser, team) in query.iter_mut(){
 
         if spawnlaser.cooldown <= 0.0 {
 
@@ -731,7 +664,7 @@ fn spawn_laser(
                         ..default()
                     },
                     Name::new("Laser"),
-                    Laser{time:LASER_TIME}
+                    Laser{time:LASER_TIME, team:team.value.clone()}
                 ));
             } else {
             spawnlaser.cooldown -= time.delta_secs();
@@ -754,4 +687,37 @@ fn move_laser(
             commands.entity(entity).despawn_recursive();
         }
     }
-}
\ No newline at end of file
+}
+const HIT_DISTANCE: f32 = 2.0;
+const EXPLOSION_PARTICLES: u32 = 120;
+
+// a laser destroys the first fighter of the other team it comes close to
+fn hit_fighter(
+    mut commands: Commands,
+    mut mover: ResMut<Mover>,
+    lasers: Query<(Entity, &Transform, &Laser)>,
+    fighters: Query<(Entity, &Transform, &Moveable, &Team)>,
+    mut explosion: Query<&mut ParicleEmiter, With<Explosion>>
+){
+    let Ok(mut explosion) = explosion.get_single_mut() else {
+        return;
+    };
+    let mut destroyed = Vec::new();
+
+    for (laser_entity, laser_transform, laser) in lasers.iter() {
+        let hit = fighters.iter().find(|(entity, transform, _, team)| {
+            team.value != laser.team
+                && !destroyed.contains(entity)
+                && transform.translation.distance(laser_transform.translation) < HIT_DISTANCE
+        });
+        if let Some((entity, transform, moveable, _)) = hit {
+            explosion.burst(Transform::from_translation(transform.translation),
+                            moveable.velocity * 0.5,
+                            EXPLOSION_PARTICLES);
+            commands.entity(entity).despawn_recursive();
+            commands.entity(laser_entity).despawn_recursive();
+            destroyed.push(entity);
+        }
+    }
+    mover.0.retain(|index| !destroyed.iter().any(|entity| entity.index() == *index));
+}
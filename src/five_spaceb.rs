@@ -2,10 +2,12 @@ use bevy::color::palettes::css::LIME;
 use bevy::prelude::*;
 use bevy::window::WindowResolution;
 use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use ray::{AabBox, Ray};
 use skybox::SkyboxPlugin;
 use bevy_egui::EguiPlugin;
 
 mod orbitcamera;
+mod ray;
 mod skybox;
 
 use rand::Rng;
@@ -23,97 +25,6 @@ struct Mover(Vec<u32>);
 const MAX_SPEED:f32=32.0;
 const MAX_FORCE:f32=16.0;
 
-struct AabBox {
-    min:Vec3,
-    max:Vec3
-}
-
-impl AabBox {
-    fn new(origin:Vec3, x_length:f32, y_length:f32, z_lenght:f32, distance:f32)->Self{
-        AabBox{
-            min: Vec3::new(
-                origin.x-x_length/2.0-distance,
-                origin.y-y_length/2.0-distance,
-                origin.z-z_lenght/2.0-distance
-            ),
-            max:Vec3::new(
-                origin.x+x_length/2.0+distance,
-                origin.y+y_length/2.0+distance,
-                origin.z+z_lenght/2.0+distance
-            )
-        }
-    }
-}
-
-struct Ray{
-    origin:Vec3,
-    direction:Vec3
-}
-
-impl Ray {
-    fn new(orign:Vec3, direction:Vec3)->Self{
-        Ray {
-            origin:orign,
-            direction
-        }
-    }
-
-    fn intersect_box(&self, aabb:&AabBox)->Option<(Vec3,Vec3)>{
-        let mut tmin = (aabb.min.x - self.origin.x) / self.direction.x;
-        let mut tmax = (aabb.max.x - self.origin.x) / self.direction.x;
-
-        if tmin > tmax {
-            let k = tmin;
-            tmin = tmax;
-            tmax = k;
-        }
-
-        let mut tymin = (aabb.min.y - self.origin.y) / self.direction.y;
-        let mut tymax = (aabb.max.y - self.origin.y) / self.direction.y;
-
-        if tymin > tymax {
-            let k = tymin;
-            tymin = tymax;
-            tymax = k;
-        }
-
-        if tmin > tymax || tymin > tmax {
-            return None
-        };
-
-        if tymin > tmin {
-            tmin = tymin;
-        }
-
-        if tymax < tmax {
-            tmax = tymax;
-        }
-
-        let mut tzmin = (aabb.min.z - self.origin.z) / self.direction.z;
-        let mut tzmax = (aabb.max.z - self.origin.z) / self.direction.z;
-
-        if tzmin > tzmax {
-            let k = tzmin;
-            tzmin = tzmax;
-            tzmax = k;
-        }
-
-        if tmin > tzmax || tzmin > tmax {
-            return Option::None;
-        }
-
-        if tzmin > tmin {
-            tmin = tzmin;
-        }
-
-        if tzmax < tmax {
-            tmax = tzmax;
-        }
-
-        Option::Some((Vec3::new(tmin,tymin,tzmin),Vec3::new(tmax,tymax,tzmax)))
-    }
-}
-
 #[derive(Component)]
 struct SpawnLaser {
     cooldown:f32
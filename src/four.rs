@@ -1,12 +1,25 @@
-use bevy::color::palettes::basic::BLUE;
+use bevy::color::palettes::basic::GRAY;
+use bevy::core_pipeline::prepass::DepthPrepass;
 use bevy::prelude::*;
 use bevy::window::WindowResolution;
-use bevy_egui::EguiPlugin;
-use rand::Rng;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
 
 use orbitcamera::{OrbitCamera, OrbitCameraPlugin};
+use particle::{EmitterDefinition, ParicleEmiter, ParticlePlugin, ParticleTrail};
+use particle::batch::ParticleBuffer;
+use particle::collision::{ParticleBounds, ParticleCollider};
+use particle::field::{Attractor, FieldGizmos, GlobalForce, Repeller, Vortex, WindZone};
+use trail::{Trail, TrailMode, TrailPlugin};
+use wind::{wind_ui, Wind, WindPlugin};
+
+use std::sync::Arc;
+
 mod orbitcamera;
 mod mesh;
+mod noise;
+mod particle;
+mod trail;
+mod wind;
 
 const HEIGHT: f32 = 440.0;
 const WIDTH: f32 = 812.0;
@@ -14,41 +27,65 @@ const WIDTH: f32 = 812.0;
 const MAX_LIMIT: f32 = 8.0;
 const MIN_LIMIT: f32 = -8.0;
 
-#[derive(Component)]
-struct Particle {
-    velocity:Vec3,
-    acceleration: Vec3,
-    lifetime:Timer
+struct Effect {
+    name: &'static str,
+    path: &'static str,
+    position: Vec3
 }
 
-#[derive(Component, Clone, Copy)]
-pub struct ParticleAlpha {
-    start: f32,
-    end: f32,
-}
+const EFFECTS: [Effect; 11] = [
+    Effect { name: "Fountain (box)", path: "effects/fountain.emitter.ron", position: Vec3::new(0.0, MAX_LIMIT, 0.0) },
+    Effect { name: "Nova (sphere)", path: "effects/nova.emitter.ron", position: Vec3::ZERO },
+    Effect { name: "Jet (cone)", path: "effects/jet.emitter.ron", position: Vec3::new(0.0, MIN_LIMIT, 0.0) },
+    Effect { name: "Ring (disk)", path: "effects/ring.emitter.ron", position: Vec3::ZERO },
+    Effect { name: "Ship (mesh surface)", path: "effects/ship_surface.emitter.ron", position: Vec3::ZERO },
+    Effect { name: "Fire (additive billboard)", path: "effects/fire.emitter.ron", position: Vec3::new(0.0, MIN_LIMIT, 0.0) },
+    Effect { name: "Smoke (flipbook billboard)", path: "effects/smoke.emitter.ron", position: Vec3::new(0.0, MIN_LIMIT, 0.0) },
+    Effect { name: "Sparks", path: "effects/sparks.emitter.ron", position: Vec3::new(0.0, MIN_LIMIT, 0.0) },
+    Effect { name: "Rain (collision)", path: "effects/rain.emitter.ron", position: Vec3::new(0.0, MAX_LIMIT, 0.0) },
+    Effect { name: "Fireworks (sub emitter)", path: "effects/fireworks.emitter.ron", position: Vec3::new(0.0, MIN_LIMIT, 0.0) },
+    Effect { name: "Dust (100k batched)", path: "effects/dust.emitter.ron", position: Vec3::ZERO },
+];
 
-impl Particle {
-    fn apply_force(&mut self, force:Vec3)->(){
-        self.acceleration = self.acceleration +  force;
-    }
+const FIELDS: [&str; 4] = ["Attractor", "Repeller", "Wind", "Vortex"];
 
-    fn get_factor(&self)->f32{
-        self.lifetime.elapsed().as_secs_f32()/self.lifetime.duration().as_secs_f32()
-    }
+#[derive(Resource)]
+struct UiValues{
+    effect: usize,
+    fields: [bool; 4],
+    sphere_collider: bool,
+    ramp_collider: bool
 }
 
 #[derive(Component)]
-struct ParicleEmiter {
-    spawn_time:Timer,
-    particle_time: f32,
-    amount_per_burst: u32,
-    position_variance: f32,
-    particle_alpha: Option<ParticleAlpha>
-}
+struct DemoField(usize);
+
+#[derive(Component)]
+struct DemoCollider;
+
+#[derive(Component)]
+struct DemoRamp;
+
+const COLLIDER_RADIUS: f32 = 2.5;
+
+const HEADLESS_EFFECT: &str = "assets/effects/dust.emitter.ron";
+const HEADLESS_FRAMES: u32 = 180;
 
 fn main() {
+    //cargo run --example four -- --headless
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless();
+        return;
+    }
+
     App::new()
         .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(UiValues{
+            effect: 0,
+            fields: [false; 4],
+            sphere_collider: false,
+            ramp_collider: false
+        })
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Example 4".to_string(),
@@ -59,19 +96,25 @@ fn main() {
             ..default()
         }))
         .add_plugins((OrbitCameraPlugin,
+                      ParticlePlugin { bounds: ParticleBounds::cube(MIN_LIMIT, MAX_LIMIT) },
+                      TrailPlugin,
+                      WindPlugin,
                       EguiPlugin))
         .add_systems(Startup, (spawn_camera,
                                spawn_scene,
                                mesh::spawn_limit_cube))
-        .add_systems(Update, (emit_particles,
-                             update_particle_lifetime,
-                             moving,
-                             update_particle_alpha.after(emit_particles)))
+        .add_systems(Update, (ui_egui,
+                              wind_egui,
+                              blow_wind,
+                              attach_trails,
+                              update_colliders))
         .run();
 }
 
 fn spawn_scene(
-    mut commands:Commands
+    mut commands:Commands,
+    asset_server: Res<AssetServer>,
+    ui_values: Res<UiValues>
 ){
     //light
     commands.spawn((DirectionalLight {
@@ -90,20 +133,18 @@ fn spawn_scene(
         brightness: 0.02,
     });
 
-    // particle emiter
+    spawn_emiter(&mut commands, &asset_server, &EFFECTS[ui_values.effect]);
+}
+
+fn spawn_emiter(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    effect: &Effect
+){
     commands
         .spawn((
-                   Transform::from_xyz(0.0,MAX_LIMIT,0.0),
-            ParicleEmiter{
-                spawn_time: Timer::from_seconds(0.05,TimerMode::Repeating),
-                particle_time: 4.0,
-                amount_per_burst: 20,
-                position_variance: 2.0,
-                particle_alpha: Some(ParticleAlpha{
-                    start: 1.0,
-                    end: 0.0
-                })
-            }));
+            Transform::from_translation(effect.position),
+            ParicleEmiter::new(asset_server.load(effect.path))));
 }
 
 fn spawn_camera(
@@ -112,6 +153,8 @@ fn spawn_camera(
     commands.spawn((
         Camera3d::default(),
         Msaa::Sample4,
+        //soft billboards fade where they cut into the scene
+        DepthPrepass,
         OrbitCamera{
             distance : 28.0,
             ..default()
@@ -119,82 +162,184 @@ fn spawn_camera(
     ));
 }
 
-fn moving(
-    time:Res<Time>,
-    mut query: Query<(&mut Transform, &mut Particle)>
+fn spawn_field(
+    commands: &mut Commands,
+    index: usize
 ){
-    for (mut transform, mut moveable) in &mut query {
-        let gravity = Vec3::new(0.0,-0.5,0.0);
-        moveable.apply_force(gravity);
-        moveable.velocity = moveable.velocity + moveable.acceleration * time.delta_secs();
-        transform.translation = transform.translation + moveable.velocity * time.delta_secs();
-
-    }
+    let mut field = commands.spawn(DemoField(index));
+    match index {
+        0 => field.insert((Transform::from_xyz(4.0, 0.0, 0.0),
+                           Attractor { strength: 60.0, radius: 6.0 })),
+        1 => field.insert((Transform::from_xyz(-4.0, 2.0, 0.0),
+                           Repeller { strength: 60.0, radius: 5.0 })),
+        2 => field.insert((Transform::from_xyz(0.0, -4.0, 0.0),
+                           WindZone { force: Vec3::new(3.0, 0.0, 0.0), half_extents: Vec3::new(8.0, 4.0, 8.0) })),
+        _ => field.insert((Transform::from_xyz(0.0, 0.0, 0.0),
+                           Vortex { axis: Vec3::Y, strength: 6.0, pull: 1.0, radius: 6.0 })),
+    };
 }
 
-fn emit_particles(
+fn ui_egui(
     mut commands:Commands,
-    time: Res<Time>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut query: Query<(&mut ParicleEmiter, &Transform)>
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    mut field_gizmos: ResMut<FieldGizmos>,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, With<ParicleEmiter>>,
+    query_fields: Query<(Entity, &DemoField)>
 ){
-    for (mut emiter, transform) in query.iter_mut(){
-        emiter.spawn_time.tick(time.delta());
-        if emiter.spawn_time.just_finished(){
-            for _ in 0..emiter.amount_per_burst{
-                let mut rng = rand::thread_rng();
-                let particle = commands.spawn((
-                    Mesh3d( meshes.add(Mesh::from( Cuboid::new(0.25, 0.25,0.25)))),
-                    MeshMaterial3d( materials.add(StandardMaterial{
-                                                        base_color:Color::Srgba(BLUE),
-                                                        alpha_mode: AlphaMode::Blend,
-                                                        ..default()})),
-                    Transform::from_xyz(transform.translation.x + rng.gen_range(-emiter.position_variance..emiter.position_variance),
-                                        transform.translation.y + rng.gen_range(-emiter.position_variance..emiter.position_variance),
-                                        transform.translation.z + rng.gen_range(-emiter.position_variance..emiter.position_variance)),
-                    Particle {
-                        lifetime: Timer::from_seconds(emiter.particle_time,TimerMode::Once),
-                        velocity: Vec3::new(1.0, 1.0, 1.0),
-                        acceleration: Vec3::new(0.0, 0.0,0.0)
-                    }
-                )).id();
-
-                if let Some(alpha) = emiter.particle_alpha {
-                    commands.entity(particle).insert(ParticleAlpha{
-                        start:alpha.start,
-                        end:alpha.end
-                    });
+    let mut selected = ui_values.effect;
+    let mut fields = ui_values.fields;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Effect")
+            .selected_text(EFFECTS[selected].name)
+            .show_ui(ui, |ui| {
+                for (index, effect) in EFFECTS.iter().enumerate() {
+                    ui.selectable_value(&mut selected, index, effect.name);
+                }
+            });
+        for (index, name) in FIELDS.iter().enumerate() {
+            ui.add(egui::Checkbox::new(&mut fields[index], *name));
+        }
+        ui.add(egui::Checkbox::new(&mut field_gizmos.enabled, "show fields"));
+        ui.add(egui::Checkbox::new(&mut ui_values.sphere_collider, "sphere collider"));
+        ui.add(egui::Checkbox::new(&mut ui_values.ramp_collider, "ramp collider"));
+    });
+
+    for (index, enabled) in fields.into_iter().enumerate() {
+        if enabled == ui_values.fields[index] {
+            continue;
+        }
+        if enabled {
+            spawn_field(&mut commands, index);
+        } else {
+            for (entity, field) in query_fields.iter() {
+                if field.0 == index {
+                    commands.entity(entity).despawn_recursive();
                 }
             }
         }
     }
-}
+    ui_values.fields = fields;
 
-fn update_particle_lifetime(
-    mut commands:Commands,
-    mut query: Query<(Entity, &mut Particle)>,
-    time: Res<Time>,
-) {
-    for (entity, mut particle) in query.iter_mut() {
-        particle.lifetime.tick(time.delta());
-        if particle.lifetime.finished() {
+    if selected != ui_values.effect {
+        ui_values.effect = selected;
+        for entity in query.iter() {
             commands.entity(entity).despawn_recursive();
         }
+        spawn_emiter(&mut commands, &asset_server, &EFFECTS[selected]);
     }
 }
 
-fn lerp(a: f32, b: f32, t: f32) -> f32 {
-    a * (1.0 - t) + b * t
+// the wind blows on all particles, batched ones too
+fn wind_egui(
+    mut egui_contexts: EguiContexts,
+    mut wind: ResMut<Wind>
+){
+    egui::Window::new("Wind")
+        .default_open(false)
+        .show(egui_contexts.ctx_mut(), |ui|{
+            wind_ui(ui, &mut wind);
+        });
+}
+
+// hands the wind to the particles as their global force
+fn blow_wind(
+    wind: Res<Wind>,
+    mut global_force: ResMut<GlobalForce>
+){
+    global_force.0 = wind.enabled.then(|| {
+        let wind = wind.clone();
+        Arc::new(move |position| wind.force_at(position)) as Arc<dyn Fn(Vec3) -> Vec3 + Send + Sync>
+    });
 }
 
-fn update_particle_alpha(
+// the sparks and fireworks ask for trails behind their particles
+fn attach_trails(
+    mut commands:Commands,
+    query: Query<(Entity, &ParticleTrail), Added<ParticleTrail>>
+){
+    for (entity, settings) in query.iter() {
+        let mode = settings.width.map_or(TrailMode::Line, |width| TrailMode::Ribbon { width });
+        commands.entity(entity).insert(Trail::new(settings.length, settings.color)
+            .with_mode(mode)
+            .with_spacing(settings.spacing));
+    }
+}
+
+fn update_colliders(
+    mut commands:Commands,
+    ui_values: Res<UiValues>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut query: Query<(&Particle,&ParticleAlpha, &MeshMaterial3d<StandardMaterial>),With<ParticleAlpha>>
-) {
-    for (particle, alpha, material) in query.iter_mut() {
-        if let Some( mat) = materials.get_mut(material) {
-            mat.base_color.set_alpha(lerp(alpha.start, alpha.end, particle.get_factor()));
+    query: Query<Entity, With<DemoCollider>>,
+    query_ramp: Query<Entity, With<DemoRamp>>
+){
+    if !ui_values.is_changed() {
+        return;
+    }
+    if ui_values.sphere_collider && query.is_empty() {
+        commands.spawn((
+            Mesh3d(meshes.add(Mesh::from(Sphere { radius: COLLIDER_RADIUS }))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::Srgba(GRAY),
+                ..default()
+            })),
+            Transform::from_xyz(0.0, MIN_LIMIT + COLLIDER_RADIUS + 1.0, 0.0),
+            ParticleCollider::Sphere { radius: COLLIDER_RADIUS },
+            DemoCollider
+        ));
+    }
+    if !ui_values.sphere_collider {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    if ui_values.ramp_collider && query_ramp.is_empty() {
+        commands.spawn((
+            Mesh3d(meshes.add(Mesh::from(Plane3d::default().mesh().size(2.0 * MAX_LIMIT, 2.0 * MAX_LIMIT)))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::Srgba(GRAY),
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            })),
+            Transform::from_xyz(0.0, MIN_LIMIT + 3.0, 0.0)
+                .with_rotation(Quat::from_rotation_z(-0.3)),
+            ParticleCollider::Plane { normal: Vec3::Y },
+            DemoRamp
+        ));
+    }
+    if !ui_values.ramp_collider {
+        for entity in query_ramp.iter() {
+            commands.entity(entity).despawn_recursive();
         }
     }
-}
\ No newline at end of file
+}
+
+// simulates the batched dust effect without a window and prints how long a frame of
+// it takes; the mesh itself is checked by the tests of particle::batch
+fn run_headless() {
+    let bytes = std::fs::read(HEADLESS_EFFECT).expect("effect file");
+    let definition = EmitterDefinition::from_ron(&bytes).expect("effect definition");
+    let mut buffer = ParticleBuffer::default();
+    let delta = 1.0 / 60.0;
+    let mut rate_accumulator = 0.0;
+
+    for frame in 1..=HEADLESS_FRAMES {
+        rate_accumulator += definition.rate * delta;
+        let count = rate_accumulator.floor();
+        rate_accumulator -= count;
+
+        let start = std::time::Instant::now();
+        buffer.emit(&definition, None, &Transform::IDENTITY, Vec3::ZERO, count as u32);
+        buffer.update(&definition, &[], delta);
+        let mesh = buffer.to_mesh(Quat::IDENTITY, definition.billboard.as_ref());
+        let elapsed = start.elapsed();
+
+        if frame % 30 == 0 {
+            println!("frame {frame}: {} particles, {} vertices, {:.1} ms",
+                     buffer.len(), mesh.count_vertices(), elapsed.as_secs_f32() * 1000.0);
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use bevy_egui::egui;
+use rand::Rng;
+
+use std::ops::RangeInclusive;
+
+// A genetic algorithm: every member of a population carries its DNA, the fitness
+// says how well it did. The next generation is bred from parents picked by the
+// selection, their genes mixed by the crossover and changed now and then by mutation.
+// The elite, the best members, are copied unchanged.
+pub trait Dna: Clone {
+    fn crossover(&self, other: &Self, crossover: Crossover, rng: &mut impl Rng) -> Self;
+
+    // every gene changes with the probability rate
+    fn mutate(&mut self, rate: f32, rng: &mut impl Rng);
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Selection {
+    //the chance to be picked is proportional to the fitness
+    Roulette,
+    //the chance to be picked is proportional to the place in the order of fitness
+    Rank,
+    //the fittest of a few members picked at random
+    Tournament(usize)
+}
+
+pub const SELECTIONS: [(Selection, &str); 3] = [
+    (Selection::Roulette, "Roulette"),
+    (Selection::Rank, "Rank"),
+    (Selection::Tournament(3), "Tournament")
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Crossover {
+    //the genes up to a random point from one parent, the rest from the other
+    SinglePoint,
+    //every gene from either parent
+    Uniform
+}
+
+pub const CROSSOVERS: [(Crossover, &str); 2] = [
+    (Crossover::SinglePoint, "Single point"),
+    (Crossover::Uniform, "Uniform")
+];
+
+impl Crossover {
+    pub fn genes<T: Clone>(&self, first: &[T], second: &[T], rng: &mut impl Rng) -> Vec<T> {
+        let length = first.len().min(second.len());
+        match self {
+            Crossover::SinglePoint => {
+                let point = rng.random_range(0..=length);
+                first[..point].iter().chain(second[point..length].iter()).cloned().collect()
+            }
+            Crossover::Uniform => (0..length)
+                .map(|index| if rng.random_bool(0.5) { first[index].clone() } else { second[index].clone() })
+                .collect()
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Statistics {
+    pub generation: usize,
+    pub best: f32,
+    pub mean: f32,
+    pub worst: f32
+}
+
+impl Statistics {
+    pub fn new(generation: usize, fitness: &[f32]) -> Self {
+        if fitness.is_empty() {
+            return Statistics { generation, ..Statistics::default() };
+        }
+        Statistics {
+            generation,
+            best: fitness.iter().copied().fold(f32::MIN, f32::max),
+            mean: fitness.iter().sum::<f32>() / fitness.len() as f32,
+            worst: fitness.iter().copied().fold(f32::MAX, f32::min)
+        }
+    }
+}
+
+pub struct Population<D: Dna> {
+    pub members: Vec<D>,
+    pub generation: usize,
+    pub selection: Selection,
+    pub crossover: Crossover,
+    pub mutation_rate: f32,
+    pub elitism: usize
+}
+
+impl<D: Dna> Population<D> {
+    pub fn new(members: Vec<D>) -> Self {
+        Population {
+            members,
+            generation: 0,
+            selection: Selection::Roulette,
+            crossover: Crossover::SinglePoint,
+            mutation_rate: 0.01,
+            elitism: 1
+        }
+    }
+
+    // a new population with the selection, crossover, mutation rate and elitism of
+    // this one
+    pub fn reseeded(&self, members: Vec<D>) -> Self {
+        Population {
+            members,
+            generation: 0,
+            ..*self
+        }
+    }
+
+    // the index of a parent; negative fitness counts as zero
+    pub fn select(&self, fitness: &[f32], rng: &mut impl Rng) -> usize {
+        let count = fitness.len();
+        match self.selection {
+            Selection::Roulette => {
+                let total: f32 = fitness.iter().map(|value| value.max(0.0)).sum();
+                if total <= 0.0 {
+                    return rng.random_range(0..count);
+                }
+                let mut pick = rng.random_range(0.0..total);
+                for (index, value) in fitness.iter().enumerate() {
+                    pick -= value.max(0.0);
+                    if pick < 0.0 {
+                        return index;
+                    }
+                }
+                count - 1
+            }
+            Selection::Rank => {
+                //the worst has rank 1, the best rank count
+                let order = ranking(fitness);
+                let mut pick = rng.random_range(0..count * (count + 1) / 2);
+                for (rank, index) in order.iter().rev().enumerate() {
+                    if pick <= rank {
+                        return *index;
+                    }
+                    pick -= rank + 1;
+                }
+                order[0]
+            }
+            Selection::Tournament(size) => (0..size.max(1))
+                .map(|_| rng.random_range(0..count))
+                .max_by(|a, b| fitness[*a].total_cmp(&fitness[*b]))
+                .unwrap_or(0)
+        }
+    }
+
+    // replaces the members by their children and returns the statistics of the
+    // generation that was rated
+    pub fn evolve(&mut self, fitness: &[f32], rng: &mut impl Rng) -> Statistics {
+        assert_eq!(fitness.len(), self.members.len(), "one fitness per member");
+        let statistics = Statistics::new(self.generation, fitness);
+
+        //at least one child is bred, else the evolution would stand still
+        let mut children: Vec<D> = ranking(fitness).iter()
+            .take(self.elitism.min(self.members.len().saturating_sub(1)))
+            .map(|index| self.members[*index].clone())
+            .collect();
+        while children.len() < self.members.len() {
+            let first = &self.members[self.select(fitness, rng)];
+            let second = &self.members[self.select(fitness, rng)];
+            let mut child = first.crossover(second, self.crossover, rng);
+            child.mutate(self.mutation_rate, rng);
+            children.push(child);
+        }
+        self.members = children;
+        self.generation += 1;
+        statistics
+    }
+}
+
+// the selection, crossover, mutation rate and elitism; the elite stays below the
+// size of the population
+pub fn population_ui<D: Dna>(ui: &mut egui::Ui, population: &mut Population<D>, mutation_rates: RangeInclusive<f32>) {
+    let selection = SELECTIONS.iter().find(|(selection, _)| *selection == population.selection).map_or("", |(_, name)| name);
+    egui::ComboBox::from_label("Selection")
+        .selected_text(selection)
+        .show_ui(ui, |ui| {
+            for (selection, name) in SELECTIONS {
+                ui.selectable_value(&mut population.selection, selection, name);
+            }
+        });
+    let crossover = CROSSOVERS.iter().find(|(crossover, _)| *crossover == population.crossover).map_or("", |(_, name)| name);
+    egui::ComboBox::from_label("Crossover")
+        .selected_text(crossover)
+        .show_ui(ui, |ui| {
+            for (crossover, name) in CROSSOVERS {
+                ui.selectable_value(&mut population.crossover, crossover, name);
+            }
+        });
+    ui.add(egui::Slider::new(&mut population.mutation_rate, mutation_rates).text("mutation rate"));
+    let elite = 10.min(population.members.len().saturating_sub(1));
+    population.elitism = population.elitism.min(elite);
+    ui.add(egui::Slider::new(&mut population.elitism, 0..=elite).text("elitism"));
+}
+
+// the best and the mean fitness of every generation, squeezed into the width; the
+// fitness axis goes up to max_fitness or else fits the best one
+pub fn fitness_plot(ui: &mut egui::Ui, history: &[Statistics], size: egui::Vec2, max_fitness: Option<f32>) {
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    painter.line_segment([rect.left_bottom(), rect.right_bottom()], egui::Stroke::new(1.0, egui::Color32::GRAY));
+
+    let max_fitness = max_fitness.unwrap_or_else(|| history.iter().fold(f32::EPSILON, |max, statistics| max.max(statistics.best)));
+    let to_screen = |index: usize, value: f32| egui::pos2(
+        rect.left() + index as f32 / history.len().max(2).saturating_sub(1) as f32 * rect.width(),
+        rect.bottom() - value.max(0.0) / max_fitness * rect.height()
+    );
+    let best = history.iter().enumerate().map(|(index, statistics)| to_screen(index, statistics.best)).collect();
+    let mean = history.iter().enumerate().map(|(index, statistics)| to_screen(index, statistics.mean)).collect();
+    painter.add(egui::Shape::line(best, egui::Stroke::new(1.5, egui::Color32::BLUE)));
+    painter.add(egui::Shape::line(mean, egui::Stroke::new(1.5, egui::Color32::DARK_GREEN)));
+}
+
+// the indices from the best to the worst
+pub fn ranking(fitness: &[f32]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..fitness.len()).collect();
+    order.sort_by(|a, b| fitness[*b].total_cmp(&fitness[*a]));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const PICKS: usize = 60_000;
+
+    // a string of bits, the fitness is the number of ones
+    #[derive(Clone)]
+    struct Bits(Vec<bool>);
+
+    impl Dna for Bits {
+        fn crossover(&self, other: &Self, crossover: Crossover, rng: &mut impl Rng) -> Self {
+            Bits(crossover.genes(&self.0, &other.0, rng))
+        }
+
+        fn mutate(&mut self, rate: f32, rng: &mut impl Rng) {
+            for bit in &mut self.0 {
+                if rng.random_bool(rate as f64) {
+                    *bit = !*bit;
+                }
+            }
+        }
+    }
+
+    impl Bits {
+        fn fitness(&self) -> f32 {
+            self.0.iter().filter(|bit| **bit).count() as f32
+        }
+    }
+
+    fn population(size: usize, selection: Selection) -> Population<Bits> {
+        let mut population = Population::new(vec![Bits(vec![false; 8]); size]);
+        population.selection = selection;
+        population
+    }
+
+    // how often each member was picked, as a share of all picks
+    fn shares(selection: Selection, fitness: &[f32]) -> Vec<f32> {
+        let population = population(fitness.len(), selection);
+        let mut rng = StdRng::seed_from_u64(47);
+        let mut counts = vec![0; fitness.len()];
+        for _ in 0..PICKS {
+            counts[population.select(fitness, &mut rng)] += 1;
+        }
+        counts.iter().map(|count| *count as f32 / PICKS as f32).collect()
+    }
+
+    fn assert_shares(shares: &[f32], expected: &[f32]) {
+        for (share, expected) in shares.iter().zip(expected) {
+            assert!((share - expected).abs() < 0.01, "{shares:?} instead of {expected:?}");
+        }
+    }
+
+    #[test]
+    fn roulette_picks_in_proportion_to_the_fitness() {
+        assert_shares(&shares(Selection::Roulette, &[1.0, 3.0, 0.0, 4.0]), &[0.125, 0.375, 0.0, 0.5]);
+        //negative fitness counts as zero
+        assert_shares(&shares(Selection::Roulette, &[-5.0, 1.0]), &[0.0, 1.0]);
+        //without any fitness every member is as likely
+        assert_shares(&shares(Selection::Roulette, &[0.0, 0.0]), &[0.5, 0.5]);
+    }
+
+    #[test]
+    fn rank_picks_in_proportion_to_the_place() {
+        //the ranks 3, 1 and 2 out of 6, however large the differences in fitness
+        assert_shares(&shares(Selection::Rank, &[100.0, -1.0, 5.0]), &[0.5, 1.0 / 6.0, 1.0 / 3.0]);
+    }
+
+    #[test]
+    fn tournament_picks_the_fittest_of_its_members() {
+        //a member wins if it is the fittest of the size picked, so the worst of three
+        //only wins when it is picked every time
+        let worst = 1.0 / 27.0;
+        let middle = 8.0 / 27.0 - worst;
+        assert_shares(&shares(Selection::Tournament(3), &[1.0, 0.0, 2.0]), &[middle, worst, 1.0 - middle - worst]);
+        //a tournament of one is a random pick
+        assert_shares(&shares(Selection::Tournament(1), &[1.0, 0.0]), &[0.5, 0.5]);
+    }
+
+    #[test]
+    fn crossover_takes_every_gene_from_a_parent() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let first = [0; 16];
+        let second = [1; 16];
+        for _ in 0..100 {
+            let genes = Crossover::SinglePoint.genes(&first, &second, &mut rng);
+            assert_eq!(genes.len(), 16);
+            assert!(genes.windows(2).all(|pair| pair[0] <= pair[1]), "one point only: {genes:?}");
+            assert_eq!(Crossover::Uniform.genes(&first, &second, &mut rng).len(), 16);
+        }
+    }
+
+    #[test]
+    fn an_elite_as_large_as_the_population_still_breeds() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let mut population = population(4, Selection::Roulette);
+        population.elitism = 4;
+        population.mutation_rate = 1.0;
+        let fitness = [1.0; 4];
+        population.evolve(&fitness, &mut rng);
+        //the child of all-false parents with every bit flipped
+        assert!(population.members.iter().any(|member| member.fitness() == 8.0));
+    }
+
+    #[test]
+    fn a_reseeded_population_keeps_the_settings() {
+        let mut old = population(10, Selection::Tournament(3));
+        old.crossover = Crossover::Uniform;
+        old.mutation_rate = 0.2;
+        old.elitism = 3;
+        old.generation = 12;
+        let new = old.reseeded(vec![Bits(vec![true; 8]); 5]);
+        assert_eq!((new.selection, new.crossover, new.mutation_rate, new.elitism), (Selection::Tournament(3), Crossover::Uniform, 0.2, 3));
+        assert_eq!((new.members.len(), new.generation), (5, 0));
+    }
+
+    #[test]
+    fn evolution_keeps_the_elite_and_improves() {
+        let mut rng = StdRng::seed_from_u64(47);
+        for (selection, name) in SELECTIONS {
+            let mut population = population(30, selection);
+            population.members[0] = Bits(vec![true; 8]);
+            population.mutation_rate = 0.05;
+            let mut first = None;
+            let mut last = Statistics::default();
+            for _ in 0..40 {
+                let fitness: Vec<f32> = population.members.iter().map(Bits::fitness).collect();
+                last = population.evolve(&fitness, &mut rng);
+                first.get_or_insert(last);
+                assert_eq!(last.best, 8.0, "{name}: the elite survives");
+            }
+            assert_eq!(last.generation, 39);
+            assert!(last.mean > first.expect("generations").mean + 2.0, "{name}: {last:?}");
+        }
+    }
+}
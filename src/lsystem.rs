@@ -0,0 +1,285 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use rand::Rng;
+
+use std::f32::consts::{PI, TAU};
+
+// Lindenmayer systems: starting with the axiom every symbol is replaced by its
+// production in each iteration. A symbol with several productions picks one at random,
+// weighted. Symbols without a production stay as they are.
+pub struct Production {
+    pub symbol: char,
+    pub replacement: String,
+    pub weight: f32
+}
+
+pub struct LSystem {
+    pub axiom: String,
+    pub productions: Vec<Production>
+}
+
+impl LSystem {
+    pub fn new(axiom: &str) -> Self {
+        LSystem {
+            axiom: axiom.to_string(),
+            productions: Vec::new()
+        }
+    }
+
+    // a weight only matters between rules of the same symbol
+    pub fn with_rule(mut self, symbol: char, replacement: &str, weight: f32) -> Self {
+        self.productions.push(Production {
+            symbol,
+            replacement: replacement.to_string(),
+            weight
+        });
+        self
+    }
+
+    fn replace(&self, symbol: char, rng: &mut impl Rng) -> Option<&str> {
+        let candidates: Vec<&Production> = self.productions.iter().filter(|production| production.symbol == symbol).collect();
+        let total: f32 = candidates.iter().map(|production| production.weight).sum();
+        if candidates.is_empty() || total <= 0.0 {
+            return None;
+        }
+        let mut pick = rng.random_range(0.0..total);
+        for production in &candidates {
+            if pick < production.weight {
+                return Some(&production.replacement);
+            }
+            pick -= production.weight;
+        }
+        candidates.last().map(|production| production.replacement.as_str())
+    }
+
+    pub fn generate(&self, iterations: u32, rng: &mut impl Rng) -> String {
+        let mut sentence = self.axiom.clone();
+        for _ in 0..iterations {
+            let mut next = String::with_capacity(sentence.len() * 2);
+            for symbol in sentence.chars() {
+                match self.replace(symbol, rng) {
+                    Some(replacement) => next.push_str(replacement),
+                    None => next.push(symbol)
+                }
+            }
+            sentence = next;
+        }
+        sentence
+    }
+}
+
+pub struct Segment {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub width: f32
+}
+
+// Turtle graphics in 3D with the symbols of "The Algorithmic Beauty of Plants":
+// F draws a step forward, f moves without drawing, + and - turn left and right,
+// & and ^ pitch down and up, \ and / roll left and right, | turns around, [ and ]
+// push and pop the state. Every branch is width_decay times thinner than its parent.
+pub struct Turtle {
+    pub step: f32,
+    //radians
+    pub angle: f32,
+    pub width: f32,
+    pub width_decay: f32
+}
+
+impl Default for Turtle {
+    fn default() -> Self {
+        Turtle {
+            step: 1.0,
+            angle: PI / 6.0,
+            width: 0.1,
+            width_decay: 0.7
+        }
+    }
+}
+
+impl Turtle {
+    // the turtle starts at the origin heading up; its up axis points to the viewer,
+    // so turns stay in the xy plane
+    pub fn interpret(&self, sentence: &str) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut position = Vec3::ZERO;
+        //heading along y, yaw turns around z, pitch around x and roll around y
+        let mut orientation = Quat::IDENTITY;
+        let mut width = self.width;
+        let mut stack = Vec::new();
+
+        for symbol in sentence.chars() {
+            match symbol {
+                'F' | 'f' => {
+                    let end = position + orientation * Vec3::Y * self.step;
+                    if symbol == 'F' {
+                        segments.push(Segment { start: position, end, width });
+                    }
+                    position = end;
+                }
+                '+' => orientation *= Quat::from_rotation_z(self.angle),
+                '-' => orientation *= Quat::from_rotation_z(-self.angle),
+                '&' => orientation *= Quat::from_rotation_x(self.angle),
+                '^' => orientation *= Quat::from_rotation_x(-self.angle),
+                '\\' => orientation *= Quat::from_rotation_y(self.angle),
+                '/' => orientation *= Quat::from_rotation_y(-self.angle),
+                '|' => orientation *= Quat::from_rotation_z(PI),
+                '[' => {
+                    stack.push((position, orientation, width));
+                    width *= self.width_decay;
+                }
+                ']' => {
+                    if let Some(state) = stack.pop() {
+                        (position, orientation, width) = state;
+                    }
+                }
+                _ => {}
+            }
+        }
+        segments
+    }
+}
+
+// scales and moves the segments so their bounds fit into a cube of size centered
+// at the origin
+pub fn fit(segments: &mut [Segment], size: f32) {
+    let (min, max) = segments.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), segment| {
+        (min.min(segment.start).min(segment.end), max.max(segment.start).max(segment.end))
+    });
+    let extent = (max - min).max_element();
+    if segments.is_empty() || extent == 0.0 {
+        return;
+    }
+    let scale = size / extent;
+    let center = (min + max) / 2.0;
+    for segment in segments.iter_mut() {
+        segment.start = (segment.start - center) * scale;
+        segment.end = (segment.end - center) * scale;
+        segment.width *= scale;
+    }
+}
+
+// the segments as lines, like the limit cube
+pub fn line_mesh(segments: &[Segment]) -> Mesh {
+    let positions: Vec<[f32; 3]> = segments.iter()
+        .flat_map(|segment| [segment.start.to_array(), segment.end.to_array()])
+        .collect();
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let indices = (0..positions.len() as u32).collect();
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::RENDER_WORLD);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh
+}
+
+// every segment as an open tube with sides faces and its width as diameter
+pub fn tube_mesh(segments: &[Segment], sides: u32) -> Mesh {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+
+    for segment in segments {
+        let direction = (segment.end - segment.start).normalize_or(Vec3::Y);
+        let rotation = Quat::from_rotation_arc(Vec3::Y, direction);
+        let first = positions.len() as u32;
+        for side in 0..sides {
+            let angle = side as f32 / sides as f32 * TAU;
+            let normal = rotation * Vec3::new(angle.cos(), 0.0, angle.sin());
+            let offset = normal * segment.width / 2.0;
+            positions.push((segment.start + offset).to_array());
+            positions.push((segment.end + offset).to_array());
+            normals.push(normal.to_array());
+            normals.push(normal.to_array());
+        }
+        for side in 0..sides {
+            let bottom = first + side * 2;
+            let next = first + (side + 1) % sides * 2;
+            indices.extend_from_slice(&[bottom, bottom + 1, next, next, bottom + 1, next + 1]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn koch() -> LSystem {
+        LSystem::new("F--F--F").with_rule('F', "F+F--F+F", 1.0)
+    }
+
+    #[test]
+    fn the_koch_sentence_grows_by_four() {
+        let mut rng = StdRng::seed_from_u64(46);
+        for iterations in 0..5 {
+            //3 * 4^n steps and 4^(n + 1) turns
+            let sentence = koch().generate(iterations, &mut rng);
+            assert_eq!(sentence.len(), 7 * 4usize.pow(iterations));
+            assert_eq!(sentence.matches('F').count(), 3 * 4usize.pow(iterations));
+        }
+    }
+
+    #[test]
+    fn symbols_without_a_rule_stay() {
+        let mut rng = StdRng::seed_from_u64(46);
+        let lsystem = LSystem::new("AB").with_rule('A', "AB", 1.0);
+        assert_eq!(lsystem.generate(3, &mut rng), "ABBBB");
+    }
+
+    #[test]
+    fn a_seeded_stochastic_system_repeats() {
+        let plant = LSystem::new("X")
+            .with_rule('X', "F+[[X]-X]-F[-FX]+X", 1.0)
+            .with_rule('X', "F/[[X]&X]^F[&FX]\\X", 1.0);
+        let first = plant.generate(4, &mut StdRng::seed_from_u64(46));
+        let second = plant.generate(4, &mut StdRng::seed_from_u64(46));
+        assert_eq!(first, second);
+        assert!(first.contains('+') && first.contains('/'));
+    }
+
+    #[test]
+    fn brackets_restore_the_turtle() {
+        let turtle = Turtle {
+            angle: PI / 2.0,
+            ..default()
+        };
+        //an unmatched ] leaves the turtle as it is
+        let segments = turtle.interpret("F[+F]]F");
+        assert_eq!(segments.len(), 3);
+        assert!(segments[1].start.abs_diff_eq(Vec3::Y, 1e-6));
+        assert!(segments[1].end.abs_diff_eq(Vec3::new(-1.0, 1.0, 0.0), 1e-6));
+        assert!((segments[1].width - turtle.width * turtle.width_decay).abs() < 1e-6);
+        //after the branch the turtle goes on up from where it opened it
+        assert!(segments[2].start.abs_diff_eq(Vec3::Y, 1e-6));
+        assert!(segments[2].end.abs_diff_eq(Vec3::Y * 2.0, 1e-6));
+        assert_eq!(segments[2].width, turtle.width);
+    }
+
+    #[test]
+    fn fit_centers_the_segments_in_the_cube() {
+        let sentence = koch().generate(3, &mut StdRng::seed_from_u64(46));
+        let turtle = Turtle {
+            angle: PI / 3.0,
+            ..default()
+        };
+        let mut segments = turtle.interpret(&sentence);
+        fit(&mut segments, 10.0);
+        let (min, max) = segments.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), segment| {
+            (min.min(segment.start).min(segment.end), max.max(segment.start).max(segment.end))
+        });
+        assert!(((max - min).max_element() - 10.0).abs() < 1e-3);
+        assert!((min + max).abs().max_element() < 1e-3);
+        //nothing to fit
+        fit(&mut [], 10.0);
+    }
+}
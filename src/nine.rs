@@ -0,0 +1,542 @@
+use bevy::color::palettes::basic::{BLUE, GRAY, GREEN, RED, YELLOW};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use ga::{Crossover, Dna, Population, Statistics};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::f32::consts::PI;
+
+mod orbitcamera;
+mod mesh;
+mod ga;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 8.0;
+const MIN_LIMIT: f32 = -8.0;
+
+//steps of a generation, every gene is a force for GENE_STEPS of them
+const LIFETIME: usize = 400;
+const GENE_STEPS: usize = 10;
+//a fixed time step, so a generation flies the same with and without window
+const DELTA: f32 = 1.0 / 60.0;
+
+const MAX_SPEED: f32 = 12.0;
+const MAX_FORCE: f32 = 24.0;
+
+const START: Vec3 = Vec3::new(0.0, MIN_LIMIT + 1.0, 0.0);
+const TARGET: Vec3 = Vec3::new(0.0, MAX_LIMIT - 2.0, 0.0);
+const TARGET_RADIUS: f32 = 1.0;
+
+const PLOT_WIDTH: f32 = 280.0;
+const PLOT_HEIGHT: f32 = 140.0;
+
+const HEADLESS_SEED: u64 = 9;
+
+// a box the rockets crash into
+struct Obstacle {
+    center: Vec3,
+    half_extents: Vec3
+}
+
+impl Obstacle {
+    fn contains(&self, position: Vec3) -> bool {
+        ((position - self.center).abs() - self.half_extents).max_element() < 0.0
+    }
+}
+
+// the rockets have to fly around the plate or through the hole in the wall on their
+// way up
+const LAYOUTS: [(&str, &[Obstacle]); 3] = [
+    ("Open", &[]),
+    ("Plate", &[Obstacle { center: Vec3::ZERO, half_extents: Vec3::new(5.0, 0.5, 5.0) }]),
+    ("Wall", &[
+        Obstacle { center: Vec3::new(-3.25, 0.0, 0.0), half_extents: Vec3::new(4.75, 0.5, 8.0) },
+        Obstacle { center: Vec3::new(6.25, 0.0, 0.0), half_extents: Vec3::new(1.75, 0.5, 8.0) },
+        Obstacle { center: Vec3::new(3.0, 0.0, -4.75), half_extents: Vec3::new(1.5, 0.5, 3.25) },
+        Obstacle { center: Vec3::new(3.0, 0.0, 4.75), half_extents: Vec3::new(1.5, 0.5, 3.25) }
+    ])
+];
+
+#[derive(Resource)]
+struct UiValues{
+    restart: bool,
+    running: bool,
+    layout: usize,
+    population: usize,
+    steps_per_frame: usize
+}
+
+// the forces a rocket fires one after the other
+#[derive(Clone)]
+struct RocketDna {
+    forces: Vec<Vec3>
+}
+
+fn random_force(rng: &mut impl Rng) -> Vec3 {
+    let direction = Vec3::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0));
+    direction.normalize_or(Vec3::Y) * rng.random_range(0.0..MAX_FORCE)
+}
+
+impl RocketDna {
+    fn random(rng: &mut impl Rng) -> Self {
+        RocketDna {
+            forces: (0..LIFETIME / GENE_STEPS).map(|_| random_force(rng)).collect()
+        }
+    }
+}
+
+impl Dna for RocketDna {
+    fn crossover(&self, other: &Self, crossover: Crossover, rng: &mut impl Rng) -> Self {
+        RocketDna {
+            forces: crossover.genes(&self.forces, &other.forces, rng)
+        }
+    }
+
+    fn mutate(&mut self, rate: f32, rng: &mut impl Rng) {
+        for force in self.forces.iter_mut() {
+            if rng.random_bool(rate as f64) {
+                *force = random_force(rng);
+            }
+        }
+    }
+}
+
+struct Moveable {
+    velocity: Vec3,
+    acceleration: Vec3,
+    maximum_speed: f32,
+    maximum_force: f32
+}
+
+impl Default for Moveable {
+    fn default() -> Self {
+        Moveable {
+            velocity: Vec3::ZERO,
+            acceleration: Vec3::ZERO,
+            maximum_speed: MAX_SPEED,
+            maximum_force: MAX_FORCE
+        }
+    }
+}
+
+impl Moveable {
+    fn apply_force(&mut self, force: Vec3) {
+        self.acceleration += force.clamp_length_max(self.maximum_force);
+    }
+
+    // the new position after one step
+    fn update(&mut self, position: Vec3) -> Vec3 {
+        self.velocity = (self.velocity + self.acceleration * DELTA).clamp_length_max(self.maximum_speed);
+        self.acceleration = Vec3::ZERO;
+        position + self.velocity * DELTA
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Flight {
+    Flying,
+    Crashed,
+    //the step it reached the target
+    Arrived(usize)
+}
+
+struct Rocket {
+    position: Vec3,
+    moveable: Moveable,
+    flight: Flight,
+    //the closest distance to the target on the way
+    closest: f32
+}
+
+impl Rocket {
+    fn new() -> Self {
+        Rocket {
+            position: START,
+            moveable: Moveable::default(),
+            flight: Flight::Flying,
+            closest: START.distance(TARGET)
+        }
+    }
+
+    fn step(&mut self, dna: &RocketDna, step: usize, obstacles: &[Obstacle]) {
+        if self.flight != Flight::Flying {
+            return;
+        }
+        self.moveable.apply_force(dna.forces[step / GENE_STEPS]);
+        self.position = self.moveable.update(self.position);
+        let distance = self.position.distance(TARGET);
+        self.closest = self.closest.min(distance);
+        if distance < TARGET_RADIUS {
+            self.flight = Flight::Arrived(step);
+        } else if self.position.abs().max_element() > MAX_LIMIT
+            || obstacles.iter().any(|obstacle| obstacle.contains(self.position)) {
+            self.flight = Flight::Crashed;
+        }
+    }
+
+    // close is good, arriving early is better and crashing is bad
+    fn fitness(&self) -> f32 {
+        match self.flight {
+            Flight::Arrived(step) => 2.0 * (2.0 - step as f32 / LIFETIME as f32),
+            Flight::Flying => 1.0 / (1.0 + self.closest).powi(2),
+            Flight::Crashed => 0.1 / (1.0 + self.closest).powi(2)
+        }
+    }
+}
+
+// the population with the flights of its current generation
+#[derive(Resource)]
+struct Evolution {
+    population: Population<RocketDna>,
+    rockets: Vec<Rocket>,
+    layout: usize,
+    step: usize,
+    history: Vec<Statistics>,
+    //rockets of the last generation that reached the target
+    arrived: usize
+}
+
+impl Evolution {
+    fn new(size: usize, layout: usize, rng: &mut impl Rng) -> Self {
+        let mut population = Population::new((0..size).map(|_| RocketDna::random(rng)).collect());
+        population.elitism = 2;
+        Evolution {
+            population,
+            rockets: (0..size).map(|_| Rocket::new()).collect(),
+            layout,
+            step: 0,
+            history: Vec::new(),
+            arrived: 0
+        }
+    }
+
+    // one step of all rockets, at the end of their lifetime the next generation starts
+    fn step(&mut self, rng: &mut impl Rng) {
+        let obstacles = LAYOUTS[self.layout].1;
+        for (rocket, dna) in self.rockets.iter_mut().zip(self.population.members.iter()) {
+            rocket.step(dna, self.step, obstacles);
+        }
+        self.step += 1;
+        if self.step < LIFETIME && self.rockets.iter().any(|rocket| rocket.flight == Flight::Flying) {
+            return;
+        }
+        let fitness: Vec<f32> = self.rockets.iter().map(Rocket::fitness).collect();
+        self.arrived = self.rockets.iter().filter(|rocket| matches!(rocket.flight, Flight::Arrived(_))).count();
+        self.history.push(self.population.evolve(&fitness, rng));
+        self.rockets = (0..self.population.members.len()).map(|_| Rocket::new()).collect();
+        self.step = 0;
+    }
+
+    fn generation(&mut self, rng: &mut impl Rng) -> Statistics {
+        let generation = self.population.generation;
+        while self.population.generation == generation {
+            self.step(rng);
+        }
+        self.history[self.history.len() - 1]
+    }
+}
+
+#[derive(Component)]
+struct RocketScene;
+
+#[derive(Component)]
+struct RocketShip(usize);
+
+#[derive(Resource)]
+struct RocketMaterials {
+    flying: Handle<StandardMaterial>,
+    crashed: Handle<StandardMaterial>,
+    arrived: Handle<StandardMaterial>
+}
+
+fn main() {
+    //cargo run --example nine -- --headless
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless();
+        return;
+    }
+
+    let values = UiValues{
+        restart: true,
+        running: true,
+        layout: 1,
+        population: 100,
+        steps_per_frame: 1
+    };
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(Evolution::new(values.population, values.layout, &mut rand::rng()))
+        .insert_resource(values)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 9".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              statistics_egui,
+                              restart,
+                              evolve,
+                              update_rockets).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+    let mut material = |color| materials.add(StandardMaterial {
+        base_color: Color::Srgba(color),
+        ..default()
+    });
+    commands.insert_resource(RocketMaterials {
+        flying: material(BLUE),
+        crashed: material(GRAY),
+        arrived: material(GREEN)
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 28.0,
+            ..default()
+        }
+    ));
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut evolution: ResMut<Evolution>,
+    rocket_materials: Res<RocketMaterials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<RocketScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    //a new population that keeps the settings of the old one
+    let mut next = Evolution::new(ui_values.population, ui_values.layout, &mut rand::rng());
+    next.population = evolution.population.reseeded(next.population.members);
+    *evolution = next;
+
+    let rocket = meshes.add(Mesh::from(Cone { radius: 0.15, height: 0.6 }));
+    for index in 0..ui_values.population {
+        commands.spawn((
+            Mesh3d(rocket.clone()),
+            MeshMaterial3d(rocket_materials.flying.clone()),
+            Transform::from_translation(START),
+            RocketShip(index),
+            RocketScene
+        ));
+    }
+    commands.spawn((
+        Mesh3d(meshes.add(Mesh::from(Sphere { radius: TARGET_RADIUS }))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::Srgba(YELLOW),
+            ..default()
+        })),
+        Transform::from_translation(TARGET),
+        RocketScene
+    ));
+    let obstacle_material = materials.add(StandardMaterial {
+        base_color: Color::Srgba(RED).with_alpha(0.5),
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+    for obstacle in LAYOUTS[ui_values.layout].1 {
+        commands.spawn((
+            Mesh3d(meshes.add(Mesh::from(Cuboid { half_size: obstacle.half_extents }))),
+            MeshMaterial3d(obstacle_material.clone()),
+            Transform::from_translation(obstacle.center),
+            RocketScene
+        ));
+    }
+}
+
+fn evolve(
+    ui_values: Res<UiValues>,
+    mut evolution: ResMut<Evolution>
+){
+    if !ui_values.running {
+        return;
+    }
+    let mut rng = rand::rng();
+    for _ in 0..ui_values.steps_per_frame {
+        evolution.step(&mut rng);
+    }
+}
+
+fn update_rockets(
+    evolution: Res<Evolution>,
+    rocket_materials: Res<RocketMaterials>,
+    mut query: Query<(&mut Transform, &mut MeshMaterial3d<StandardMaterial>, &RocketShip)>
+){
+    for (mut transform, mut material, ship) in query.iter_mut() {
+        let Some(rocket) = evolution.rockets.get(ship.0) else {
+            continue;
+        };
+        transform.translation = rocket.position;
+        //the cone points along the velocity
+        if rocket.moveable.velocity.length_squared() > 0.0 {
+            transform.rotation = Quat::from_rotation_arc(Vec3::Y, rocket.moveable.velocity.normalize());
+        }
+        let handle = match rocket.flight {
+            Flight::Flying => &rocket_materials.flying,
+            Flight::Crashed => &rocket_materials.crashed,
+            Flight::Arrived(_) => &rocket_materials.arrived
+        };
+        if material.0 != *handle {
+            material.0 = handle.clone();
+        }
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    mut evolution: ResMut<Evolution>
+){
+    let mut layout = ui_values.layout;
+    let mut population = ui_values.population;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Obstacles")
+            .selected_text(LAYOUTS[layout].0)
+            .show_ui(ui, |ui| {
+                for (index, (name, _)) in LAYOUTS.iter().enumerate() {
+                    ui.selectable_value(&mut layout, index, *name);
+                }
+            });
+        ui.add(egui::Slider::new(&mut population, 10..=300).text("population"));
+
+        ga::population_ui(ui, &mut evolution.population, 0.0..=0.1);
+
+        ui.add(egui::Slider::new(&mut ui_values.steps_per_frame, 1..=50).text("steps per frame"));
+        ui.horizontal(|ui| {
+            let label = if ui_values.running { "Pause" } else { "Run" };
+            if ui.button(label).clicked() {
+                ui_values.running = !ui_values.running;
+            }
+            if ui.button("Restart").clicked() {
+                ui_values.restart = true;
+            }
+        });
+    });
+    if layout != ui_values.layout || population != ui_values.population {
+        ui_values.layout = layout;
+        ui_values.population = population;
+        ui_values.restart = true;
+    }
+}
+
+fn statistics_egui(
+    mut egui_contexts: EguiContexts,
+    evolution: Res<Evolution>
+){
+    egui::Window::new("Generations")
+        .default_pos(egui::pos2(WIDTH - PLOT_WIDTH - 40.0, 20.0))
+        .show(egui_contexts.ctx_mut(), |ui|{
+            ui.label(format!("generation: {}", evolution.population.generation));
+            ui.add(egui::ProgressBar::new(evolution.step as f32 / LIFETIME as f32).text("lifetime"));
+            let flying = evolution.rockets.iter().filter(|rocket| rocket.flight == Flight::Flying).count();
+            ui.label(format!("flying: {flying}"));
+            if let Some(last) = evolution.history.last() {
+                ui.label(format!("arrived: {} of {}", evolution.arrived, evolution.rockets.len()));
+                ui.label(format!("fitness best: {:.3}  mean: {:.3}  worst: {:.3}", last.best, last.mean, last.worst));
+            }
+            ga::fitness_plot(ui, &evolution.history, egui::vec2(PLOT_WIDTH, PLOT_HEIGHT), None);
+            ui.colored_label(egui::Color32::BLUE, "best");
+            ui.colored_label(egui::Color32::DARK_GREEN, "mean");
+        });
+}
+
+// evolves the rockets without a window, the same way every run, and prints how the
+// fitness grows
+fn run_headless() {
+    const GENERATIONS: usize = 150;
+    let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+    for (layout, (name, _)) in LAYOUTS.iter().enumerate() {
+        let mut evolution = Evolution::new(100, layout, &mut rng);
+        let first = evolution.generation(&mut rng);
+        let mut last = first;
+        let mut arrived = 0;
+        for _ in 1..GENERATIONS {
+            last = evolution.generation(&mut rng);
+            arrived = arrived.max(evolution.arrived);
+            if last.generation % 25 == 0 {
+                println!("{name} generation {:>3}: best {:.3} mean {:.3} arrived {}",
+                         last.generation, last.best, last.mean, evolution.arrived);
+            }
+        }
+        println!("{name}: mean fitness {:.3} -> {:.3}, at most {arrived} arrived", first.mean, last.mean);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rockets_learn_to_reach_the_target() {
+        let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+        let mut evolution = Evolution::new(100, 0, &mut rng);
+        let first = evolution.generation(&mut rng);
+        let mut last = first;
+        for _ in 0..30 {
+            last = evolution.generation(&mut rng);
+        }
+        assert!(last.mean > first.mean * 100.0, "{first:?} -> {last:?}");
+        assert!(evolution.arrived > 0);
+    }
+
+    #[test]
+    fn obstacles_hold_their_inside_only() {
+        let plate = &LAYOUTS[1].1[0];
+        assert!(plate.contains(Vec3::ZERO));
+        assert!(!plate.contains(Vec3::new(0.0, 1.0, 0.0)));
+        assert!(!plate.contains(Vec3::new(5.5, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,724 @@
+use bevy::color::palettes::basic::{BLUE, GREEN, RED};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::collections::{HashMap, HashSet};
+
+mod orbitcamera;
+mod mesh;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 16.0;
+const MIN_LIMIT: f32 = -16.0;
+
+const DELTA: f32 = 1.0 / 30.0;
+
+// name and range of every gene, the genes themselves lie in 0..1
+const GENES: [(&str, f32, f32); 2] = [
+    ("size", 0.3, 1.5),
+    ("speed", 1.0, 10.0)
+];
+
+//a bloop smells food in this distance and eats it when it touches it with its body
+const SENSE: f32 = 6.0;
+const FOOD_ENERGY: f32 = 1.0;
+const FOOD_MAX: usize = 300;
+
+//health per second for living and for moving; the moving costs grow with the
+//mass (size³) and the square of the speed, like the kinetic energy
+const LIVING_COST: f32 = 0.3;
+const MOVING_COST: f32 = 0.008;
+
+//a bloop with this health splits into two with half of it each
+const BIRTH_HEALTH: f32 = 1.0;
+const SPLIT_HEALTH: f32 = 2.0;
+
+//how far the wander direction turns each step
+const WANDER: f32 = 0.3;
+
+//one census per second
+const CENSUS_STEPS: usize = 30;
+const BINS: usize = 10;
+
+const PLOT_WIDTH: f32 = 280.0;
+const PLOT_HEIGHT: f32 = 120.0;
+
+const HEADLESS_SEED: u64 = 50;
+
+#[derive(Resource)]
+struct UiValues{
+    restart: bool,
+    running: bool,
+    population: usize,
+    steps_per_frame: usize
+}
+
+#[derive(Clone, Copy)]
+struct BloopDna {
+    genes: [f32; 2]
+}
+
+impl BloopDna {
+    fn random(rng: &mut impl Rng) -> Self {
+        BloopDna {
+            genes: std::array::from_fn(|_| rng.random_range(0.0..1.0))
+        }
+    }
+
+    fn gene(&self, index: usize) -> f32 {
+        GENES[index].1 + self.genes[index] * (GENES[index].2 - GENES[index].1)
+    }
+
+    // the radius of the body, the scale of its mesh
+    fn size(&self) -> f32 {
+        self.gene(0)
+    }
+
+    fn maximum_speed(&self) -> f32 {
+        self.gene(1)
+    }
+
+    //bloops reproduce asexually, there is no crossover, only mutation
+    fn mutate(&mut self, rate: f32, rng: &mut impl Rng) {
+        for gene in self.genes.iter_mut() {
+            if rng.random_bool(rate as f64) {
+                *gene = (*gene + rng.random_range(-0.1..0.1)).clamp(0.0, 1.0);
+            }
+        }
+    }
+}
+
+struct Moveable {
+    velocity: Vec3,
+    maximum_speed: f32,
+    maximum_force: f32
+}
+
+impl Moveable {
+    fn seek(&self, target: Vec3, position: Vec3) -> Vec3 {
+        //steering force = desired velocity - velocity
+        let desired_velocity = (target - position).normalize_or_zero() * self.maximum_speed;
+        (desired_velocity - self.velocity).clamp_length_max(self.maximum_force)
+    }
+
+    // the new position after one step, bouncing off the limit cube
+    fn update(&mut self, position: Vec3, force: Vec3) -> Vec3 {
+        self.velocity = (self.velocity + force * DELTA).clamp_length_max(self.maximum_speed);
+        for axis in 0..3 {
+            if (position[axis] > MAX_LIMIT && self.velocity[axis] > 0.0)
+                || (position[axis] < MIN_LIMIT && self.velocity[axis] < 0.0) {
+                self.velocity[axis] *= -1.0;
+            }
+        }
+        position + self.velocity * DELTA
+    }
+}
+
+struct Bloop {
+    //stays the same for the whole life, the entity of the bloop is found by it
+    id: u64,
+    position: Vec3,
+    moveable: Moveable,
+    //the direction it wanders in without food in sight
+    wander: Vec3,
+    dna: BloopDna,
+    health: f32,
+    //of its lineage, the first bloops are generation 0
+    generation: usize
+}
+
+impl Bloop {
+    fn new(id: u64, position: Vec3, dna: BloopDna, generation: usize, rng: &mut impl Rng) -> Self {
+        let wander = random_vector(rng).normalize_or(Vec3::X);
+        Bloop {
+            id,
+            position,
+            moveable: Moveable {
+                velocity: wander * dna.maximum_speed(),
+                maximum_speed: dna.maximum_speed(),
+                maximum_force: dna.maximum_speed() * 4.0
+            },
+            wander,
+            dna,
+            health: BIRTH_HEALTH,
+            generation
+        }
+    }
+}
+
+//the food lies in a grid of cells as wide as the sense distance, a bloop only
+//searches the cells around its own
+const FOOD_CELLS: usize = ((MAX_LIMIT - MIN_LIMIT) / SENSE) as usize + 1;
+
+struct Food {
+    cells: Vec<Vec<Vec3>>
+}
+
+impl Food {
+    fn new() -> Self {
+        Food {
+            cells: vec![Vec::new(); FOOD_CELLS * FOOD_CELLS * FOOD_CELLS]
+        }
+    }
+
+    fn cell(position: Vec3) -> [usize; 3] {
+        ((position - MIN_LIMIT) / SENSE).to_array().map(|value| (value.max(0.0) as usize).min(FOOD_CELLS - 1))
+    }
+
+    fn add(&mut self, position: Vec3) {
+        let [x, y, z] = Food::cell(position);
+        self.cells[(x * FOOD_CELLS + y) * FOOD_CELLS + z].push(position);
+    }
+
+    // the indices of the cell of the position and its neighbours
+    fn around(position: Vec3) -> impl Iterator<Item = usize> {
+        let [x, y, z] = Food::cell(position);
+        let range = |value: usize| value.saturating_sub(1)..=(value + 1).min(FOOD_CELLS - 1);
+        range(x).flat_map(move |x| range(y).flat_map(move |y| range(z).map(move |z| (x * FOOD_CELLS + y) * FOOD_CELLS + z)))
+    }
+
+    fn closest(&self, position: Vec3) -> Option<Vec3> {
+        Food::around(position)
+            .flat_map(|cell| self.cells[cell].iter())
+            .map(|food| (*food, food.distance_squared(position)))
+            .filter(|(_, distance)| *distance < SENSE * SENSE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(food, _)| food)
+    }
+
+    // removes the food within the radius and returns how much it was
+    fn eat(&mut self, position: Vec3, radius: f32) -> usize {
+        let mut eaten = 0;
+        for cell in Food::around(position) {
+            let count = self.cells[cell].len();
+            self.cells[cell].retain(|food| food.distance(position) > radius);
+            eaten += count - self.cells[cell].len();
+        }
+        eaten
+    }
+
+    fn len(&self) -> usize {
+        self.cells.iter().map(Vec::len).sum()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Vec3> {
+        self.cells.iter().flatten()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Census {
+    population: usize,
+    food: usize,
+    //mean genes in 0..1
+    size: f32,
+    speed: f32
+}
+
+#[derive(Resource)]
+struct Ecosystem {
+    bloops: Vec<Bloop>,
+    food: Food,
+    //new food per second
+    food_rate: f32,
+    mutation_rate: f32,
+    next_id: u64,
+    step: usize,
+    //the fraction of the next piece of food
+    growing: f32,
+    history: Vec<Census>
+}
+
+impl Ecosystem {
+    fn new(population: usize, rng: &mut impl Rng) -> Self {
+        let mut ecosystem = Ecosystem {
+            bloops: Vec::new(),
+            food: Food::new(),
+            food_rate: 40.0,
+            mutation_rate: 0.1,
+            next_id: 0,
+            step: 0,
+            growing: 0.0,
+            history: Vec::new()
+        };
+        for _ in 0..FOOD_MAX {
+            ecosystem.food.add(random_position(rng));
+        }
+        for _ in 0..population {
+            let dna = BloopDna::random(rng);
+            let bloop = Bloop::new(ecosystem.next_id, random_position(rng), dna, 0, rng);
+            ecosystem.bloops.push(bloop);
+            ecosystem.next_id += 1;
+        }
+        ecosystem
+    }
+
+    fn step(&mut self, rng: &mut impl Rng) {
+        self.growing += self.food_rate * DELTA;
+        while self.growing >= 1.0 {
+            self.growing -= 1.0;
+            if self.food.len() < FOOD_MAX {
+                self.food.add(random_position(rng));
+            }
+        }
+
+        for bloop in self.bloops.iter_mut() {
+            //the closest food in sight, else wander
+            let force = match self.food.closest(bloop.position) {
+                Some(food) => bloop.moveable.seek(food, bloop.position),
+                None => {
+                    bloop.wander = (bloop.wander + random_vector(rng) * WANDER).normalize_or(bloop.wander);
+                    bloop.moveable.seek(bloop.position + bloop.wander, bloop.position)
+                }
+            };
+            bloop.position = bloop.moveable.update(bloop.position, force);
+
+            let size = bloop.dna.size();
+            bloop.health -= (LIVING_COST + MOVING_COST * size.powi(3) * bloop.moveable.velocity.length_squared()) * DELTA;
+            bloop.health += self.food.eat(bloop.position, size) as f32 * FOOD_ENERGY;
+        }
+
+        //asexual reproduction: a healthy bloop splits, the child gets a mutated copy of its dna
+        let mut children = Vec::new();
+        for bloop in self.bloops.iter_mut().filter(|bloop| bloop.health >= SPLIT_HEALTH) {
+            bloop.health /= 2.0;
+            let mut dna = bloop.dna;
+            dna.mutate(self.mutation_rate, rng);
+            let mut child = Bloop::new(self.next_id, bloop.position, dna, bloop.generation + 1, rng);
+            child.health = bloop.health;
+            children.push(child);
+            self.next_id += 1;
+        }
+        self.bloops.extend(children);
+        self.bloops.retain(|bloop| bloop.health > 0.0);
+
+        self.step += 1;
+        if self.step % CENSUS_STEPS == 0 {
+            self.history.push(self.census());
+        }
+    }
+
+    fn census(&self) -> Census {
+        let count = self.bloops.len().max(1) as f32;
+        Census {
+            population: self.bloops.len(),
+            food: self.food.len(),
+            size: self.bloops.iter().map(|bloop| bloop.dna.genes[0]).sum::<f32>() / count,
+            speed: self.bloops.iter().map(|bloop| bloop.dna.genes[1]).sum::<f32>() / count
+        }
+    }
+
+    // the deepest lineage
+    fn generation(&self) -> usize {
+        self.bloops.iter().map(|bloop| bloop.generation).max().unwrap_or(0)
+    }
+
+    // how many bloops have a gene in each of the bins from 0 to 1
+    fn distribution(&self, gene: usize) -> [usize; BINS] {
+        let mut bins = [0; BINS];
+        for bloop in &self.bloops {
+            bins[((bloop.dna.genes[gene] * BINS as f32) as usize).min(BINS - 1)] += 1;
+        }
+        bins
+    }
+}
+
+fn random_vector(rng: &mut impl Rng) -> Vec3 {
+    Vec3::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0))
+}
+
+fn random_position(rng: &mut impl Rng) -> Vec3 {
+    random_vector(rng) * MAX_LIMIT
+}
+
+#[derive(Component)]
+struct BloopBody(u64);
+
+// slow bloops are blue, fast ones red
+#[derive(Resource)]
+struct BloopMaterials(Vec<Handle<StandardMaterial>>);
+
+#[derive(Resource)]
+struct BloopMesh(Handle<Mesh>);
+
+fn main() {
+    //cargo run --release --example nine_ecosystem -- --headless
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless();
+        return;
+    }
+
+    let values = UiValues{
+        restart: false,
+        running: true,
+        population: 40,
+        steps_per_frame: 1
+    };
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(Ecosystem::new(values.population, &mut rand::rng()))
+        .insert_resource(values)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 9 Ecosystem".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              census_egui,
+                              restart,
+                              live,
+                              update_bloops,
+                              draw_food).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, MAX_LIMIT, 0.0),
+            rotation: Quat::from_rotation_x(-std::f32::consts::PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+
+    commands.insert_resource(BloopMesh(meshes.add(Mesh::from(Sphere::new(1.0)))));
+    commands.insert_resource(BloopMaterials((0..BINS).map(|bin| materials.add(StandardMaterial {
+        base_color: Color::Srgba(BLUE.mix(&RED, bin as f32 / (BINS - 1) as f32)),
+        ..default()
+    })).collect()));
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 48.0,
+            ..default()
+        }
+    ));
+}
+
+fn restart(
+    mut ui_values: ResMut<UiValues>,
+    mut ecosystem: ResMut<Ecosystem>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    //a new population that keeps the settings of the old one
+    let mut next = Ecosystem::new(ui_values.population, &mut rand::rng());
+    next.food_rate = ecosystem.food_rate;
+    next.mutation_rate = ecosystem.mutation_rate;
+    *ecosystem = next;
+}
+
+fn live(
+    ui_values: Res<UiValues>,
+    mut ecosystem: ResMut<Ecosystem>
+){
+    if !ui_values.running {
+        return;
+    }
+    let mut rng = rand::rng();
+    for _ in 0..ui_values.steps_per_frame {
+        ecosystem.step(&mut rng);
+    }
+}
+
+// spawns an entity for every new bloop and despawns the ones of dead bloops;
+// the size gene scales the mesh, the speed gene picks the color
+fn update_bloops(
+    mut commands: Commands,
+    ecosystem: Res<Ecosystem>,
+    bloop_mesh: Res<BloopMesh>,
+    bloop_materials: Res<BloopMaterials>,
+    mut query: Query<(Entity, &mut Transform, &mut MeshMaterial3d<StandardMaterial>, &BloopBody)>
+){
+    let material = |bloop: &Bloop| &bloop_materials.0[((bloop.dna.genes[1] * BINS as f32) as usize).min(BINS - 1)];
+    let bloops: HashMap<u64, &Bloop> = ecosystem.bloops.iter().map(|bloop| (bloop.id, bloop)).collect();
+    let mut shown = HashSet::new();
+    for (entity, mut transform, mut handle, body) in query.iter_mut() {
+        match bloops.get(&body.0).copied() {
+            Some(bloop) => {
+                transform.translation = bloop.position;
+                transform.scale = Vec3::splat(bloop.dna.size());
+                if handle.0 != *material(bloop) {
+                    handle.0 = material(bloop).clone();
+                }
+                shown.insert(body.0);
+            }
+            None => commands.entity(entity).despawn_recursive()
+        }
+    }
+    for bloop in ecosystem.bloops.iter().filter(|bloop| !shown.contains(&bloop.id)) {
+        commands.spawn((
+            Mesh3d(bloop_mesh.0.clone()),
+            MeshMaterial3d(material(bloop).clone()),
+            Transform::from_translation(bloop.position).with_scale(Vec3::splat(bloop.dna.size())),
+            BloopBody(bloop.id)
+        ));
+    }
+}
+
+fn draw_food(
+    mut gizmos: Gizmos,
+    ecosystem: Res<Ecosystem>
+){
+    for food in ecosystem.food.iter() {
+        gizmos.sphere(Isometry3d::from_translation(*food), 0.2, GREEN);
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    mut ecosystem: ResMut<Ecosystem>
+){
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        ui.add(egui::Slider::new(&mut ui_values.population, 2..=100).text("start population"));
+        ui.add(egui::Slider::new(&mut ecosystem.food_rate, 1.0..=80.0).text("food per second"));
+        ui.add(egui::Slider::new(&mut ecosystem.mutation_rate, 0.0..=0.5).text("mutation rate"));
+        ui.add(egui::Slider::new(&mut ui_values.steps_per_frame, 1..=100).text("steps per frame"));
+        ui.horizontal(|ui| {
+            let label = if ui_values.running { "Pause" } else { "Run" };
+            if ui.button(label).clicked() {
+                ui_values.running = !ui_values.running;
+            }
+            if ui.button("Restart").clicked() {
+                ui_values.restart = true;
+            }
+        });
+    });
+}
+
+fn census_egui(
+    mut egui_contexts: EguiContexts,
+    ecosystem: Res<Ecosystem>
+){
+    egui::Window::new("Population")
+        .default_pos(egui::pos2(WIDTH - PLOT_WIDTH - 40.0, 20.0))
+        .show(egui_contexts.ctx_mut(), |ui|{
+            ui.label(format!("time: {:.0} s  generation: {}", ecosystem.step as f32 * DELTA, ecosystem.generation()));
+            ui.label(format!("bloops: {}  food: {}", ecosystem.bloops.len(), ecosystem.food.len()));
+            if ecosystem.bloops.is_empty() {
+                ui.colored_label(egui::Color32::RED, "extinct, restart with more food");
+            }
+            history_plot(ui, &ecosystem.history);
+            ui.horizontal(|ui| {
+                ui.colored_label(egui::Color32::BLACK, "bloops");
+                ui.colored_label(egui::Color32::DARK_GREEN, "food");
+            });
+            for (gene, (name, min, max)) in GENES.iter().enumerate() {
+                ui.separator();
+                ui.label(format!("{name} from {min} to {max}"));
+                distribution_plot(ui, &ecosystem.distribution(gene));
+            }
+        });
+}
+
+// the number of bloops and pieces of food over time
+fn history_plot(
+    ui: &mut egui::Ui,
+    history: &[Census]
+){
+    let (response, painter) = ui.allocate_painter(egui::vec2(PLOT_WIDTH, PLOT_HEIGHT), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    painter.line_segment([rect.left_bottom(), rect.right_bottom()], egui::Stroke::new(1.0, egui::Color32::GRAY));
+
+    let max = history.iter().fold(1, |max, census| max.max(census.population).max(census.food)) as f32;
+    let to_screen = |index: usize, value: usize| egui::pos2(
+        rect.left() + index as f32 / history.len().max(2).saturating_sub(1) as f32 * rect.width(),
+        rect.bottom() - value as f32 / max * rect.height()
+    );
+    let bloops = history.iter().enumerate().map(|(index, census)| to_screen(index, census.population)).collect();
+    let food = history.iter().enumerate().map(|(index, census)| to_screen(index, census.food)).collect();
+    painter.add(egui::Shape::line(food, egui::Stroke::new(1.5, egui::Color32::DARK_GREEN)));
+    painter.add(egui::Shape::line(bloops, egui::Stroke::new(1.5, egui::Color32::BLACK)));
+}
+
+// a histogram of one gene, colored like the bloops for the speed
+fn distribution_plot(
+    ui: &mut egui::Ui,
+    bins: &[usize; BINS]
+){
+    let (response, painter) = ui.allocate_painter(egui::vec2(PLOT_WIDTH, PLOT_HEIGHT / 2.0), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    let max = bins.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let width = rect.width() / BINS as f32;
+    for (index, count) in bins.iter().enumerate() {
+        let left = rect.left() + index as f32 * width;
+        let top = rect.bottom() - *count as f32 / max * rect.height();
+        painter.rect_filled(
+            egui::Rect::from_min_max(egui::pos2(left + 1.0, top), egui::pos2(left + width - 1.0, rect.bottom())),
+            0.0,
+            egui::Color32::GRAY
+        );
+    }
+}
+
+// lets the ecosystem live for thousands of generations without a window, the same
+// way every run, and prints how the genes gather
+fn run_headless() {
+    const GENERATIONS: usize = 2000;
+    let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+    let mut ecosystem = Ecosystem::new(40, &mut rng);
+    let first = ecosystem.census();
+    let mut reported = 0;
+    while ecosystem.generation() < GENERATIONS {
+        ecosystem.step(&mut rng);
+        if ecosystem.bloops.is_empty() {
+            println!("the bloops died out after {:.0} s", ecosystem.step as f32 * DELTA);
+            return;
+        }
+        let generation = ecosystem.generation();
+        if generation >= reported + 250 {
+            reported = generation;
+            let census = ecosystem.census();
+            println!("generation {generation:>4}: {:>3} bloops, {:>3} food, size {:.2}, speed {:.2}",
+                     census.population, census.food, census.size, census.speed);
+        }
+    }
+    let last = ecosystem.census();
+    println!("{:.0} s: size {:.2} -> {:.2}, speed {:.2} -> {:.2}",
+             ecosystem.step as f32 * DELTA, first.size, last.size, first.speed, last.speed);
+
+    //random genes are spread with a standard deviation of about 0.29, selection gathers them
+    for (gene, (name, _, _)) in GENES.iter().enumerate() {
+        let mean = ecosystem.bloops.iter().map(|bloop| bloop.dna.genes[gene]).sum::<f32>() / ecosystem.bloops.len() as f32;
+        let variance = ecosystem.bloops.iter().map(|bloop| (bloop.dna.genes[gene] - mean).powi(2)).sum::<f32>() / ecosystem.bloops.len() as f32;
+        println!("{name}: {:?}, standard deviation {:.2}", ecosystem.distribution(gene), variance.sqrt());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(HEADLESS_SEED)
+    }
+
+    // one bloop without food around and no food growing
+    fn alone(health: f32) -> Ecosystem {
+        let mut rng = rng();
+        let mut ecosystem = Ecosystem::new(1, &mut rng);
+        ecosystem.food = Food::new();
+        ecosystem.food_rate = 0.0;
+        ecosystem.bloops[0].health = health;
+        ecosystem
+    }
+
+    #[test]
+    fn the_closest_food_is_found_across_cells() {
+        let mut food = Food::new();
+        food.add(Vec3::new(0.5, 0.0, 0.0));
+        food.add(Vec3::new(-1.0, 0.0, 0.0));
+        food.add(Vec3::new(SENSE + 1.0, 0.0, 0.0));
+        assert_eq!(food.closest(Vec3::new(-0.5, 0.0, 0.0)), Some(Vec3::new(-1.0, 0.0, 0.0)));
+        assert_eq!(food.closest(Vec3::new(SENSE - 0.5, 0.0, 0.0)), Some(Vec3::new(SENSE + 1.0, 0.0, 0.0)));
+        //out of sense
+        assert_eq!(food.closest(Vec3::new(MIN_LIMIT, MIN_LIMIT, MIN_LIMIT)), None);
+    }
+
+    #[test]
+    fn only_food_within_the_radius_is_eaten() {
+        let mut food = Food::new();
+        for x in 0..4 {
+            food.add(Vec3::new(x as f32, 0.0, 0.0));
+        }
+        assert_eq!(food.eat(Vec3::ZERO, 1.5), 2);
+        assert_eq!(food.len(), 2);
+        assert_eq!(food.eat(Vec3::ZERO, 1.5), 0);
+    }
+
+    #[test]
+    fn food_outside_the_cube_lands_in_the_border_cells() {
+        assert_eq!(Food::cell(Vec3::splat(MIN_LIMIT - 1.0)), [0; 3]);
+        assert_eq!(Food::cell(Vec3::splat(MAX_LIMIT + 1.0)), [FOOD_CELLS - 1; 3]);
+    }
+
+    #[test]
+    fn mutations_keep_the_genes_in_range() {
+        let mut rng = rng();
+        let mut dna = BloopDna { genes: [0.0, 1.0] };
+        for _ in 0..1000 {
+            dna.mutate(0.5, &mut rng);
+            assert!(dna.genes.iter().all(|gene| (0.0..=1.0).contains(gene)), "{:?}", dna.genes);
+        }
+        assert_eq!(BloopDna { genes: [0.0, 1.0] }.size(), GENES[0].1);
+        assert_eq!(BloopDna { genes: [0.0, 1.0] }.maximum_speed(), GENES[1].2);
+    }
+
+    #[test]
+    fn a_healthy_bloop_splits_in_halves() {
+        let mut ecosystem = alone(SPLIT_HEALTH + 1.0);
+        ecosystem.step(&mut rng());
+        assert_eq!(ecosystem.bloops.len(), 2);
+        let [parent, child] = [&ecosystem.bloops[0], &ecosystem.bloops[1]];
+        assert_eq!(parent.health, child.health);
+        assert!(parent.health < (SPLIT_HEALTH + 1.0) / 2.0);
+        assert_eq!((parent.generation, child.generation), (0, 1));
+        assert_ne!(parent.id, child.id);
+    }
+
+    #[test]
+    fn a_starving_bloop_dies() {
+        let mut ecosystem = alone(BIRTH_HEALTH);
+        let mut rng = rng();
+        let mut steps = 0;
+        while !ecosystem.bloops.is_empty() {
+            ecosystem.step(&mut rng);
+            steps += 1;
+        }
+        //it lives at most as long as its health lasts without moving
+        assert!(steps as f32 * DELTA <= BIRTH_HEALTH / LIVING_COST + DELTA, "{steps} steps");
+        assert_eq!(ecosystem.history.len(), steps / CENSUS_STEPS);
+    }
+
+    #[test]
+    fn bloops_stay_near_the_cube() {
+        let mut moveable = Moveable { velocity: Vec3::X, maximum_speed: 1.0, maximum_force: 1.0 };
+        let mut position = Vec3::new(MAX_LIMIT, 0.0, 0.0);
+        for _ in 0..100 {
+            position = moveable.update(position, Vec3::ZERO);
+        }
+        assert!(position.x <= MAX_LIMIT + DELTA && moveable.velocity.x < 0.0, "{position}");
+    }
+}
@@ -0,0 +1,665 @@
+use bevy::color::palettes::basic::GREEN;
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use ga::{Crossover, Dna, Population, Selection, Statistics};
+use preset::{SteeringPreset, PRESET_PATH};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+mod orbitcamera;
+mod mesh;
+mod ga;
+mod preset;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 32.0;
+const MIN_LIMIT: f32 = -32.0;
+
+//one hunt: the flock around its home and a predator, with a fixed time step
+const BOIDS: usize = 32;
+const STEPS: usize = 900;
+const DELTA: f32 = 1.0 / 30.0;
+
+//the same distances as the flock of chapter 5
+const NEIGHBOR_DISTANCE: f32 = 8.0;
+const DESIRED_SEPARATION: f32 = 2.0;
+
+//boids inside the home radius count fully, the others a quarter
+const HOME: Vec3 = Vec3::ZERO;
+const HOME_RADIUS: f32 = 16.0;
+const AWAY_SCORE: f32 = 0.25;
+
+//a boid sees the predator and evades it
+const SIGHT: f32 = 16.0;
+const EVADE_WEIGHT: f32 = 2.0;
+
+//boids that come closer crash into each other
+const COLLISION_DISTANCE: f32 = 0.5;
+
+const PREDATOR_SPEED: f32 = 28.0;
+const PREDATOR_FORCE: f32 = 80.0;
+const CATCH_DISTANCE: f32 = 1.5;
+//every boid close to the prey confuses the predator a little, see hunt
+const CONFUSION_DISTANCE: f32 = 4.0;
+const CONFUSION: f64 = 0.2;
+//steps the predator rests after a catch
+const DIGEST_STEPS: usize = 45;
+
+//fast and agile boids use more energy, the fitness pays for it
+const ENERGY_COST: f32 = 0.1;
+
+// name and range of every gene, the genes themselves lie in 0..1
+const GENES: [(&str, f32, f32); 6] = [
+    ("align", 0.0, 4.0),
+    ("separate", 0.0, 8.0),
+    ("cohesion", 0.0, 4.0),
+    ("seek", 0.0, 8.0),
+    ("maximum speed", 8.0, 32.0),
+    ("maximum force", 4.0, 32.0)
+];
+
+const PLOT_WIDTH: f32 = 280.0;
+const PLOT_HEIGHT: f32 = 140.0;
+
+const HEADLESS_SEED: u64 = 48;
+
+#[derive(Resource)]
+struct UiValues{
+    restart: bool,
+    running: bool,
+    population: usize,
+    evaluations_per_frame: usize,
+    message: String
+}
+
+#[derive(Clone)]
+struct FlockingDna {
+    genes: [f32; 6]
+}
+
+impl FlockingDna {
+    fn random(rng: &mut impl Rng) -> Self {
+        FlockingDna {
+            genes: std::array::from_fn(|_| rng.random_range(0.0..1.0))
+        }
+    }
+
+    fn preset(&self) -> SteeringPreset {
+        let [align, separate, cohesion, seek, maximum_speed, maximum_force]: [f32; 6] =
+            std::array::from_fn(|index| GENES[index].1 + self.genes[index] * (GENES[index].2 - GENES[index].1));
+        SteeringPreset { align, separate, cohesion, seek, maximum_speed, maximum_force, fitness: 0.0, generation: 0 }
+    }
+}
+
+impl Dna for FlockingDna {
+    fn crossover(&self, other: &Self, crossover: Crossover, rng: &mut impl Rng) -> Self {
+        let genes = crossover.genes(&self.genes, &other.genes, rng);
+        FlockingDna {
+            genes: std::array::from_fn(|index| genes[index])
+        }
+    }
+
+    //a small nudge, so a good weight is not lost at once
+    fn mutate(&mut self, rate: f32, rng: &mut impl Rng) {
+        for gene in self.genes.iter_mut() {
+            if rng.random_bool(rate as f64) {
+                *gene = (*gene + rng.random_range(-0.2..0.2)).clamp(0.0, 1.0);
+            }
+        }
+    }
+}
+
+struct Moveable {
+    velocity: Vec3,
+    maximum_speed: f32,
+    maximum_force: f32
+}
+
+impl Moveable {
+    fn seek(&self, target: Vec3, position: Vec3) -> Vec3 {
+        //steering force = desired velocity - velocity
+        let desired_velocity = (target - position).normalize_or_zero() * self.maximum_speed;
+        (desired_velocity - self.velocity).clamp_length_max(self.maximum_force)
+    }
+
+    // the new position after one step, bouncing off the limit cube
+    fn update(&mut self, position: Vec3, force: Vec3) -> Vec3 {
+        self.velocity = (self.velocity + force * DELTA).clamp_length_max(self.maximum_speed);
+        for axis in 0..3 {
+            if (position[axis] > MAX_LIMIT && self.velocity[axis] > 0.0)
+                || (position[axis] < MIN_LIMIT && self.velocity[axis] < 0.0) {
+                self.velocity[axis] *= -1.0;
+            }
+        }
+        position + self.velocity * DELTA
+    }
+}
+
+struct Boid {
+    position: Vec3,
+    moveable: Moveable,
+    alive: bool
+}
+
+struct Predator {
+    position: Vec3,
+    moveable: Moveable,
+    digesting: usize
+}
+
+// a flock steered by the preset, hunted by a predator
+struct Hunt {
+    boids: Vec<Boid>,
+    predator: Predator,
+    step: usize,
+    score: f32,
+    rng: StdRng
+}
+
+impl Hunt {
+    // the same seed gives the same start, so the members of a generation are compared fairly
+    fn new(seed: u64, preset: &SteeringPreset) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let boids = (0..BOIDS).map(|_| Boid {
+            position: HOME + random_vector(&mut rng) * HOME_RADIUS * 0.75,
+            moveable: Moveable {
+                velocity: random_vector(&mut rng) * 4.0,
+                maximum_speed: preset.maximum_speed,
+                maximum_force: preset.maximum_force
+            },
+            alive: true
+        }).collect();
+        let predator = Predator {
+            position: random_vector(&mut rng).normalize_or(Vec3::X) * (MAX_LIMIT - 2.0),
+            moveable: Moveable {
+                velocity: Vec3::ZERO,
+                maximum_speed: PREDATOR_SPEED,
+                maximum_force: PREDATOR_FORCE
+            },
+            digesting: 0
+        };
+        Hunt { boids, predator, step: 0, score: 0.0, rng }
+    }
+
+    fn finished(&self) -> bool {
+        self.step >= STEPS || self.boids.iter().all(|boid| !boid.alive)
+    }
+
+    fn alive(&self) -> usize {
+        self.boids.iter().filter(|boid| boid.alive).count()
+    }
+
+    fn step(&mut self, preset: &SteeringPreset) {
+        let forces: Vec<Vec3> = (0..self.boids.len()).map(|index| self.steering(index, preset)).collect();
+        for (boid, force) in self.boids.iter_mut().zip(forces) {
+            if boid.alive {
+                boid.position = boid.moveable.update(boid.position, force);
+            }
+        }
+        self.collide();
+        self.hunt();
+        self.score += self.boids.iter()
+            .filter(|boid| boid.alive)
+            .map(|boid| if boid.position.distance(HOME) < HOME_RADIUS { 1.0 } else { AWAY_SCORE })
+            .sum::<f32>();
+        self.step += 1;
+    }
+
+    // align, separate, cohesion and seek as in chapter 5, plus evading the predator
+    fn steering(&self, index: usize, preset: &SteeringPreset) -> Vec3 {
+        let boid = &self.boids[index];
+        if !boid.alive {
+            return Vec3::ZERO;
+        }
+        let moveable = &boid.moveable;
+        let mut velocities = Vec3::ZERO;
+        let mut positions = Vec3::ZERO;
+        let mut neighbours = 0;
+        let mut away = Vec3::ZERO;
+        let mut close = 0;
+        for (other_index, other) in self.boids.iter().enumerate() {
+            if other_index == index || !other.alive {
+                continue;
+            }
+            let distance = boid.position.distance(other.position);
+            if distance < NEIGHBOR_DISTANCE {
+                velocities += other.moveable.velocity;
+                positions += other.position;
+                neighbours += 1;
+            }
+            if distance < DESIRED_SEPARATION && distance > 0.0 {
+                away += (boid.position - other.position).normalize() / distance;
+                close += 1;
+            }
+        }
+
+        let mut force = moveable.seek(HOME, boid.position) * preset.seek;
+        if neighbours > 0 {
+            let align = (velocities / neighbours as f32).normalize_or_zero() * moveable.maximum_speed - moveable.velocity;
+            force += align.clamp_length_max(moveable.maximum_force) * preset.align;
+            force += moveable.seek(positions / neighbours as f32, boid.position) * preset.cohesion;
+        }
+        if close > 0 {
+            let separate = (away / close as f32).normalize_or_zero() * moveable.maximum_speed - moveable.velocity;
+            force += separate.clamp_length_max(moveable.maximum_force) * preset.separate;
+        }
+        let predator = &self.predator;
+        if boid.position.distance(predator.position) < SIGHT {
+            force -= moveable.seek(predator.position + predator.moveable.velocity * 0.5, boid.position) * EVADE_WEIGHT;
+        }
+        force
+    }
+
+    // boids that come too close crash into each other
+    fn collide(&mut self) {
+        let mut crashed = vec![false; self.boids.len()];
+        for (index, boid) in self.boids.iter().enumerate().filter(|(_, boid)| boid.alive) {
+            crashed[index] = self.boids.iter().enumerate().any(|(other_index, other)| {
+                other_index != index && other.alive && boid.position.distance(other.position) < COLLISION_DISTANCE
+            });
+        }
+        for (boid, crashed) in self.boids.iter_mut().zip(crashed) {
+            if crashed {
+                boid.alive = false;
+            }
+        }
+    }
+
+    // the predator pursues the closest boid; the more boids are around the prey the
+    // harder it is to catch
+    fn hunt(&mut self) {
+        let predator = &mut self.predator;
+        if predator.digesting > 0 {
+            predator.digesting -= 1;
+            predator.moveable.velocity *= 0.95;
+            predator.position = predator.moveable.update(predator.position, Vec3::ZERO);
+            return;
+        }
+        let Some(prey) = self.boids.iter().enumerate()
+            .filter(|(_, boid)| boid.alive)
+            .min_by(|(_, a), (_, b)| a.position.distance(predator.position).total_cmp(&b.position.distance(predator.position)))
+            .map(|(index, _)| index) else {
+            return;
+        };
+        let target = &self.boids[prey];
+        let force = predator.moveable.seek(target.position + target.moveable.velocity * 0.5, predator.position);
+        predator.position = predator.moveable.update(predator.position, force);
+
+        if predator.position.distance(target.position) < CATCH_DISTANCE {
+            let confusion = self.boids.iter().enumerate()
+                .filter(|(index, boid)| *index != prey && boid.alive && boid.position.distance(target.position) < CONFUSION_DISTANCE)
+                .count();
+            if self.rng.random_bool(1.0 / (1.0 + CONFUSION * confusion as f64)) {
+                self.boids[prey].alive = false;
+                self.predator.digesting = DIGEST_STEPS;
+            }
+        }
+    }
+}
+
+fn random_vector(rng: &mut impl Rng) -> Vec3 {
+    Vec3::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0))
+}
+
+// the share of boids that stayed alive at home over the whole hunt, less the energy
+fn fitness(preset: &SteeringPreset, seed: u64) -> f32 {
+    let mut hunt = Hunt::new(seed, preset);
+    while !hunt.finished() {
+        hunt.step(preset);
+    }
+    let energy = preset.maximum_speed / GENES[4].2 * preset.maximum_force / GENES[5].2;
+    hunt.score / (BOIDS * STEPS) as f32 - ENERGY_COST * energy
+}
+
+#[derive(Resource)]
+struct Evolution {
+    population: Population<FlockingDna>,
+    fitness: Vec<f32>,
+    history: Vec<Statistics>,
+    //the best preset of the last generation
+    best: Option<SteeringPreset>
+}
+
+impl Evolution {
+    fn new(size: usize, rng: &mut impl Rng) -> Self {
+        let mut population = Population::new((0..size).map(|_| FlockingDna::random(rng)).collect());
+        population.selection = Selection::Tournament(3);
+        population.mutation_rate = 0.1;
+        population.elitism = 2;
+        Evolution {
+            population,
+            fitness: Vec::new(),
+            history: Vec::new(),
+            best: None
+        }
+    }
+
+    // rates the next member, after the last one the next generation is bred
+    fn evaluate(&mut self, rng: &mut impl Rng) {
+        let index = self.fitness.len();
+        let seed = self.population.generation as u64;
+        self.fitness.push(fitness(&self.population.members[index].preset(), seed));
+        if self.fitness.len() < self.population.members.len() {
+            return;
+        }
+        let best = ga::ranking(&self.fitness)[0];
+        self.best = Some(SteeringPreset {
+            fitness: self.fitness[best],
+            generation: self.population.generation,
+            ..self.population.members[best].preset()
+        });
+        let fitness = std::mem::take(&mut self.fitness);
+        self.history.push(self.population.evolve(&fitness, rng));
+    }
+}
+
+// the hunt of the best preset, shown while the evolution goes on
+#[derive(Resource)]
+struct Replay {
+    hunt: Hunt,
+    preset: SteeringPreset
+}
+
+#[derive(Component)]
+struct BoidShip(usize);
+
+#[derive(Component)]
+struct PredatorShip;
+
+fn main() {
+    //cargo run --example nine_flocking -- --headless [--save-preset]
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless(std::env::args().any(|arg| arg == "--save-preset"));
+        return;
+    }
+
+    let values = UiValues{
+        restart: false,
+        running: true,
+        population: 40,
+        evaluations_per_frame: 1,
+        message: String::new()
+    };
+    let preset = FlockingDna { genes: [0.5; 6] }.preset();
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(Evolution::new(values.population, &mut rand::rng()))
+        .insert_resource(Replay { hunt: Hunt::new(0, &preset), preset })
+        .insert_resource(values)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 9 - evolved flocking".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              statistics_egui,
+                              evolve,
+                              replay,
+                              update_ships,
+                              draw_home).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+    asset_server: Res<AssetServer>
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, MAX_LIMIT, 0.0),
+            rotation: Quat::from_rotation_x(-std::f32::consts::PI),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+    for index in 0..BOIDS {
+        commands.spawn((
+            SceneRoot(asset_server.load("models/cone_blue.glb#Scene0")),
+            Transform::default(),
+            BoidShip(index)
+        ));
+    }
+    commands.spawn((
+        SceneRoot(asset_server.load("models/cone_pink.glb#Scene0")),
+        Transform::default().with_scale(Vec3::splat(2.0)),
+        PredatorShip
+    ));
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 56.0,
+            ..default()
+        }
+    ));
+}
+
+fn evolve(
+    mut ui_values: ResMut<UiValues>,
+    mut evolution: ResMut<Evolution>
+){
+    let mut rng = rand::rng();
+    if ui_values.restart {
+        ui_values.restart = false;
+        let mut next = Evolution::new(ui_values.population, &mut rng);
+        next.population = evolution.population.reseeded(next.population.members);
+        *evolution = next;
+    }
+    if !ui_values.running {
+        return;
+    }
+    for _ in 0..ui_values.evaluations_per_frame {
+        evolution.evaluate(&mut rng);
+    }
+}
+
+// one step per frame; a finished hunt starts again with the best preset so far
+fn replay(
+    evolution: Res<Evolution>,
+    mut replay: ResMut<Replay>
+){
+    if replay.hunt.finished() {
+        if let Some(best) = evolution.best {
+            replay.preset = best;
+        }
+        let seed = rand::rng().random();
+        replay.hunt = Hunt::new(seed, &replay.preset);
+    }
+    let preset = replay.preset;
+    replay.hunt.step(&preset);
+}
+
+fn place(transform: &mut Transform, position: Vec3, velocity: Vec3) {
+    transform.translation = position;
+    if velocity.length_squared() > 0.0 {
+        transform.look_at(position + velocity, Vec3::Y);
+    }
+}
+
+fn update_ships(
+    replay: Res<Replay>,
+    mut boids: Query<(&mut Transform, &mut Visibility, &BoidShip), Without<PredatorShip>>,
+    mut predator: Query<&mut Transform, With<PredatorShip>>
+){
+    for (mut transform, mut visibility, ship) in boids.iter_mut() {
+        let boid = &replay.hunt.boids[ship.0];
+        place(&mut transform, boid.position, boid.moveable.velocity);
+        visibility.set_if_neq(if boid.alive { Visibility::Inherited } else { Visibility::Hidden });
+    }
+    if let Ok(mut transform) = predator.get_single_mut() {
+        let predator = &replay.hunt.predator;
+        place(&mut transform, predator.position, predator.moveable.velocity);
+    }
+}
+
+fn draw_home(
+    mut gizmos: Gizmos
+){
+    gizmos.sphere(Isometry3d::from_translation(HOME), HOME_RADIUS, GREEN);
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    mut evolution: ResMut<Evolution>
+){
+    let mut population = ui_values.population;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        ui.add(egui::Slider::new(&mut population, 10..=100).text("population"));
+
+        ga::population_ui(ui, &mut evolution.population, 0.0..=0.5);
+        ui.add(egui::Slider::new(&mut ui_values.evaluations_per_frame, 1..=10).text("hunts per frame"));
+
+        ui.horizontal(|ui| {
+            let label = if ui_values.running { "Pause" } else { "Run" };
+            if ui.button(label).clicked() {
+                ui_values.running = !ui_values.running;
+            }
+            if ui.button("Restart").clicked() {
+                ui_values.restart = true;
+            }
+        });
+        let best = evolution.best;
+        if ui.add_enabled(best.is_some(), egui::Button::new("Save preset")).clicked() {
+            if let Some(best) = best {
+                ui_values.message = match best.save(PRESET_PATH) {
+                    Ok(()) => format!("saved {PRESET_PATH}"),
+                    Err(error) => error.to_string()
+                };
+            }
+        }
+        if !ui_values.message.is_empty() {
+            ui.label(&ui_values.message);
+        }
+    });
+    if population != ui_values.population {
+        ui_values.population = population;
+        ui_values.restart = true;
+    }
+}
+
+fn statistics_egui(
+    mut egui_contexts: EguiContexts,
+    evolution: Res<Evolution>,
+    replay: Res<Replay>
+){
+    egui::Window::new("Generations")
+        .default_pos(egui::pos2(WIDTH - PLOT_WIDTH - 40.0, 20.0))
+        .show(egui_contexts.ctx_mut(), |ui|{
+            let members = evolution.population.members.len();
+            ui.label(format!("generation: {}", evolution.population.generation));
+            ui.add(egui::ProgressBar::new(evolution.fitness.len() as f32 / members as f32).text("rated"));
+            if let Some(last) = evolution.history.last() {
+                ui.label(format!("fitness best: {:.3}  mean: {:.3}", last.best, last.mean));
+            }
+            //the fitness lies between -ENERGY_COST and 1
+            ga::fitness_plot(ui, &evolution.history, egui::vec2(PLOT_WIDTH, PLOT_HEIGHT), Some(1.0));
+            ui.colored_label(egui::Color32::BLUE, "best");
+            ui.colored_label(egui::Color32::DARK_GREEN, "mean");
+
+            ui.separator();
+            ui.label(format!("replay: {} of {BOIDS} boids alive", replay.hunt.alive()));
+            let preset = replay.preset;
+            let values = [preset.align, preset.separate, preset.cohesion, preset.seek, preset.maximum_speed, preset.maximum_force];
+            for ((name, _, _), value) in GENES.iter().zip(values) {
+                ui.label(format!("{name}: {value:.2}"));
+            }
+        });
+}
+
+// evolves the weights without a window, the same way every run, and writes the best
+// preset to the temporary directory; with --save-preset it replaces the one the
+// chapter 5 examples load
+fn run_headless(save_preset: bool) {
+    const GENERATIONS: usize = 30;
+    let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+    let mut evolution = Evolution::new(40, &mut rng);
+    while evolution.history.len() < GENERATIONS {
+        evolution.evaluate(&mut rng);
+        if evolution.fitness.is_empty() {
+            let last = evolution.history[evolution.history.len() - 1];
+            println!("generation {:>2}: best {:.3} mean {:.3}", last.generation, last.best, last.mean);
+        }
+    }
+
+    let best = evolution.best.expect("a rated generation");
+    let path = if save_preset {
+        PRESET_PATH.into()
+    } else {
+        std::env::temp_dir().join("flocking.preset.ron")
+    };
+    match best.save(&path) {
+        Ok(()) => println!("{}: {best:?}", path.display()),
+        Err(error) => eprintln!("{}: {error}", path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genes_span_the_ranges_of_the_weights() {
+        let lowest = FlockingDna { genes: [0.0; 6] }.preset();
+        let highest = FlockingDna { genes: [1.0; 6] }.preset();
+        assert_eq!(lowest.align, GENES[0].1);
+        assert_eq!(highest.maximum_force, GENES[5].2);
+    }
+
+    #[test]
+    fn mutations_keep_the_genes_in_range() {
+        let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+        let mut dna = FlockingDna { genes: [0.0, 1.0, 0.5, 0.0, 1.0, 0.5] };
+        for _ in 0..1000 {
+            dna.mutate(0.5, &mut rng);
+            assert!(dna.genes.iter().all(|gene| (0.0..=1.0).contains(gene)), "{:?}", dna.genes);
+        }
+    }
+
+    #[test]
+    fn a_hunt_flies_the_same_for_the_same_seed() {
+        let preset = FlockingDna { genes: [0.5; 6] }.preset();
+        assert_eq!(fitness(&preset, 7), fitness(&preset, 7));
+    }
+
+    #[test]
+    fn flocks_learn_to_survive() {
+        let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+        let mut evolution = Evolution::new(12, &mut rng);
+        while evolution.history.len() < 6 {
+            evolution.evaluate(&mut rng);
+        }
+        let first = evolution.history[0];
+        let last = evolution.history[5];
+        assert!(last.mean > first.mean, "{first:?} -> {last:?}");
+    }
+}
@@ -0,0 +1,219 @@
+use rand::Rng;
+
+// Neural networks without a library: a perceptron that learns with the perceptron
+// rule and a feed forward network of fully connected layers. The network does not
+// learn by itself, its weights can be read and written as one list of genes, so a
+// genetic algorithm can evolve them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Activation {
+    Sigmoid,
+    Tanh,
+    Relu,
+    //1 from zero on, else -1
+    Sign
+}
+
+pub const ACTIVATIONS: [(Activation, &str); 4] = [
+    (Activation::Sigmoid, "Sigmoid"),
+    (Activation::Tanh, "Tanh"),
+    (Activation::Relu, "ReLU"),
+    (Activation::Sign, "Sign")
+];
+
+impl Activation {
+    pub fn apply(&self, x: f32) -> f32 {
+        match self {
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.max(0.0),
+            Activation::Sign => if x >= 0.0 { 1.0 } else { -1.0 }
+        }
+    }
+}
+
+pub struct Perceptron {
+    pub weights: Vec<f32>,
+    pub bias: f32,
+    pub learning_rate: f32
+}
+
+impl Perceptron {
+    pub fn new(inputs: usize, learning_rate: f32, rng: &mut impl Rng) -> Self {
+        Perceptron {
+            weights: (0..inputs).map(|_| rng.random_range(-1.0..1.0)).collect(),
+            bias: rng.random_range(-1.0..1.0),
+            learning_rate
+        }
+    }
+
+    // -1 or 1
+    pub fn guess(&self, inputs: &[f32]) -> f32 {
+        let sum: f32 = self.weights.iter().zip(inputs).map(|(weight, input)| weight * input).sum();
+        Activation::Sign.apply(sum + self.bias)
+    }
+
+    // moves the weights towards the target by the error times the input
+    pub fn train(&mut self, inputs: &[f32], target: f32) {
+        let error = target - self.guess(inputs);
+        for (weight, input) in self.weights.iter_mut().zip(inputs) {
+            *weight += error * input * self.learning_rate;
+        }
+        self.bias += error * self.learning_rate;
+    }
+}
+
+// every output is connected to every input, the weights are stored output by output
+#[derive(Clone)]
+pub struct Layer {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub biases: Vec<f32>,
+    pub activation: Activation
+}
+
+impl Layer {
+    pub fn new(inputs: usize, outputs: usize, activation: Activation, rng: &mut impl Rng) -> Self {
+        Layer {
+            inputs,
+            outputs,
+            weights: (0..inputs * outputs).map(|_| rng.random_range(-1.0..1.0)).collect(),
+            biases: (0..outputs).map(|_| rng.random_range(-1.0..1.0)).collect(),
+            activation
+        }
+    }
+
+    pub fn weight(&self, input: usize, output: usize) -> f32 {
+        self.weights[output * self.inputs + input]
+    }
+
+    pub fn forward(&self, inputs: &[f32]) -> Vec<f32> {
+        (0..self.outputs).map(|output| {
+            let row = &self.weights[output * self.inputs..(output + 1) * self.inputs];
+            let sum: f32 = row.iter().zip(inputs).map(|(weight, input)| weight * input).sum();
+            self.activation.apply(sum + self.biases[output])
+        }).collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct Network {
+    pub layers: Vec<Layer>
+}
+
+impl Network {
+    // sizes from the inputs to the outputs, e.g. [7, 8, 2]
+    pub fn new(sizes: &[usize], hidden: Activation, output: Activation, rng: &mut impl Rng) -> Self {
+        let count = sizes.len().saturating_sub(1);
+        Network {
+            layers: (0..count).map(|index| {
+                let activation = if index + 1 == count { output } else { hidden };
+                Layer::new(sizes[index], sizes[index + 1], activation, rng)
+            }).collect()
+        }
+    }
+
+    pub fn predict(&self, inputs: &[f32]) -> Vec<f32> {
+        self.layers.iter().fold(inputs.to_vec(), |values, layer| layer.forward(&values))
+    }
+
+    // the values of every layer, starting with the inputs
+    pub fn activations(&self, inputs: &[f32]) -> Vec<Vec<f32>> {
+        let mut values = vec![inputs.to_vec()];
+        for layer in &self.layers {
+            let next = layer.forward(&values[values.len() - 1]);
+            values.push(next);
+        }
+        values
+    }
+
+    // all weights and biases, layer by layer
+    pub fn genes(&self) -> Vec<f32> {
+        self.layers.iter()
+            .flat_map(|layer| layer.weights.iter().chain(layer.biases.iter()).copied())
+            .collect()
+    }
+
+    pub fn set_genes(&mut self, genes: &[f32]) {
+        let mut genes = genes.iter().copied();
+        for layer in self.layers.iter_mut() {
+            for value in layer.weights.iter_mut().chain(layer.biases.iter_mut()) {
+                if let Some(gene) = genes.next() {
+                    *value = gene;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn random_point(rng: &mut impl Rng) -> Vec<f32> {
+        (0..3).map(|_| rng.random_range(-1.0..1.0)).collect()
+    }
+
+    #[test]
+    fn a_perceptron_separates_a_plane() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let plane = |point: &[f32]| if point[1] > 0.5 * point[0] - 0.3 * point[2] + 0.1 { 1.0 } else { -1.0 };
+        let mut perceptron = Perceptron::new(3, 0.01, &mut rng);
+        for _ in 0..20000 {
+            let point = random_point(&mut rng);
+            perceptron.train(&point, plane(&point));
+        }
+        let correct = (0..1000).filter(|_| {
+            let point = random_point(&mut rng);
+            perceptron.guess(&point) == plane(&point)
+        }).count();
+        assert!(correct > 950, "{correct} of 1000");
+    }
+
+    #[test]
+    fn weights_are_stored_output_by_output() {
+        let mut network = Network::new(&[2, 1], Activation::Relu, Activation::Relu, &mut StdRng::seed_from_u64(49));
+        network.set_genes(&[2.0, 3.0, -1.0]);
+        assert_eq!(network.layers[0].weight(1, 0), 3.0);
+        assert_eq!(network.predict(&[1.0, 1.0]), vec![4.0]);
+        assert_eq!(network.predict(&[0.0, 0.0]), vec![0.0]);
+    }
+
+    #[test]
+    fn genes_are_read_the_way_they_are_written() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let mut network = Network::new(&[7, 6, 2], Activation::Tanh, Activation::Tanh, &mut rng);
+        //7 * 6 + 6 weights and biases in the hidden layer, 6 * 2 + 2 in the output layer
+        let genes: Vec<f32> = (0..62).map(|gene| gene as f32 / 62.0).collect();
+        assert_eq!(network.genes().len(), genes.len());
+        network.set_genes(&genes);
+        assert_eq!(network.genes(), genes);
+        assert_eq!(network.activations(&[0.0; 7]).iter().map(Vec::len).collect::<Vec<_>>(), vec![7, 6, 2]);
+    }
+
+    // xor cannot be learned by a single perceptron, a small network with changed
+    // weights kept whenever they are better can
+    #[test]
+    fn a_network_learns_xor() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let cases = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)];
+        let error = |network: &Network| cases.iter()
+            .map(|(inputs, target)| (network.predict(inputs)[0] - target).powi(2))
+            .sum::<f32>();
+        let mut network = Network::new(&[2, 3, 1], Activation::Tanh, Activation::Sigmoid, &mut rng);
+        let mut candidate = network.clone();
+        for _ in 0..20000 {
+            let genes: Vec<f32> = network.genes().iter().map(|gene| gene + rng.random_range(-0.5..0.5) * rng.random_range(0.0..1.0)).collect();
+            candidate.set_genes(&genes);
+            if error(&candidate) < error(&network) {
+                network.set_genes(&genes);
+            }
+        }
+        for (inputs, target) in cases {
+            let output = network.predict(&inputs)[0];
+            assert_eq!(output.round(), target, "xor {inputs:?} gives {output}");
+        }
+    }
+}
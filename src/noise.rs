@@ -0,0 +1,197 @@
+// shared by several examples, each one uses only a part of it
+#![allow(dead_code)]
+
+// Perlin and simplex noise in one to four dimensions: random gradients on the
+// corners of a grid, blended smoothly, so close points get similar values. The
+// results lie roughly in -1..1. Fbm adds octaves of growing frequency and falling
+// amplitude for detail on every scale.
+
+// a pseudo random number for every grid corner, the same for every run
+fn hash<const N: usize>(cell: [i32; N]) -> u32 {
+    const { assert!(N >= 1 && N <= 4, "noise has one to four dimensions") };
+    const PRIMES: [u32; 4] = [0x8da6_b343, 0xd816_3841, 0xcb1a_b31f, 0x9e37_79b1];
+    let mut hash = cell.iter().zip(PRIMES)
+        .fold(0x2545_f491_u32, |hash, (coordinate, prime)| hash ^ (*coordinate as u32).wrapping_mul(prime));
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0x5bd1_e995);
+    hash ^ (hash >> 15)
+}
+
+// 6t⁵ - 15t⁴ + 10t³, no kinks at the grid lines
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// the gradient of a corner dotted with the offset to it; from three dimensions on
+// the gradients point to the edges of the cube (one axis zero, the others ±1),
+// below to its corners
+fn gradient<const N: usize>(hash: u32, offset: [f32; N]) -> f32 {
+    let skip = if N > 2 { (hash >> N) as usize % N } else { N };
+    (0..N)
+        .filter(|axis| *axis != skip)
+        .map(|axis| if hash >> axis & 1 == 0 { offset[axis] } else { -offset[axis] })
+        .sum()
+}
+
+// brings the largest values of each dimension close to ±1
+const PERLIN_SCALE: [f32; 4] = [2.0, 1.0, 1.1, 0.93];
+const SIMPLEX_SCALE: [f32; 4] = [70.0, 70.0, 76.0, 62.0];
+
+pub fn perlin<const N: usize>(point: [f32; N]) -> f32 {
+    const { assert!(N >= 1 && N <= 4, "noise has one to four dimensions") };
+    let cell = point.map(|value| value.floor() as i32);
+    let fraction: [f32; N] = std::array::from_fn(|axis| point[axis] - point[axis].floor());
+    let weights = fraction.map(fade);
+
+    //every corner of the cell weighted by how close the point is to it, the same as
+    //interpolating along one axis after the other
+    let mut value = 0.0;
+    for corner in 0..1_usize << N {
+        let mut weight = 1.0;
+        let mut corner_cell = cell;
+        let mut offset = fraction;
+        for axis in 0..N {
+            if corner >> axis & 1 == 1 {
+                weight *= weights[axis];
+                corner_cell[axis] += 1;
+                offset[axis] -= 1.0;
+            } else {
+                weight *= 1.0 - weights[axis];
+            }
+        }
+        value += weight * gradient(hash(corner_cell), offset);
+    }
+    value * PERLIN_SCALE[N - 1]
+}
+
+// Simplex noise splits space into simplices (intervals, triangles, tetrahedra, ...)
+// instead of cubes, so each point only has N + 1 corners to blend.
+pub fn simplex<const N: usize>(point: [f32; N]) -> f32 {
+    const { assert!(N >= 1 && N <= 4, "noise has one to four dimensions") };
+    let dimensions = N as f32;
+    //skewing turns the simplex grid into a cube grid and back
+    let skew = ((dimensions + 1.0).sqrt() - 1.0) / dimensions;
+    let unskew = (1.0 - 1.0 / (dimensions + 1.0).sqrt()) / dimensions;
+
+    let skewed = point.iter().sum::<f32>() * skew;
+    let cell = point.map(|value| (value + skewed).floor() as i32);
+    let unskewed = cell.iter().sum::<i32>() as f32 * unskew;
+    let first: [f32; N] = std::array::from_fn(|axis| point[axis] - (cell[axis] as f32 - unskewed));
+
+    //the simplex that holds the point walks from the first corner along the axes,
+    //largest offset first
+    let mut order: [usize; N] = std::array::from_fn(|axis| axis);
+    order.sort_by(|a, b| first[*b].total_cmp(&first[*a]));
+
+    let mut value = 0.0;
+    let mut steps = [0; N];
+    for corner in 0..=N {
+        if corner > 0 {
+            steps[order[corner - 1]] += 1;
+        }
+        let offset: [f32; N] = std::array::from_fn(|axis| {
+            first[axis] - steps[axis] as f32 + corner as f32 * unskew
+        });
+        let falloff = 0.5 - offset.iter().map(|value| value * value).sum::<f32>();
+        if falloff > 0.0 {
+            let corner_cell: [i32; N] = std::array::from_fn(|axis| cell[axis] + steps[axis]);
+            value += falloff.powi(4) * gradient(hash(corner_cell), offset);
+        }
+    }
+    value * SIMPLEX_SCALE[N - 1]
+}
+
+// fractal Brownian motion: each octave samples the noise at lacunarity times the
+// frequency and gain times the amplitude of the one before
+#[derive(Clone, Copy, PartialEq)]
+pub struct Fbm {
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Fbm {
+            octaves: 4,
+            lacunarity: 2.0,
+            gain: 0.5
+        }
+    }
+}
+
+impl Fbm {
+    // the sum is divided by the total amplitude, so it stays in the range of noise
+    pub fn sample<const N: usize>(&self, noise: fn([f32; N]) -> f32, point: [f32; N]) -> f32 {
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut value = 0.0;
+        let mut total = 0.0;
+        for _ in 0..self.octaves {
+            value += amplitude * noise(point.map(|value| value * frequency));
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        if total > 0.0 { value / total } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // points on a grid of step 0.137 through -4..4 in every dimension
+    fn grid<const N: usize>(steps: usize) -> impl Iterator<Item = [f32; N]> {
+        (0..steps.pow(N as u32)).map(move |index| {
+            std::array::from_fn(|axis| (index / steps.pow(axis as u32) % steps) as f32 * 0.137 - 4.0)
+        })
+    }
+
+    fn largest<const N: usize>(noise: fn([f32; N]) -> f32, steps: usize) -> f32 {
+        grid::<N>(steps).map(|point| noise(point).abs()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn perlin_is_zero_on_the_grid() {
+        for x in -3..3 {
+            assert_eq!(perlin([x as f32]), 0.0);
+            for y in -3..3 {
+                assert_eq!(perlin([x as f32, y as f32]), 0.0);
+                assert_eq!(perlin([x as f32, y as f32, 7.0]), 0.0);
+                assert_eq!(perlin([x as f32, y as f32, -2.0, 5.0]), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn values_stay_roughly_within_one() {
+        let bounds = [
+            largest::<1>(perlin, 60), largest::<2>(perlin, 60), largest::<3>(perlin, 60), largest::<4>(perlin, 24),
+            largest::<1>(simplex, 60), largest::<2>(simplex, 60), largest::<3>(simplex, 60), largest::<4>(simplex, 24),
+        ];
+        for (index, bound) in bounds.iter().enumerate() {
+            assert!(*bound <= 1.1, "{} in {} dimensions reaches {bound}", if index < 4 { "perlin" } else { "simplex" }, index % 4 + 1);
+            //the scale is not so small that the values stay close to zero
+            assert!(*bound > 0.5, "{} in {} dimensions only reaches {bound}", if index < 4 { "perlin" } else { "simplex" }, index % 4 + 1);
+        }
+    }
+
+    #[test]
+    fn the_same_point_gives_the_same_value() {
+        for point in grid::<3>(10) {
+            assert_eq!(perlin(point), perlin(point));
+            assert_eq!(simplex(point), simplex(point));
+        }
+        assert_ne!(perlin([0.5, 0.5]), perlin([1.5, 0.5]));
+    }
+
+    #[test]
+    fn fbm_without_octaves_is_zero() {
+        let fbm = Fbm { octaves: 0, ..Fbm::default() };
+        assert_eq!(fbm.sample(perlin, [0.3, 0.7]), 0.0);
+        //one octave is the noise itself
+        let fbm = Fbm { octaves: 1, ..Fbm::default() };
+        assert_eq!(fbm.sample(simplex, [0.3, 0.7, 0.1]), simplex([0.3, 0.7, 0.1]));
+    }
+}
@@ -0,0 +1,262 @@
+use bevy::prelude::*;
+
+// Barnes-Hut octree: every node keeps the total mass and the center of mass of the
+// bodies below it. A node that looks small from a position (size / distance < theta)
+// pulls like a single body, so the attraction of all bodies costs O(N log N).
+pub struct Octree {
+    nodes: Vec<Node>,
+    bodies: Vec<(Vec3, f32)>
+}
+
+struct Node {
+    center: Vec3,
+    half_size: f32,
+    mass: f32,
+    //sum of position * mass, divided by mass it is the center of mass
+    weighted: Vec3,
+    //the eight children are stored one after another
+    children: Option<usize>,
+    //a leaf holds one body, at MAX_DEPTH all that landed in it
+    bodies: Vec<usize>
+}
+
+//bodies on the same spot would be split forever
+const MAX_DEPTH: u32 = 32;
+
+impl Node {
+    fn new(center: Vec3, half_size: f32) -> Self {
+        Node {
+            center,
+            half_size,
+            mass: 0.0,
+            weighted: Vec3::ZERO,
+            children: None,
+            bodies: Vec::new()
+        }
+    }
+
+    fn center_of_mass(&self) -> Vec3 {
+        self.weighted / self.mass
+    }
+
+    fn contains(&self, position: Vec3) -> bool {
+        (position - self.center).abs().max_element() <= self.half_size
+    }
+
+    fn octant(&self, position: Vec3) -> usize {
+        (position.x > self.center.x) as usize
+            | ((position.y > self.center.y) as usize) << 1
+            | ((position.z > self.center.z) as usize) << 2
+    }
+}
+
+impl Octree {
+    // bodies are (position, mass)
+    pub fn new(bodies: &[(Vec3, f32)]) -> Self {
+        let (min, max) = bodies.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), (position, _)| {
+            (min.min(*position), max.max(*position))
+        });
+        let (center, half_size) = if bodies.is_empty() {
+            (Vec3::ZERO, 1.0)
+        } else {
+            ((min + max) / 2.0, (max - min).max_element() / 2.0 + 0.001)
+        };
+
+        let mut octree = Octree {
+            nodes: vec![Node::new(center, half_size)],
+            bodies: bodies.to_vec()
+        };
+        for index in 0..bodies.len() {
+            octree.insert(index);
+        }
+        octree
+    }
+
+    fn insert(&mut self, index: usize) {
+        let (position, mass) = self.bodies[index];
+        let mut node = 0;
+        let mut depth = 0;
+
+        loop {
+            self.nodes[node].mass += mass;
+            self.nodes[node].weighted += position * mass;
+
+            let first = match self.nodes[node].children {
+                Some(first) => first,
+                None => {
+                    //an empty leaf takes the body, so does one that cannot be split
+                    if self.nodes[node].bodies.is_empty() || depth >= MAX_DEPTH {
+                        self.nodes[node].bodies.push(index);
+                        return;
+                    }
+                    //a full leaf is split and its body moves one level down
+                    let first = self.subdivide(node);
+                    for previous in std::mem::take(&mut self.nodes[node].bodies) {
+                        let (previous_position, previous_mass) = self.bodies[previous];
+                        let child = first + self.nodes[node].octant(previous_position);
+                        self.nodes[child].mass = previous_mass;
+                        self.nodes[child].weighted = previous_position * previous_mass;
+                        self.nodes[child].bodies.push(previous);
+                    }
+                    first
+                }
+            };
+            node = first + self.nodes[node].octant(position);
+            depth += 1;
+        }
+    }
+
+    fn subdivide(&mut self, node: usize) -> usize {
+        let first = self.nodes.len();
+        let half_size = self.nodes[node].half_size / 2.0;
+        let center = self.nodes[node].center;
+        for octant in 0..8 {
+            let sign = |bit: usize| if octant & bit != 0 { 1.0 } else { -1.0 };
+            let offset = Vec3::new(sign(1), sign(2), sign(4)) * half_size;
+            self.nodes.push(Node::new(center + offset, half_size));
+        }
+        self.nodes[node].children = Some(first);
+        first
+    }
+
+    // visits the nodes that act as one body seen from position, skipping the body
+    // with index skip; a node around position is always opened, its mass would
+    // include the body itself
+    fn visit(&self, position: Vec3, skip: usize, theta: f32, mut action: impl FnMut(f32, Vec3)) {
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.mass == 0.0 {
+                continue;
+            }
+            match node.children {
+                None => {
+                    for body in node.bodies.iter().filter(|body| **body != skip) {
+                        let (center, mass) = self.bodies[*body];
+                        action(mass, center);
+                    }
+                }
+                Some(first) => {
+                    let distance = node.center_of_mass().distance(position);
+                    if !node.contains(position) && 2.0 * node.half_size / distance < theta {
+                        action(node.mass, node.center_of_mass());
+                    } else {
+                        stack.extend(first..first + 8);
+                    }
+                }
+            }
+        }
+    }
+
+    // gravitational acceleration at the position of body index, softening keeps
+    // close encounters finite
+    pub fn acceleration(&self, position: Vec3, index: usize, theta: f32, gravity: f32, softening: f32) -> Vec3 {
+        let mut acceleration = Vec3::ZERO;
+        self.visit(position, index, theta, |mass, center| {
+            let offset = center - position;
+            let distance_squared = offset.length_squared() + softening * softening;
+            acceleration += gravity * mass * offset / (distance_squared * distance_squared.sqrt());
+        });
+        acceleration
+    }
+
+    // potential energy per unit mass at the position of body index
+    pub fn potential(&self, position: Vec3, index: usize, theta: f32, gravity: f32, softening: f32) -> f32 {
+        let mut potential = 0.0;
+        self.visit(position, index, theta, |mass, center| {
+            let distance_squared = center.distance_squared(position) + softening * softening;
+            potential -= gravity * mass / distance_squared.sqrt();
+        });
+        potential
+    }
+}
+
+// every body pulls every other one, O(N²), to compare with the octree
+pub fn direct_accelerations(bodies: &[(Vec3, f32)], gravity: f32, softening: f32) -> Vec<Vec3> {
+    bodies.iter().enumerate().map(|(index, (position, _))| {
+        bodies.iter().enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, (other_position, mass))| {
+                let offset = *other_position - *position;
+                let distance_squared = offset.length_squared() + softening * softening;
+                gravity * mass * offset / (distance_squared * distance_squared.sqrt())
+            })
+            .sum()
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const SOFTENING: f32 = 0.05;
+
+    fn bodies(count: usize) -> Vec<(Vec3, f32)> {
+        let mut rng = StdRng::seed_from_u64(41);
+        (0..count).map(|_| {
+            let position = Vec3::new(rng.random_range(-10.0..10.0), rng.random_range(-10.0..10.0), rng.random_range(-10.0..10.0));
+            (position, rng.random_range(0.1..2.0))
+        }).collect()
+    }
+
+    fn octree_accelerations(bodies: &[(Vec3, f32)], theta: f32) -> Vec<Vec3> {
+        let octree = Octree::new(bodies);
+        bodies.iter().enumerate()
+            .map(|(index, (position, _))| octree.acceleration(*position, index, theta, 1.0, SOFTENING))
+            .collect()
+    }
+
+    // the relative errors against the direct sum, one per body
+    fn errors(bodies: &[(Vec3, f32)], theta: f32) -> Vec<f32> {
+        let direct = direct_accelerations(bodies, 1.0, SOFTENING);
+        octree_accelerations(bodies, theta).iter().zip(&direct)
+            .map(|(octree, direct)| octree.distance(*direct) / direct.length())
+            .collect()
+    }
+
+    fn largest(errors: &[f32]) -> f32 {
+        errors.iter().copied().fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn theta_zero_is_the_direct_sum() {
+        let errors = errors(&bodies(200), 0.0);
+        assert!(largest(&errors) < 1e-4, "largest relative error {}", largest(&errors));
+    }
+
+    #[test]
+    fn theta_one_half_stays_close_to_the_direct_sum() {
+        let errors = errors(&bodies(500), 0.5);
+        let mean = errors.iter().sum::<f32>() / errors.len() as f32;
+        assert!(mean < 0.01, "mean relative error {mean}");
+        assert!(largest(&errors) < 0.1, "largest relative error {}", largest(&errors));
+    }
+
+    // the body in the corner sees the root 0.87 sizes away, below theta 1.5, but the
+    // root holds its own mass
+    #[test]
+    fn no_body_pulls_on_itself() {
+        let bodies = [(Vec3::ZERO, 1.0), (Vec3::splat(10.0), 1.0), (Vec3::new(10.0, 10.0, 9.9), 1.0)];
+        let direct = direct_accelerations(&bodies, 1.0, SOFTENING);
+        let acceleration = Octree::new(&bodies).acceleration(bodies[0].0, 0, 1.5, 1.0, SOFTENING);
+        assert!(acceleration.distance(direct[0]) < 0.01 * direct[0].length(), "{acceleration} instead of {}", direct[0]);
+    }
+
+    // bodies on the same spot and bodies without mass keep a place in the tree
+    #[test]
+    fn every_body_lands_in_a_leaf() {
+        let spot = Vec3::new(1.0, 2.0, 3.0);
+        let bodies = [(spot, 1.0), (spot, 2.0), (Vec3::ZERO, 0.0), (Vec3::new(0.1, 0.0, 0.0), 0.0), (Vec3::new(-4.0, 0.0, 0.0), 0.5)];
+        let octree = Octree::new(&bodies);
+        let mut leaves: Vec<usize> = octree.nodes.iter().flat_map(|node| node.bodies.iter().copied()).collect();
+        leaves.sort();
+        assert_eq!(leaves, (0..bodies.len()).collect::<Vec<_>>());
+        let direct = direct_accelerations(&bodies, 1.0, SOFTENING);
+        for (index, (position, _)) in bodies.iter().enumerate() {
+            let acceleration = octree.acceleration(*position, index, 0.0, 1.0, SOFTENING);
+            assert!(acceleration.distance(direct[index]) < 1e-4, "body {index}: {acceleration} instead of {}", direct[index]);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// shared by several examples, each one uses only a part of it
+#![allow(dead_code)]
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+// Simple harmonic motion: an Oscillator moves its entity around a center with one
+// sine per axis, offset = amplitude * sin(angle + phase), where the angle grows by
+// TAU * frequency every second so a new frequency carries on from the current angle.
+// A Wave lays out a row of spheres whose heights are the sum of travelling sine waves.
+// With a rest rotation the offset turns the entity instead, in radians per axis.
+pub struct OscillatorPlugin;
+
+impl Plugin for OscillatorPlugin {
+    fn build(&self, app: &mut App){
+        app.add_systems(Update, (oscillate,
+                                 update_waves));
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct Oscillator {
+    pub amplitude: Vec3,
+    //oscillations per second
+    pub frequency: Vec3,
+    //radians
+    pub phase: Vec3,
+    pub center: Vec3,
+    pub rotation: Option<Quat>,
+    //radians turned so far
+    angle: Vec3
+}
+
+impl Oscillator {
+    pub fn new(amplitude: Vec3, frequency: Vec3) -> Self {
+        Oscillator {
+            amplitude,
+            frequency,
+            phase: Vec3::ZERO,
+            center: Vec3::ZERO,
+            rotation: None,
+            angle: Vec3::ZERO
+        }
+    }
+
+    pub fn with_phase(mut self, phase: Vec3) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn with_center(mut self, center: Vec3) -> Self {
+        self.center = center;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = Some(rotation);
+        self
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        self.angle = (self.angle + TAU * self.frequency * dt) % TAU;
+    }
+
+    pub fn offset(&self) -> Vec3 {
+        let angle = self.angle + self.phase;
+        self.amplitude * Vec3::new(angle.x.sin(), angle.y.sin(), angle.z.sin())
+    }
+}
+
+fn oscillate(
+    time: Res<Time>,
+    mut query: Query<(&mut Transform, &mut Oscillator)>
+){
+    for (mut transform, mut oscillator) in query.iter_mut() {
+        oscillator.advance(time.delta_secs());
+        let offset = oscillator.offset();
+        match oscillator.rotation {
+            Some(rotation) => transform.rotation = rotation * Quat::from_euler(EulerRot::XYZ, offset.x, offset.y, offset.z),
+            None => transform.translation = oscillator.center + offset
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct WaveComponent {
+    pub amplitude: f32,
+    pub wavelength: f32,
+    //units per second the wave travels along x
+    pub speed: f32
+}
+
+#[derive(Component)]
+pub struct Wave {
+    pub components: Vec<WaveComponent>,
+    pub count: usize,
+    pub spacing: f32
+}
+
+// a sphere of a wave, a child of the wave entity
+#[derive(Component)]
+pub struct WaveBall {
+    index: usize
+}
+
+impl Wave {
+    pub fn height(&self, x: f32, time: f32) -> f32 {
+        self.components.iter()
+            .filter(|component| component.wavelength > 0.0)
+            .map(|component| component.amplitude * (TAU * (x - component.speed * time) / component.wavelength).sin())
+            .sum()
+    }
+
+    // the row is centered on the transform of the wave
+    fn x(&self, index: usize) -> f32 {
+        (index as f32 - (self.count as f32 - 1.0) / 2.0) * self.spacing
+    }
+
+    pub fn spawn(
+        self,
+        commands: &mut Commands,
+        mesh: Handle<Mesh>,
+        material: Handle<StandardMaterial>,
+        transform: Transform
+    ) -> Entity {
+        let count = self.count;
+        commands
+            .spawn((transform, Visibility::default(), self))
+            .with_children(|parent| {
+                for index in 0..count {
+                    parent.spawn((
+                        Mesh3d(mesh.clone()),
+                        MeshMaterial3d(material.clone()),
+                        Transform::default(),
+                        WaveBall { index }
+                    ));
+                }
+            })
+            .id()
+    }
+}
+
+fn update_waves(
+    time: Res<Time>,
+    waves: Query<(&Wave, &Children)>,
+    mut balls: Query<(&mut Transform, &WaveBall)>
+){
+    for (wave, children) in waves.iter() {
+        for child in children.iter() {
+            let Ok((mut transform, ball)) = balls.get_mut(*child) else {
+                continue;
+            };
+            let x = wave.x(ball.index);
+            transform.translation = Vec3::new(x, wave.height(x, time.elapsed_secs()), 0.0);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use bevy::asset::{io::Reader, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::collision::CollisionSettings;
+use super::curve::{Gradient, ScalarCurve};
+use super::event::ParticleTrigger;
+use super::shape::EmitterShape;
+
+// Emitter definitions live in assets/effects/*.emitter.ron and are hot reloaded
+// by the asset server, so effects can be tweaked while the example is running.
+#[derive(Asset, TypePath, Deserialize, Clone)]
+pub struct EmitterDefinition {
+    #[serde(default)]
+    pub rate: f32,
+    #[serde(default)]
+    pub burst: Option<Burst>,
+    pub lifetime: (f32, f32),
+    pub shape: EmitterShape,
+    pub speed: (f32, f32),
+    #[serde(default)]
+    pub forces: Vec<Vec3>,
+    pub color: Gradient,
+    pub size: ScalarCurve,
+    pub alpha: ScalarCurve,
+    #[serde(default)]
+    pub speed_multiplier: Option<ScalarCurve>,
+    #[serde(default)]
+    pub collision: Option<CollisionSettings>,
+    #[serde(default)]
+    pub sub_emitters: Vec<SubEmitter>,
+    #[serde(default)]
+    pub trail: Option<TrailSettings>,
+    #[serde(default)]
+    pub blend: ParticleBlend,
+    //camera facing quads instead of cubes
+    #[serde(default)]
+    pub billboard: Option<Billboard>,
+    //all particles in one mesh instead of one entity each
+    #[serde(default)]
+    pub batched: bool,
+    #[serde(default)]
+    pub capacity: Option<u32>,
+}
+
+impl EmitterDefinition {
+    // parses and checks a definition; meshes, textures and sub emitters are left to
+    // the asset loader, so this also works without an app
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, EmitterLoaderError> {
+        let mut definition: EmitterDefinition = ron::de::from_bytes(bytes)?;
+
+        //a negative or endless duration would panic in the timers
+        let valid = |value: f32| value.is_finite() && value >= 0.0;
+        for (name, (min, max)) in [("lifetime", definition.lifetime), ("speed", definition.speed)] {
+            if !valid(min) || !valid(max) {
+                return Err(EmitterLoaderError::Invalid(name));
+            }
+        }
+        if !valid(definition.rate) {
+            return Err(EmitterLoaderError::Invalid("rate"));
+        }
+        if definition.burst.is_some_and(|burst| !valid(burst.interval)) {
+            return Err(EmitterLoaderError::Invalid("burst interval"));
+        }
+
+        if definition.color.is_empty() {
+            return Err(EmitterLoaderError::EmptyCurve("color"));
+        }
+        definition.color.sort();
+        for (name, curve) in [("size", &mut definition.size),
+                              ("alpha", &mut definition.alpha)] {
+            if curve.is_empty() {
+                return Err(EmitterLoaderError::EmptyCurve(name));
+            }
+            curve.sort();
+        }
+        if let Some(curve) = &mut definition.speed_multiplier {
+            if curve.is_empty() {
+                return Err(EmitterLoaderError::EmptyCurve("speed_multiplier"));
+            }
+            curve.sort();
+        }
+        Ok(definition)
+    }
+
+    // size of the particle pool, by default enough for the longest living particles
+    pub fn capacity(&self) -> usize {
+        if let Some(capacity) = self.capacity {
+            return capacity as usize;
+        }
+        let max_lifetime = self.lifetime.0.max(self.lifetime.1);
+        let mut capacity = self.rate * max_lifetime;
+        if let Some(burst) = self.burst {
+            capacity += burst.count as f32 * (max_lifetime / burst.interval.max(0.001)).ceil();
+        }
+        capacity.ceil() as usize + 1
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+pub enum ParticleBlend {
+    #[default]
+    Alpha,
+    Additive
+}
+
+// the texture is an atlas of columns x rows frames, played cycles times over the
+// lifetime of a particle; softness is the depth over which a quad fades out where
+// it cuts into the scene
+#[derive(Deserialize, Clone)]
+pub struct Billboard {
+    pub texture: String,
+    #[serde(default = "one")]
+    pub columns: u32,
+    #[serde(default = "one")]
+    pub rows: u32,
+    #[serde(default = "cycle")]
+    pub cycles: f32,
+    #[serde(default)]
+    pub softness: f32,
+    #[serde(skip)]
+    pub image: Handle<Image>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn cycle() -> f32 {
+    1.0
+}
+
+impl Billboard {
+    pub fn frames(&self) -> u32 {
+        self.columns.max(1) * self.rows.max(1)
+    }
+
+    // atlas frame shown at that part of the lifetime
+    pub fn frame(&self, factor: f32) -> u32 {
+        let total = (self.frames() as f32 * self.cycles.max(0.0)).ceil().max(1.0);
+        ((factor * total).floor()).min(total - 1.0) as u32 % self.frames()
+    }
+
+    // offset and size of that frame in the atlas, in uv coordinates
+    pub fn frame_rect(&self, factor: f32) -> (Vec2, Vec2) {
+        let columns = self.columns.max(1);
+        let frame = self.frame(factor);
+        let size = Vec2::new(1.0 / columns as f32, 1.0 / self.rows.max(1) as f32);
+        (Vec2::new((frame % columns) as f32, (frame / columns) as f32) * size, size)
+    }
+}
+
+// a trail behind every particle, a line strip or with a width a ribbon
+#[derive(Deserialize, Clone, Copy)]
+pub struct TrailSettings {
+    pub length: usize,
+    #[serde(default = "trail_spacing")]
+    pub spacing: f32,
+    #[serde(default)]
+    pub width: Option<f32>,
+}
+
+fn trail_spacing() -> f32 {
+    0.1
+}
+
+// on a trigger, count particles of another effect are emitted where the particle was
+#[derive(Deserialize, Clone)]
+pub struct SubEmitter {
+    pub trigger: ParticleTrigger,
+    pub effect: String,
+    pub count: u32,
+    //part of the particle's velocity the new particles take along
+    #[serde(default)]
+    pub inherit_velocity: f32,
+    #[serde(skip)]
+    pub definition: Handle<EmitterDefinition>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct Burst {
+    pub interval: f32,
+    pub count: u32,
+}
+
+pub fn random_in(range: (f32, f32)) -> f32 {
+    if range.0 < range.1 {
+        rand::rng().random_range(range.0..range.1)
+    } else {
+        range.0
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum EmitterLoaderError {
+    #[error("could not read emitter definition: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse emitter definition: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("curve `{0}` needs at least one keyframe")]
+    EmptyCurve(&'static str),
+    #[error("`{0}` must be finite and not negative")]
+    Invalid(&'static str),
+    #[error("`{0}` cannot be its own sub emitter")]
+    OwnSubEmitter(String),
+}
+
+#[derive(Default)]
+pub struct EmitterDefinitionLoader;
+
+impl AssetLoader for EmitterDefinitionLoader {
+    type Asset = EmitterDefinition;
+    type Settings = ();
+    type Error = EmitterLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut definition = EmitterDefinition::from_ron(&bytes)?;
+
+        if let EmitterShape::MeshSurface { path, mesh } = &mut definition.shape {
+            *mesh = load_context.load(path.clone());
+        }
+        if let Some(billboard) = &mut definition.billboard {
+            billboard.image = load_context.load(billboard.texture.clone());
+        }
+        for sub_emitter in definition.sub_emitters.iter_mut() {
+            //its children would spawn children of their own without end
+            if std::path::Path::new(&sub_emitter.effect) == load_context.path() {
+                return Err(EmitterLoaderError::OwnSubEmitter(sub_emitter.effect.clone()));
+            }
+            sub_emitter.definition = load_context.load(sub_emitter.effect.clone());
+        }
+        Ok(definition)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["emitter.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a definition with the fields that have no default, the rest is put in front
+    fn definition(fields: &str) -> String {
+        format!(r#"(
+            {fields}
+            shape: Sphere(radius: 1.0),
+            color: (keys: [(0.0, (red: 1.0, green: 1.0, blue: 1.0, alpha: 1.0))]),
+            size: (keys: [(0.0, 0.1)]),
+            alpha: (keys: [(0.0, 1.0)]),
+        )"#)
+    }
+
+    fn from_ron(fields: &str) -> Result<EmitterDefinition, EmitterLoaderError> {
+        EmitterDefinition::from_ron(definition(fields).as_bytes())
+    }
+
+    #[test]
+    fn a_valid_definition_is_read() {
+        let definition = from_ron("rate: 20.0, lifetime: (0.5, 1.0), speed: (0.0, 2.0),").expect("definition");
+        assert_eq!(definition.lifetime, (0.5, 1.0));
+        assert_eq!(definition.rate, 20.0);
+        //20 per second living up to a second
+        assert_eq!(definition.capacity(), 21);
+    }
+
+    #[test]
+    fn negative_ranges_and_rates_are_rejected() {
+        for (fields, name) in [
+            ("lifetime: (-0.5, 1.0), speed: (1.0, 1.0),", "lifetime"),
+            ("lifetime: (1.0, inf), speed: (1.0, 1.0),", "lifetime"),
+            ("lifetime: (1.0, 1.0), speed: (1.0, -2.0),", "speed"),
+            ("lifetime: (1.0, 1.0), speed: (NaN, 1.0),", "speed"),
+            ("rate: -1.0, lifetime: (1.0, 1.0), speed: (1.0, 1.0),", "rate"),
+            ("burst: Some((interval: inf, count: 5)), lifetime: (1.0, 1.0), speed: (1.0, 1.0),", "burst interval"),
+        ] {
+            match from_ron(fields) {
+                Err(EmitterLoaderError::Invalid(invalid)) => assert_eq!(invalid, name, "{fields}"),
+                Err(error) => panic!("{fields}: {error}"),
+                Ok(_) => panic!("{fields} was accepted"),
+            }
+        }
+    }
+
+    #[test]
+    fn curves_need_keyframes() {
+        let text = definition("lifetime: (1.0, 1.0), speed: (1.0, 1.0),").replace("size: (keys: [(0.0, 0.1)])", "size: (keys: [])");
+        assert!(matches!(EmitterDefinition::from_ron(text.as_bytes()), Err(EmitterLoaderError::EmptyCurve("size"))));
+    }
+
+    #[test]
+    fn keyframes_are_sorted() {
+        let text = definition("lifetime: (1.0, 1.0), speed: (1.0, 1.0),").replace("alpha: (keys: [(0.0, 1.0)])", "alpha: (keys: [(1.0, 0.0), (0.0, 1.0)])");
+        let definition = EmitterDefinition::from_ron(text.as_bytes()).expect("definition");
+        assert_eq!(definition.alpha.sample(0.0), 1.0);
+        assert_eq!(definition.alpha.sample(1.0), 0.0);
+    }
+
+    #[test]
+    fn broken_ron_is_an_error() {
+        assert!(matches!(EmitterDefinition::from_ron(b"(rate: )"), Err(EmitterLoaderError::Ron(_))));
+    }
+}
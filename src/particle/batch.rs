@@ -0,0 +1,292 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::NoFrustumCulling;
+
+use super::asset::{random_in, Billboard, EmitterDefinition};
+use super::field::{Fields, ForceField};
+use super::material::ParticleMaterial;
+use super::shape::SurfaceSampler;
+use super::ParicleEmiter;
+
+// Particles of a batched emitter are no entities, they live in these arrays and are
+// drawn as camera facing quads of a single mesh that is rebuilt every frame.
+// Batched particles do not collide, send no events and have no trails.
+#[derive(Default)]
+pub struct ParticleBuffer {
+    pub positions: Vec<Vec3>,
+    pub velocities: Vec<Vec3>,
+    pub ages: Vec<f32>,
+    pub lifetimes: Vec<f32>,
+    pub colors: Vec<LinearRgba>,
+    pub sizes: Vec<f32>,
+}
+
+impl ParticleBuffer {
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // emits count particles from the shape placed at transform, up to the capacity
+    // of the definition if it has one
+    pub fn emit(
+        &mut self,
+        definition: &EmitterDefinition,
+        surface: Option<&SurfaceSampler>,
+        transform: &Transform,
+        velocity: Vec3,
+        count: u32,
+    ) {
+        let capacity = definition.capacity.map_or(usize::MAX, |capacity| capacity as usize);
+        let color = LinearRgba::from(definition.color.sample(0.0)
+            .with_alpha(definition.alpha.sample(0.0)));
+
+        for _ in 0..count {
+            if self.len() >= capacity {
+                break;
+            }
+            let Some((position, direction)) = definition.shape.sample(surface) else {
+                break;
+            };
+            self.positions.push(transform.transform_point(position));
+            self.velocities.push(transform.rotation * direction * random_in(definition.speed) + velocity);
+            self.ages.push(0.0);
+            self.lifetimes.push(random_in(definition.lifetime));
+            self.colors.push(color);
+            self.sizes.push(definition.size.sample(0.0));
+        }
+    }
+
+    pub fn update(&mut self, definition: &EmitterDefinition, fields: &[ForceField], delta: f32) {
+        let force: Vec3 = definition.forces.iter().sum();
+        let mut index = 0;
+
+        while index < self.len() {
+            self.ages[index] += delta;
+            if self.ages[index] >= self.lifetimes[index] {
+                self.swap_remove(index);
+                continue;
+            }
+            let factor = self.ages[index] / self.lifetimes[index];
+            let position = self.positions[index];
+            let acceleration = force + fields.iter()
+                .map(|field| field.force_at(position))
+                .sum::<Vec3>();
+            let speed_multiplier = definition.speed_multiplier.as_ref()
+                .map_or(1.0, |curve| curve.sample(factor));
+
+            self.velocities[index] += acceleration * delta;
+            self.positions[index] += self.velocities[index] * speed_multiplier * delta;
+            self.colors[index] = LinearRgba::from(definition.color.sample(factor)
+                .with_alpha(definition.alpha.sample(factor)));
+            self.sizes[index] = definition.size.sample(factor);
+            index += 1;
+        }
+    }
+
+    fn swap_remove(&mut self, index: usize) {
+        self.positions.swap_remove(index);
+        self.velocities.swap_remove(index);
+        self.ages.swap_remove(index);
+        self.lifetimes.swap_remove(index);
+        self.colors.swap_remove(index);
+        self.sizes.swap_remove(index);
+    }
+
+    // four vertices and six indices per particle, the quads face along the camera
+    // rotation; with a billboard the uvs pick the flipbook frame of each particle
+    pub fn fill_mesh(&self, mesh: &mut Mesh, camera_rotation: Quat, billboard: Option<&Billboard>) {
+        let right = camera_rotation * Vec3::X;
+        let up = camera_rotation * Vec3::Y;
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+        let mut positions = Vec::with_capacity(self.len() * 4);
+        let mut colors = Vec::with_capacity(self.len() * 4);
+        let mut uvs = Vec::with_capacity(self.len() * 4);
+        let mut indices = Vec::with_capacity(self.len() * 6);
+
+        for index in 0..self.len() {
+            let half_size = self.sizes[index] / 2.0;
+            let (offset, size) = billboard.map_or((Vec2::ZERO, Vec2::ONE), |billboard| {
+                billboard.frame_rect(self.ages[index] / self.lifetimes[index])
+            });
+            let first = positions.len() as u32;
+
+            for (x, y) in corners {
+                let corner = self.positions[index] + (right * x + up * y) * half_size;
+                positions.push(corner.to_array());
+                colors.push(self.colors[index].to_f32_array());
+                //uv y points down the texture
+                uvs.push((offset + size * Vec2::new((x + 1.0) / 2.0, (1.0 - y) / 2.0)).to_array());
+            }
+            indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.insert_indices(Indices::U32(indices));
+    }
+
+    pub fn to_mesh(&self, camera_rotation: Quat, billboard: Option<&Billboard>) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+        self.fill_mesh(&mut mesh, camera_rotation, billboard);
+        mesh
+    }
+}
+
+// the mesh entity and particle arrays of a batched emiter
+pub struct ParticleBatch {
+    pub buffer: ParticleBuffer,
+    mesh: Handle<Mesh>,
+    pub(super) material: Handle<ParticleMaterial>,
+    pub(super) entity: Entity
+}
+
+// the mesh is in world space, so it is no child of the emiter
+#[derive(Component)]
+pub struct BatchMesh {
+    pub(super) emiter: Entity
+}
+
+impl ParticleBatch {
+    pub fn spawn(
+        commands: &mut Commands,
+        emiter: Entity,
+        meshes: &mut Assets<Mesh>,
+        material: Handle<ParticleMaterial>
+    ) -> Self {
+        let mesh = meshes.add(ParticleBuffer::default().to_mesh(Quat::IDENTITY, None));
+        let entity = commands.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material.clone()),
+            Transform::default(),
+            Visibility::Hidden,
+            //the bounds change every frame
+            NoFrustumCulling,
+            BatchMesh { emiter }
+        )).id();
+        ParticleBatch {
+            buffer: ParticleBuffer::default(),
+            mesh,
+            material,
+            entity
+        }
+    }
+}
+
+pub fn update_batches(
+    time: Res<Time>,
+    definitions: Res<Assets<EmitterDefinition>>,
+    fields: Fields,
+    mut query: Query<&mut ParicleEmiter>
+){
+    let fields = fields.collect();
+
+    for mut emiter in query.iter_mut() {
+        let Some(definition) = definitions.get(&emiter.definition) else {
+            continue;
+        };
+        if let Some(batch) = &mut emiter.batch {
+            batch.buffer.update(definition, &fields, time.delta_secs());
+        }
+    }
+}
+
+pub fn build_batch_meshes(
+    definitions: Res<Assets<EmitterDefinition>>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<&ParicleEmiter>,
+    mut visibilities: Query<&mut Visibility, With<BatchMesh>>
+){
+    let camera_rotation = camera.get_single().map_or(Quat::IDENTITY, |camera| camera.rotation());
+
+    for emiter in query.iter() {
+        let (Some(batch), Some(definition)) = (&emiter.batch, definitions.get(&emiter.definition)) else {
+            continue;
+        };
+        let Ok(mut visibility) = visibilities.get_mut(batch.entity) else {
+            continue;
+        };
+        if batch.buffer.is_empty() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        if let Some(mesh) = meshes.get_mut(&batch.mesh) {
+            batch.buffer.fill_mesh(mesh, camera_rotation, definition.billboard.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every particle lives exactly half a second
+    const DEFINITION: &str = r#"(
+        lifetime: (0.5, 0.5),
+        shape: Sphere(radius: 1.0),
+        speed: (1.0, 1.0),
+        color: (keys: [(0.0, (red: 1.0, green: 1.0, blue: 1.0, alpha: 1.0))]),
+        size: (keys: [(0.0, 0.1)]),
+        alpha: (keys: [(0.0, 1.0), (1.0, 0.0)]),
+        batched: true,
+        capacity: Some(1000),
+    )"#;
+
+    fn definition() -> EmitterDefinition {
+        EmitterDefinition::from_ron(DEFINITION.as_bytes()).expect("definition")
+    }
+
+    fn counts(buffer: &ParticleBuffer) -> (usize, usize) {
+        let mesh = buffer.to_mesh(Quat::IDENTITY, None);
+        (mesh.count_vertices(), mesh.indices().map_or(0, |indices| indices.len()))
+    }
+
+    #[test]
+    fn four_vertices_and_six_indices_per_particle() {
+        let definition = definition();
+        let mut buffer = ParticleBuffer::default();
+        buffer.emit(&definition, None, &Transform::IDENTITY, Vec3::ZERO, 100);
+        buffer.update(&definition, &[], 0.1);
+
+        assert_eq!(buffer.len(), 100);
+        assert_eq!(counts(&buffer), (400, 600));
+        let mesh = buffer.to_mesh(Quat::IDENTITY, None);
+        assert!(mesh.indices().expect("indices").iter().all(|index| index < 400));
+    }
+
+    #[test]
+    fn expired_particles_leave_the_mesh() {
+        let definition = definition();
+        let mut buffer = ParticleBuffer::default();
+        buffer.emit(&definition, None, &Transform::IDENTITY, Vec3::ZERO, 100);
+        buffer.update(&definition, &[], 0.3);
+        buffer.emit(&definition, None, &Transform::IDENTITY, Vec3::ZERO, 50);
+        assert_eq!(counts(&buffer), (600, 900));
+
+        //the first 100 are 0.6 s old now, the other 50 only 0.3 s
+        buffer.update(&definition, &[], 0.3);
+        assert_eq!(buffer.len(), 50);
+        assert_eq!(counts(&buffer), (200, 300));
+
+        buffer.update(&definition, &[], 0.3);
+        assert!(buffer.is_empty());
+        assert_eq!(counts(&buffer), (0, 0));
+    }
+
+    #[test]
+    fn emission_stops_at_the_capacity() {
+        let definition = definition();
+        let mut buffer = ParticleBuffer::default();
+        buffer.emit(&definition, None, &Transform::IDENTITY, Vec3::ZERO, 1500);
+        assert_eq!(buffer.len(), 1000);
+        assert_eq!(counts(&buffer), (4000, 6000));
+    }
+}
@@ -0,0 +1,121 @@
+// shared by several examples, each one uses only a part of it
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+// restitution keeps that part of the speed into the surface, friction takes that
+// part of the speed along the surface
+#[derive(Deserialize, Clone, Copy)]
+pub struct CollisionSettings {
+    #[serde(default)]
+    pub restitution: f32,
+    #[serde(default)]
+    pub friction: f32,
+    //collide with the inside of the limit cube
+    #[serde(default = "enabled")]
+    pub bounds: bool,
+    //also collide with rapier colliders, if the rapier plugin is in the app
+    #[serde(default)]
+    pub rapier: bool,
+    //the particle dies on its first collision
+    #[serde(default)]
+    pub kill: bool
+}
+
+// slower hits are particles resting on a surface, they send no collision events
+pub const MIN_IMPACT_SPEED: f32 = 0.5;
+
+fn enabled() -> bool {
+    true
+}
+
+impl CollisionSettings {
+    pub fn bounce(&self, velocity: Vec3, normal: Vec3) -> Vec3 {
+        let normal_speed = velocity.dot(normal);
+        if normal_speed >= 0.0 {
+            return velocity;
+        }
+        let normal_velocity = normal * normal_speed;
+        let tangent_velocity = velocity - normal_velocity;
+        tangent_velocity * (1.0 - self.friction) - normal_velocity * self.restitution
+    }
+}
+
+// the limit cube of the example, particles with bounds collide with it from inside;
+// set by ParticlePlugin
+#[derive(Resource, Clone, Copy)]
+pub struct ParticleBounds {
+    pub min: Vec3,
+    pub max: Vec3
+}
+
+impl ParticleBounds {
+    pub fn cube(min: f32, max: f32) -> Self {
+        ParticleBounds {
+            min: Vec3::splat(min),
+            max: Vec3::splat(max)
+        }
+    }
+}
+
+// a plane is solid behind its normal, a sphere is solid inside
+#[derive(Component)]
+pub enum ParticleCollider {
+    Plane { normal: Vec3 },
+    Sphere { radius: f32 }
+}
+
+pub enum CollisionSurface {
+    Plane { point: Vec3, normal: Vec3 },
+    Sphere { center: Vec3, radius: f32 }
+}
+
+impl CollisionSurface {
+    pub fn collect(colliders: &Query<(&ParticleCollider, &GlobalTransform)>) -> Vec<CollisionSurface> {
+        colliders.iter().map(|(collider, transform)| match collider {
+            ParticleCollider::Plane { normal } => CollisionSurface::Plane {
+                point: transform.translation(),
+                normal: (transform.rotation() * *normal).normalize_or(Vec3::Y)
+            },
+            ParticleCollider::Sphere { radius } => CollisionSurface::Sphere {
+                center: transform.translation(),
+                radius: *radius
+            }
+        }).collect()
+    }
+
+    // the six walls of the limit cube, facing inwards
+    pub fn bounds(bounds: &ParticleBounds) -> [CollisionSurface; 6] {
+        let ParticleBounds { min, max } = *bounds;
+        [CollisionSurface::Plane { point: min, normal: Vec3::X },
+         CollisionSurface::Plane { point: max, normal: Vec3::NEG_X },
+         CollisionSurface::Plane { point: min, normal: Vec3::Y },
+         CollisionSurface::Plane { point: max, normal: Vec3::NEG_Y },
+         CollisionSurface::Plane { point: min, normal: Vec3::Z },
+         CollisionSurface::Plane { point: max, normal: Vec3::NEG_Z }]
+    }
+
+    // corrected position and contact normal if a particle of that radius is inside
+    pub fn resolve(&self, position: Vec3, radius: f32) -> Option<(Vec3, Vec3)> {
+        match self {
+            CollisionSurface::Plane { point, normal } => {
+                let depth = (position - *point).dot(*normal) - radius;
+                if depth < 0.0 {
+                    Some((position - *normal * depth, *normal))
+                } else {
+                    None
+                }
+            }
+            CollisionSurface::Sphere { center, radius: sphere_radius } => {
+                let offset = position - *center;
+                let distance = offset.length();
+                if distance >= sphere_radius + radius {
+                    return None;
+                }
+                let normal = offset.try_normalize().unwrap_or(Vec3::Y);
+                Some((*center + normal * (sphere_radius + radius), normal))
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::color::{Mix, Srgba};
+use serde::Deserialize;
+
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self * (1.0 - t) + other * t
+    }
+}
+
+impl Lerp for Srgba {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self.mix(&other, t)
+    }
+}
+
+// how the value moves from one keyframe to the next
+#[derive(Deserialize, Clone, Copy, Default)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Step,
+    SmoothStep,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Interpolation {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Interpolation::Linear => t,
+            Interpolation::Step => 0.0,
+            Interpolation::SmoothStep => t * t * (3.0 - 2.0 * t),
+            Interpolation::EaseIn => t * t,
+            Interpolation::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Interpolation::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0) * (-2.0 * t + 2.0) / 2.0
+                }
+            }
+        }
+    }
+}
+
+// (time, value) pairs, time is the lifetime factor between 0.0 and 1.0
+#[derive(Deserialize, Clone)]
+pub struct Keyframes<T> {
+    pub keys: Vec<(f32, T)>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+pub type Gradient = Keyframes<Srgba>;
+pub type ScalarCurve = Keyframes<f32>;
+
+impl<T: Lerp> Keyframes<T> {
+    pub fn sample(&self, t: f32) -> T {
+        let keys = &self.keys;
+        let first = keys[0];
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in keys.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            if t < end.0 {
+                let span = end.0 - start.0;
+                let factor = if span > 0.0 { (t - start.0) / span } else { 1.0 };
+                return start.1.lerp(end.1, self.interpolation.apply(factor));
+            }
+        }
+        keys[keys.len() - 1].1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn sort(&mut self) {
+        self.keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+}
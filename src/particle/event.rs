@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{EmitterDefinition, ParicleEmiter};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParticleTrigger {
+    Birth,
+    Death,
+    Collision
+}
+
+// sent for every particle that is born, dies or bounces off a collider, so gameplay
+// systems can react; normal is the contact normal of a collision and Vec3::Y otherwise
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ParticleEvent {
+    pub trigger: ParticleTrigger,
+    pub emiter: Entity,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub normal: Vec3
+}
+
+pub fn trigger_sub_emiters(
+    mut events: EventReader<ParticleEvent>,
+    definitions: Res<Assets<EmitterDefinition>>,
+    mut emiters: Query<&mut ParicleEmiter>
+){
+    for event in events.read() {
+        let Ok(emiter) = emiters.get(event.emiter) else {
+            continue;
+        };
+        let Some(definition) = definitions.get(&emiter.definition) else {
+            continue;
+        };
+        let children = emiter.sub_emiters.clone();
+
+        for (sub_emiter, child) in definition.sub_emitters.iter().zip(children) {
+            if sub_emiter.trigger != event.trigger {
+                continue;
+            }
+            if let Ok(mut child) = emiters.get_mut(child) {
+                //the shape of the child points along the collision normal
+                let transform = Transform::from_translation(event.position)
+                    .with_rotation(Quat::from_rotation_arc(Vec3::Y, event.normal));
+                child.burst(transform, event.velocity * sub_emiter.inherit_velocity, sub_emiter.count);
+            }
+        }
+    }
+}
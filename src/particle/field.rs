@@ -0,0 +1,170 @@
+use bevy::color::palettes::css::{DARK_CYAN, MAGENTA, ORANGE, RED};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use std::sync::Arc;
+
+// closer than this the inverse square law would throw particles away
+const MIN_DISTANCE: f32 = 1.0;
+
+// pulls particles within radius with strength / distance²
+#[derive(Component)]
+pub struct Attractor {
+    pub strength: f32,
+    pub radius: f32
+}
+
+// pushes particles within radius away with strength / distance²
+#[derive(Component)]
+pub struct Repeller {
+    pub strength: f32,
+    pub radius: f32
+}
+
+// uniform force inside a box around the entity
+#[derive(Component)]
+pub struct WindZone {
+    pub force: Vec3,
+    pub half_extents: Vec3
+}
+
+// spins particles around an axis through the entity, pull draws them to the axis
+#[derive(Component)]
+pub struct Vortex {
+    pub axis: Vec3,
+    pub strength: f32,
+    pub pull: f32,
+    pub radius: f32
+}
+
+// a force blowing everywhere, like the wind of chapter 2; the particle module does not
+// know where it comes from, an example that has one keeps it up to date, see four
+#[derive(Resource, Clone, Default)]
+pub struct GlobalForce(pub Option<Arc<dyn Fn(Vec3) -> Vec3 + Send + Sync>>);
+
+#[derive(Resource)]
+pub struct FieldGizmos {
+    pub enabled: bool
+}
+
+// a snapshot of all fields for one frame, so each particle does not query the world
+pub enum ForceField {
+    Inverse { center: Vec3, strength: f32, radius: f32 },
+    Wind { center: Vec3, force: Vec3, half_extents: Vec3 },
+    Vortex { center: Vec3, axis: Vec3, strength: f32, pull: f32, radius: f32 },
+    //the global force blowing everywhere
+    Global(Arc<dyn Fn(Vec3) -> Vec3 + Send + Sync>)
+}
+
+impl ForceField {
+    pub fn force_at(&self, position: Vec3) -> Vec3 {
+        match self {
+            ForceField::Inverse { center, strength, radius } => {
+                let offset = *center - position;
+                let distance = offset.length();
+                if distance > *radius || distance == 0.0 {
+                    return Vec3::ZERO;
+                }
+                let distance = distance.max(MIN_DISTANCE);
+                offset.normalize() * strength / (distance * distance)
+            }
+            ForceField::Wind { center, force, half_extents } => {
+                let local = (position - *center).abs();
+                if local.cmple(*half_extents).all() {
+                    *force
+                } else {
+                    Vec3::ZERO
+                }
+            }
+            ForceField::Vortex { center, axis, strength, pull, radius } => {
+                let offset = position - *center;
+                let radial = offset - *axis * offset.dot(*axis);
+                let distance = radial.length();
+                if distance > *radius || distance == 0.0 {
+                    return Vec3::ZERO;
+                }
+                let falloff = 1.0 - distance / radius;
+                let tangent = axis.cross(radial).normalize();
+                (tangent * *strength - radial.normalize() * *pull) * falloff
+            }
+            ForceField::Global(force) => force(position)
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct Fields<'w, 's> {
+    attractors: Query<'w, 's, (&'static Attractor, &'static GlobalTransform)>,
+    repellers: Query<'w, 's, (&'static Repeller, &'static GlobalTransform)>,
+    winds: Query<'w, 's, (&'static WindZone, &'static GlobalTransform)>,
+    vortices: Query<'w, 's, (&'static Vortex, &'static GlobalTransform)>,
+    global: Res<'w, GlobalForce>,
+}
+
+impl Fields<'_, '_> {
+    pub fn collect(&self) -> Vec<ForceField> {
+        let mut fields = Vec::new();
+        for (attractor, transform) in self.attractors.iter() {
+            fields.push(ForceField::Inverse {
+                center: transform.translation(),
+                strength: attractor.strength,
+                radius: attractor.radius
+            });
+        }
+        for (repeller, transform) in self.repellers.iter() {
+            fields.push(ForceField::Inverse {
+                center: transform.translation(),
+                strength: -repeller.strength,
+                radius: repeller.radius
+            });
+        }
+        for (wind, transform) in self.winds.iter() {
+            fields.push(ForceField::Wind {
+                center: transform.translation(),
+                force: wind.force,
+                half_extents: wind.half_extents
+            });
+        }
+        for (vortex, transform) in self.vortices.iter() {
+            fields.push(ForceField::Vortex {
+                center: transform.translation(),
+                axis: vortex.axis.normalize_or(Vec3::Y),
+                strength: vortex.strength,
+                pull: vortex.pull,
+                radius: vortex.radius
+            });
+        }
+        if let Some(force) = &self.global.0 {
+            fields.push(ForceField::Global(force.clone()));
+        }
+        fields
+    }
+}
+
+pub fn draw_fields(
+    mut gizmos: Gizmos,
+    field_gizmos: Res<FieldGizmos>,
+    fields: Fields
+){
+    if !field_gizmos.enabled {
+        return;
+    }
+    for (attractor, transform) in fields.attractors.iter() {
+        gizmos.sphere(Isometry3d::from_translation(transform.translation()), attractor.radius, MAGENTA);
+    }
+    for (repeller, transform) in fields.repellers.iter() {
+        gizmos.sphere(Isometry3d::from_translation(transform.translation()), repeller.radius, RED);
+    }
+    for (wind, transform) in fields.winds.iter() {
+        let center = transform.translation();
+        gizmos.cuboid(Transform::from_translation(center).with_scale(wind.half_extents * 2.0), DARK_CYAN);
+        gizmos.arrow(center, center + wind.force.normalize_or_zero() * wind.half_extents.min_element(), DARK_CYAN);
+    }
+    for (vortex, transform) in fields.vortices.iter() {
+        let center = transform.translation();
+        let axis = vortex.axis.normalize_or(Vec3::Y);
+        let rotation = Quat::from_rotation_arc(Vec3::Z, axis);
+        gizmos.circle(Isometry3d::new(center, rotation), vortex.radius, ORANGE);
+        gizmos.arrow(center, center + axis * vortex.radius / 2.0, ORANGE);
+    }
+}
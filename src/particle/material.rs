@@ -0,0 +1,21 @@
+use bevy::pbr::{ExtendedMaterial, MaterialExtension};
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+
+const SHADER_PATH: &str = "shaders/particle.wgsl";
+
+// the standard material plus a soft fade where a particle is close to the scene
+// behind it; the fade needs a DepthPrepass on the camera
+pub type ParticleMaterial = ExtendedMaterial<StandardMaterial, SoftParticle>;
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct SoftParticle {
+    #[uniform(100)]
+    pub softness: f32,
+}
+
+impl MaterialExtension for SoftParticle {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+}
@@ -0,0 +1,509 @@
+// shared by several examples, each one uses only a part of it
+#![allow(dead_code)]
+
+use bevy::prelude::*;
+
+pub use asset::EmitterDefinition;
+use asset::{random_in, EmitterDefinitionLoader};
+use batch::{build_batch_meshes, update_batches, BatchMesh, ParticleBatch};
+use bevy_rapier3d::plugin::{NoUserData, RapierPhysicsPlugin};
+use collision::{CollisionSurface, ParticleBounds, ParticleCollider, MIN_IMPACT_SPEED};
+use event::{trigger_sub_emiters, ParticleEvent, ParticleTrigger};
+use field::{draw_fields, FieldGizmos, Fields, GlobalForce};
+use material::ParticleMaterial;
+use pool::{batch_material, ParticleMesh, ParticlePool};
+use shape::SurfaceSampler;
+
+mod asset;
+pub mod batch;
+pub mod collision;
+mod curve;
+pub mod event;
+pub mod field;
+mod material;
+mod pool;
+mod rapier;
+mod shape;
+
+// bounds is the limit cube of the example, the particles do not know its constants
+pub struct ParticlePlugin {
+    pub bounds: ParticleBounds
+}
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App){
+        app
+            .insert_resource(self.bounds)
+            .init_asset::<EmitterDefinition>()
+            .init_asset_loader::<EmitterDefinitionLoader>()
+            .add_plugins(MaterialPlugin::<ParticleMaterial>::default())
+            .init_resource::<ParticleMesh>()
+            .insert_resource(FieldGizmos{ enabled: true })
+            .init_resource::<GlobalForce>()
+            .add_event::<ParticleEvent>()
+            .add_systems(Update, (allocate_pools,
+                                  refresh_palettes,
+                                  emit_particles,
+                                  update_particle_lifetime,
+                                  moving,
+                                  update_batches,
+                                  collide_particles,
+                                  update_particle_appearance,
+                                  build_batch_meshes,
+                                  trigger_sub_emiters).chain())
+            .add_systems(Update, draw_fields);
+    }
+
+    fn finish(&self, app: &mut App){
+        if app.is_plugin_added::<RapierPhysicsPlugin<NoUserData>>() {
+            app.add_systems(Update, rapier::collide_with_rapier
+                .after(collide_particles)
+                .before(update_particle_appearance));
+        }
+    }
+}
+
+// the trail an emitter definition asks for behind each of its particles; the particle
+// module draws none itself, an example with a TrailPlugin turns it into a Trail, see four
+#[derive(Component, Clone, Copy)]
+pub struct ParticleTrail {
+    pub length: usize,
+    pub spacing: f32,
+    //a ribbon of that width, else a line
+    pub width: Option<f32>,
+    pub color: Color
+}
+
+// particles are never spawned or despawned while their emitter lives, they are
+// taken from and returned to the emitter's pool
+#[derive(Component)]
+pub struct Particle {
+    velocity:Vec3,
+    acceleration: Vec3,
+    previous_position: Vec3,
+    lifetime:Timer,
+    emiter: Entity,
+    active: bool
+}
+
+impl Particle {
+    fn apply_force(&mut self, force:Vec3){
+        self.acceleration += force;
+    }
+
+    fn get_factor(&self)->f32{
+        self.lifetime.fraction()
+    }
+
+    // a killed particle is returned to the pool by the next lifetime update
+    fn kill(&mut self){
+        let duration = self.lifetime.duration();
+        self.lifetime.set_elapsed(duration);
+    }
+
+    fn alive(&self)->bool{
+        self.active && self.lifetime.elapsed() < self.lifetime.duration()
+    }
+
+    fn event(&self, trigger: ParticleTrigger, position: Vec3, normal: Vec3) -> ParticleEvent {
+        ParticleEvent {
+            trigger,
+            emiter: self.emiter,
+            position,
+            velocity: self.velocity,
+            normal
+        }
+    }
+}
+
+// particles an emiter still owes, in world space
+struct PendingBurst {
+    transform: Transform,
+    velocity: Vec3,
+    count: u32
+}
+
+#[derive(Component)]
+pub struct ParicleEmiter {
+    pub definition: Handle<EmitterDefinition>,
+    spawn_time:Timer,
+    rate_accumulator: f32,
+    pool: ParticlePool,
+    surface: Option<SurfaceSampler>,
+    //a triggered emiter only emits the bursts it is asked for
+    triggered: bool,
+    pending: Vec<PendingBurst>,
+    sub_emiters: Vec<Entity>,
+    parent: Option<Entity>,
+    batch: Option<ParticleBatch>
+}
+
+impl ParicleEmiter {
+    pub fn new(definition: Handle<EmitterDefinition>) -> Self {
+        ParicleEmiter {
+            definition,
+            spawn_time: Timer::default(),
+            rate_accumulator: 0.0,
+            pool: ParticlePool::default(),
+            surface: None,
+            triggered: false,
+            pending: Vec::new(),
+            sub_emiters: Vec::new(),
+            parent: None,
+            batch: None
+        }
+    }
+
+    pub fn triggered(definition: Handle<EmitterDefinition>) -> Self {
+        ParicleEmiter {
+            triggered: true,
+            ..ParicleEmiter::new(definition)
+        }
+    }
+
+    // emits count particles from the shape placed at transform, velocity is added
+    // to each of them; used by sub emiters and gameplay code like explosions
+    pub fn burst(&mut self, transform: Transform, velocity: Vec3, count: u32) {
+        self.pending.push(PendingBurst { transform, velocity, count });
+    }
+
+    // how many particles are due this frame; re-reads the definition so hot reloaded
+    // rates and burst intervals take effect immediately
+    fn particles_due(&mut self, definition: &EmitterDefinition, time: &Time) -> u32 {
+        self.rate_accumulator += definition.rate * time.delta_secs();
+        let mut amount = self.rate_accumulator.floor();
+        self.rate_accumulator -= amount;
+
+        if let Some(burst) = definition.burst {
+            let interval = std::time::Duration::from_secs_f32(burst.interval.max(0.001));
+            if self.spawn_time.duration() != interval {
+                self.spawn_time = Timer::new(interval, TimerMode::Repeating);
+            }
+            self.spawn_time.tick(time.delta());
+            amount += (self.spawn_time.times_finished_this_tick() * burst.count) as f32;
+        }
+        amount as u32
+    }
+
+    fn update_surface(&mut self, definition: &EmitterDefinition, meshes: &Assets<Mesh>) {
+        let Some(handle) = definition.shape.mesh() else {
+            self.surface = None;
+            return;
+        };
+        if self.surface.as_ref().is_some_and(|surface| surface.mesh == handle.id()) {
+            return;
+        }
+        self.surface = meshes.get(handle)
+            .and_then(|mesh| SurfaceSampler::from_mesh(handle.id(), mesh));
+    }
+}
+
+fn allocate_pools(
+    mut commands:Commands,
+    definitions: Res<Assets<EmitterDefinition>>,
+    mesh: Res<ParticleMesh>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<(Entity, &mut ParicleEmiter)>,
+    batches: Query<(Entity, &BatchMesh)>
+){
+    //sub emiters and batch meshes go with the emiter that owns them
+    for (entity, emiter) in query.iter() {
+        if emiter.parent.is_some_and(|parent| !query.contains(parent)) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for (entity, batch) in batches.iter() {
+        if !query.contains(batch.emiter) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    //a hot reload can add, remove or swap the effects of the sub emiters
+    let stale: Vec<Entity> = query.iter()
+        .filter(|(_, emiter)| definitions.get(&emiter.definition).is_some_and(|definition| {
+            emiter.sub_emiters.len() != definition.sub_emitters.len()
+                || definition.sub_emitters.iter().zip(&emiter.sub_emiters).any(|(sub_emitter, child)| {
+                    query.get(*child).map_or(true, |(_, child)| child.definition != sub_emitter.definition)
+                })
+        }))
+        .map(|(entity, _)| entity)
+        .collect();
+
+    for (entity, mut emiter) in query.iter_mut(){
+        let Some(definition) = definitions.get(&emiter.definition) else {
+            continue;
+        };
+        if stale.contains(&entity) {
+            for child in emiter.sub_emiters.drain(..) {
+                commands.entity(child).despawn_recursive();
+            }
+            for sub_emitter in definition.sub_emitters.iter() {
+                let mut child = ParicleEmiter::triggered(sub_emitter.definition.clone());
+                child.parent = Some(entity);
+                let child = commands.spawn((child, Transform::default())).id();
+                emiter.sub_emiters.push(child);
+            }
+        }
+
+        if definition.batched != emiter.batch.is_some() {
+            match emiter.batch.take() {
+                Some(batch) => commands.entity(batch.entity).despawn_recursive(),
+                None => {
+                    let material = materials.add(batch_material(definition));
+                    emiter.batch = Some(ParticleBatch::spawn(&mut commands, entity, &mut meshes, material));
+                }
+            }
+        }
+        if definition.batched {
+            continue;
+        }
+
+        let pool = &mut emiter.pool;
+        if pool.palette.is_empty() {
+            pool.fill_palette(definition, &mut materials);
+        }
+        while pool.allocated < definition.capacity() + pool.extra {
+            let mut particle = commands.spawn((
+                Mesh3d(mesh.get(definition).clone()),
+                MeshMaterial3d(pool.palette[0].clone()),
+                Transform::default(),
+                Visibility::Hidden,
+                Particle {
+                    velocity: Vec3::ZERO,
+                    acceleration: Vec3::ZERO,
+                    previous_position: Vec3::ZERO,
+                    lifetime: Timer::default(),
+                    emiter: entity,
+                    active: false
+                }
+            ));
+            if let Some(settings) = definition.trail {
+                let color = definition.color.sample(0.0).with_alpha(definition.alpha.sample(0.0));
+                particle.insert(ParticleTrail {
+                    length: settings.length,
+                    spacing: settings.spacing,
+                    width: settings.width,
+                    color: Color::Srgba(color)
+                });
+            }
+            pool.free.push(particle.id());
+            pool.allocated += 1;
+        }
+    }
+}
+
+fn refresh_palettes(
+    mut events: EventReader<AssetEvent<EmitterDefinition>>,
+    definitions: Res<Assets<EmitterDefinition>>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+    mut query: Query<&mut ParicleEmiter>
+){
+    for event in events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        let Some(definition) = definitions.get(*id) else {
+            continue;
+        };
+        for mut emiter in query.iter_mut() {
+            if emiter.definition.id() == *id {
+                emiter.pool.fill_palette(definition, &mut materials);
+                if let Some(material) = emiter.batch.as_ref().and_then(|batch| materials.get_mut(&batch.material)) {
+                    *material = batch_material(definition);
+                }
+            }
+        }
+    }
+}
+
+fn emit_particles(
+    time: Res<Time>,
+    definitions: Res<Assets<EmitterDefinition>>,
+    meshes: Res<Assets<Mesh>>,
+    mut events: EventWriter<ParticleEvent>,
+    mut query: Query<(&mut ParicleEmiter, &Transform), Without<Particle>>,
+    mut particles: Query<(&mut Particle, &mut Transform, &mut Visibility)>
+){
+    for (mut emiter, transform) in query.iter_mut(){
+        let Some(definition) = definitions.get(&emiter.definition) else {
+            continue;
+        };
+        let emiter = &mut *emiter;
+        emiter.update_surface(definition, &meshes);
+
+        let mut bursts = std::mem::take(&mut emiter.pending);
+        if !emiter.triggered {
+            let count = emiter.particles_due(definition, &time);
+            bursts.push(PendingBurst { transform: *transform, velocity: Vec3::ZERO, count });
+        }
+
+        for burst in bursts {
+            if let Some(batch) = &mut emiter.batch {
+                batch.buffer.emit(definition, emiter.surface.as_ref(), &burst.transform, burst.velocity, burst.count);
+                continue;
+            }
+            for emitted in 0..burst.count {
+                let Some((position, direction)) = definition.shape.sample(emiter.surface.as_ref()) else {
+                    break;
+                };
+                let Some(entity) = emiter.pool.free.pop() else {
+                    //grow the pool, so the next burst of that size fits
+                    emiter.pool.extra += (burst.count - emitted) as usize;
+                    break;
+                };
+                let Ok((mut particle, mut particle_transform, mut visibility)) = particles.get_mut(entity) else {
+                    continue;
+                };
+                *particle_transform = Transform::from_translation(burst.transform.transform_point(position))
+                    .with_scale(Vec3::splat(definition.size.sample(0.0)));
+                *visibility = Visibility::Inherited;
+                particle.lifetime = Timer::from_seconds(random_in(definition.lifetime),TimerMode::Once);
+                particle.velocity = burst.transform.rotation * direction * random_in(definition.speed) + burst.velocity;
+                particle.acceleration = Vec3::ZERO;
+                particle.previous_position = particle_transform.translation;
+                particle.active = true;
+                events.send(particle.event(ParticleTrigger::Birth, particle_transform.translation, Vec3::Y));
+            }
+        }
+    }
+}
+
+fn update_particle_lifetime(
+    mut commands:Commands,
+    mut events: EventWriter<ParticleEvent>,
+    mut query: Query<(Entity, &mut Particle, &Transform, &mut Visibility)>,
+    mut emiters: Query<&mut ParicleEmiter>,
+    time: Res<Time>,
+) {
+    for (entity, mut particle, transform, mut visibility) in query.iter_mut() {
+        let emiter = emiters.get_mut(particle.emiter);
+        if !particle.active {
+            //the emiter is gone, so nobody will use this particle again
+            if emiter.is_err() {
+                commands.entity(entity).despawn_recursive();
+            }
+            continue;
+        }
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            particle.active = false;
+            *visibility = Visibility::Hidden;
+            events.send(particle.event(ParticleTrigger::Death, transform.translation, Vec3::Y));
+            if let Ok(mut emiter) = emiter {
+                emiter.pool.free.push(entity);
+            }
+        }
+    }
+}
+
+fn moving(
+    time:Res<Time>,
+    definitions: Res<Assets<EmitterDefinition>>,
+    emiters: Query<&ParicleEmiter>,
+    fields: Fields,
+    mut query: Query<(&mut Transform, &mut Particle)>
+){
+    let fields = fields.collect();
+
+    for (mut transform, mut particle) in &mut query {
+        if !particle.active {
+            continue;
+        }
+        let definition = emiters.get(particle.emiter).ok()
+            .and_then(|emiter| definitions.get(&emiter.definition));
+        if let Some(definition) = definition {
+            for force in definition.forces.iter() {
+                particle.apply_force(*force);
+            }
+        }
+        for field in fields.iter() {
+            particle.apply_force(field.force_at(transform.translation));
+        }
+        let speed_multiplier = definition
+            .and_then(|definition| definition.speed_multiplier.as_ref())
+            .map_or(1.0, |curve| curve.sample(particle.get_factor()));
+        let acceleration = particle.acceleration;
+        particle.previous_position = transform.translation;
+        particle.velocity += acceleration * time.delta_secs();
+        transform.translation += particle.velocity * speed_multiplier * time.delta_secs();
+        particle.acceleration = Vec3::ZERO;
+    }
+}
+
+fn collide_particles(
+    mut events: EventWriter<ParticleEvent>,
+    definitions: Res<Assets<EmitterDefinition>>,
+    emiters: Query<&ParicleEmiter>,
+    colliders: Query<(&ParticleCollider, &GlobalTransform)>,
+    particle_bounds: Res<ParticleBounds>,
+    mut query: Query<(&mut Transform, &mut Particle)>
+){
+    let surfaces = CollisionSurface::collect(&colliders);
+    let bounds = CollisionSurface::bounds(&particle_bounds);
+
+    for (mut transform, mut particle) in &mut query {
+        if !particle.alive() {
+            continue;
+        }
+        let settings = emiters.get(particle.emiter).ok()
+            .and_then(|emiter| definitions.get(&emiter.definition))
+            .and_then(|definition| definition.collision);
+        let Some(settings) = settings else {
+            continue;
+        };
+        let radius = transform.scale.max_element() / 2.0;
+        let walls = if settings.bounds { &bounds[..] } else { &[] };
+
+        for surface in surfaces.iter().chain(walls) {
+            if let Some((position, normal)) = surface.resolve(transform.translation, radius) {
+                transform.translation = position;
+                if particle.velocity.dot(normal) < -MIN_IMPACT_SPEED {
+                    events.send(particle.event(ParticleTrigger::Collision, position, normal));
+                }
+                particle.velocity = settings.bounce(particle.velocity, normal);
+                if settings.kill {
+                    particle.kill();
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn update_particle_appearance(
+    definitions: Res<Assets<EmitterDefinition>>,
+    emiters: Query<&ParicleEmiter>,
+    mesh: Res<ParticleMesh>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    mut query: Query<(&Particle, &mut Transform, &mut Mesh3d, &mut MeshMaterial3d<ParticleMaterial>)>
+) {
+    let camera_rotation = camera.get_single().map_or(Quat::IDENTITY, |camera| camera.rotation());
+
+    for (particle, mut transform, mut particle_mesh, mut material) in query.iter_mut() {
+        if !particle.active {
+            continue;
+        }
+        let Ok(emiter) = emiters.get(particle.emiter) else {
+            continue;
+        };
+        let Some(definition) = definitions.get(&emiter.definition) else {
+            continue;
+        };
+        let factor = particle.get_factor();
+        let handle = emiter.pool.material(factor);
+        if material.0 != *handle {
+            material.0 = handle.clone();
+        }
+        transform.scale = Vec3::splat(definition.size.sample(factor));
+
+        //a hot reload may switch between cubes and billboards
+        let handle = mesh.get(definition);
+        if particle_mesh.0 != *handle {
+            particle_mesh.0 = handle.clone();
+        }
+        if definition.billboard.is_some() {
+            transform.rotation = camera_rotation;
+        }
+    }
+}
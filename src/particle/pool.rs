@@ -0,0 +1,113 @@
+use bevy::math::Affine2;
+use bevy::prelude::*;
+
+use super::asset::{EmitterDefinition, ParticleBlend};
+use super::material::{ParticleMaterial, SoftParticle};
+
+// colour, alpha and the flipbook frame only depend on the lifetime factor, so every
+// emitter shares a small palette of materials sampled along its curves instead of
+// one per particle
+pub const PALETTE_STEPS: usize = 32;
+
+#[derive(Resource)]
+pub struct ParticleMesh {
+    pub cube: Handle<Mesh>,
+    pub quad: Handle<Mesh>
+}
+
+impl FromWorld for ParticleMesh {
+    fn from_world(world: &mut World) -> Self {
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        ParticleMesh {
+            cube: meshes.add(Mesh::from(Cuboid::new(1.0, 1.0, 1.0))),
+            quad: meshes.add(Mesh::from(Rectangle::new(1.0, 1.0)))
+        }
+    }
+}
+
+impl ParticleMesh {
+    pub fn get(&self, definition: &EmitterDefinition) -> &Handle<Mesh> {
+        if definition.billboard.is_some() {
+            &self.quad
+        } else {
+            &self.cube
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ParticlePool {
+    pub allocated: usize,
+    //particles that were asked for while the pool was empty, the pool grows by that
+    pub extra: usize,
+    pub free: Vec<Entity>,
+    pub palette: Vec<Handle<ParticleMaterial>>,
+}
+
+impl ParticlePool {
+    pub fn material(&self, factor: f32) -> &Handle<ParticleMaterial> {
+        let index = (factor.clamp(0.0, 1.0) * (self.palette.len() - 1) as f32).round() as usize;
+        &self.palette[index]
+    }
+
+    pub fn fill_palette(
+        &mut self,
+        definition: &EmitterDefinition,
+        materials: &mut Assets<ParticleMaterial>,
+    ) {
+        //a flipbook needs at least one step per frame
+        let steps = definition.billboard.as_ref().map_or(PALETTE_STEPS, |billboard| {
+            PALETTE_STEPS.max((billboard.frames() as f32 * billboard.cycles).ceil() as usize)
+        });
+        self.palette.truncate(steps);
+
+        for step in 0..steps {
+            let factor = step as f32 / (steps - 1) as f32;
+            let material = palette_material(definition, factor);
+
+            match self.palette.get(step).and_then(|handle| materials.get_mut(handle)) {
+                Some(existing) => *existing = material,
+                None => self.palette.push(materials.add(material)),
+            }
+        }
+    }
+}
+
+// a batched emitter has one material, colour and flipbook frame are in its vertices
+pub fn batch_material(definition: &EmitterDefinition) -> ParticleMaterial {
+    let mut material = palette_material(definition, 0.0);
+    material.base.base_color = Color::WHITE;
+    material.base.uv_transform = Affine2::IDENTITY;
+    material.base.unlit = true;
+    material
+}
+
+fn palette_material(definition: &EmitterDefinition, factor: f32) -> ParticleMaterial {
+    let base_color = Color::Srgba(definition.color.sample(factor)
+        .with_alpha(definition.alpha.sample(factor)));
+    let alpha_mode = match definition.blend {
+        ParticleBlend::Alpha => AlphaMode::Blend,
+        ParticleBlend::Additive => AlphaMode::Add
+    };
+    let mut base = StandardMaterial {
+        base_color,
+        alpha_mode,
+        ..default()
+    };
+    let mut softness = 0.0;
+
+    if let Some(billboard) = &definition.billboard {
+        let (offset, size) = billboard.frame_rect(factor);
+
+        base.base_color_texture = Some(billboard.image.clone());
+        base.uv_transform = Affine2::from_scale_angle_translation(size, 0.0, offset);
+        base.unlit = true;
+        base.double_sided = true;
+        base.cull_mode = None;
+        softness = billboard.softness;
+    }
+    ParticleMaterial {
+        base,
+        extension: SoftParticle { softness }
+    }
+}
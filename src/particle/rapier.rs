@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::collision::MIN_IMPACT_SPEED;
+use super::event::{ParticleEvent, ParticleTrigger};
+use super::{EmitterDefinition, ParicleEmiter, Particle};
+
+// casts a ray along the way a particle moved this frame, so fast particles do not
+// tunnel through thin rapier colliders
+pub fn collide_with_rapier(
+    rapier_context: ReadRapierContext,
+    mut events: EventWriter<ParticleEvent>,
+    definitions: Res<Assets<EmitterDefinition>>,
+    emiters: Query<&ParicleEmiter>,
+    mut query: Query<(&mut Transform, &mut Particle)>
+){
+    if rapier_context.rapier_context.is_empty() {
+        return;
+    }
+    let context = rapier_context.single();
+
+    for (mut transform, mut particle) in query.iter_mut() {
+        if !particle.alive() {
+            continue;
+        }
+        let settings = emiters.get(particle.emiter).ok()
+            .and_then(|emiter| definitions.get(&emiter.definition))
+            .and_then(|definition| definition.collision)
+            .filter(|settings| settings.rapier);
+        let Some(settings) = settings else {
+            continue;
+        };
+
+        let movement = transform.translation - particle.previous_position;
+        let distance = movement.length();
+        if distance == 0.0 {
+            continue;
+        }
+        let radius = transform.scale.max_element() / 2.0;
+        let direction = movement / distance;
+        let hit = context.cast_ray_and_get_normal(particle.previous_position,
+                                                  direction,
+                                                  distance + radius,
+                                                  true,
+                                                  QueryFilter::default().exclude_sensors());
+        //a particle that starts inside a collider hits it at once without a normal,
+        //it is left to fly out instead of freezing there
+        if let Some((_, hit)) = hit.filter(|(_, hit)| hit.normal != Vec3::ZERO) {
+            transform.translation = hit.point + hit.normal * radius;
+            if particle.velocity.dot(hit.normal) < -MIN_IMPACT_SPEED {
+                events.send(particle.event(ParticleTrigger::Collision, transform.translation, hit.normal));
+            }
+            particle.velocity = settings.bounce(particle.velocity, hit.normal);
+            if settings.kill {
+                particle.kill();
+            }
+        }
+    }
+}
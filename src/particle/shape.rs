@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use rand::Rng;
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+// where particles are born and in which direction they start, in the emiter's local
+// space; angles are in degrees
+#[derive(Deserialize, Clone)]
+pub enum EmitterShape {
+    Point {
+        direction: Vec3,
+        spread: f32,
+    },
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_extents: Vec3,
+        direction: Vec3,
+        spread: f32,
+    },
+    Cone {
+        radius: f32,
+        angle: f32,
+    },
+    Disk {
+        radius: f32,
+    },
+    MeshSurface {
+        path: String,
+        #[serde(skip)]
+        mesh: Handle<Mesh>,
+    },
+}
+
+impl EmitterShape {
+    // returns position and normalized direction, None while a mesh surface is not loaded yet
+    pub fn sample(&self, surface: Option<&SurfaceSampler>) -> Option<(Vec3, Vec3)> {
+        let mut rng = rand::rng();
+        let emission = match self {
+            EmitterShape::Point { direction, spread } => {
+                (Vec3::ZERO, random_in_cone(*direction, *spread))
+            }
+            EmitterShape::Sphere { radius } => {
+                let direction = random_in_cone(Vec3::Y, 180.0);
+                let distance = radius * rng.random_range(0.0..=1.0_f32).cbrt();
+                (direction * distance, direction)
+            }
+            EmitterShape::Box { half_extents, direction, spread } => {
+                let position = Vec3::new(
+                    rng.random_range(-1.0..=1.0) * half_extents.x,
+                    rng.random_range(-1.0..=1.0) * half_extents.y,
+                    rng.random_range(-1.0..=1.0) * half_extents.z,
+                );
+                (position, random_in_cone(*direction, *spread))
+            }
+            EmitterShape::Cone { radius, angle } => {
+                if *radius <= 0.0 {
+                    (Vec3::ZERO, random_in_cone(Vec3::Y, *angle))
+                } else {
+                    //the further from the axis a particle starts, the more it leans outwards
+                    let (position, distance) = random_in_disk(*radius);
+                    let outwards = position.normalize_or_zero();
+                    let tilt = angle.to_radians() * distance / radius;
+                    (position, Vec3::Y * tilt.cos() + outwards * tilt.sin())
+                }
+            }
+            EmitterShape::Disk { radius } => {
+                let (position, _) = random_in_disk(*radius);
+                (position, Vec3::Y)
+            }
+            EmitterShape::MeshSurface { .. } => surface?.sample(),
+        };
+        Some(emission)
+    }
+
+    pub fn mesh(&self) -> Option<&Handle<Mesh>> {
+        match self {
+            EmitterShape::MeshSurface { mesh, .. } => Some(mesh),
+            _ => None,
+        }
+    }
+}
+
+pub fn random_in_cone(axis: Vec3, spread: f32) -> Vec3 {
+    let mut rng = rand::rng();
+    let axis = axis.normalize_or(Vec3::Y);
+    let cos_spread = spread.clamp(0.0, 180.0).to_radians().cos();
+    let z = rng.random_range(cos_spread..=1.0);
+    let phi = rng.random_range(0.0..TAU);
+    let radius = (1.0 - z * z).sqrt();
+    let local = Vec3::new(radius * phi.cos(), radius * phi.sin(), z);
+    Quat::from_rotation_arc(Vec3::Z, axis) * local
+}
+
+// uniform point on a disk in the xz plane and its distance to the center
+fn random_in_disk(radius: f32) -> (Vec3, f32) {
+    let mut rng = rand::rng();
+    let distance = radius * rng.random_range(0.0..=1.0_f32).sqrt();
+    let phi = rng.random_range(0.0..TAU);
+    (Vec3::new(distance * phi.cos(), 0.0, distance * phi.sin()), distance)
+}
+
+// triangles of a mesh with their accumulated area, so bigger triangles get more particles
+pub struct SurfaceSampler {
+    pub mesh: AssetId<Mesh>,
+    triangles: Vec<[Vec3; 3]>,
+    accumulated_area: Vec<f32>,
+}
+
+impl SurfaceSampler {
+    pub fn from_mesh(id: AssetId<Mesh>, mesh: &Mesh) -> Option<Self> {
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            return None;
+        };
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|i| *i as usize).collect(),
+            Some(Indices::U32(indices)) => indices.iter().map(|i| *i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let mut triangles = Vec::new();
+        let mut accumulated_area = Vec::new();
+        let mut total = 0.0;
+        for triangle in indices.chunks_exact(3) {
+            let corners = [Vec3::from(positions[triangle[0]]),
+                           Vec3::from(positions[triangle[1]]),
+                           Vec3::from(positions[triangle[2]])];
+            let area = (corners[1] - corners[0]).cross(corners[2] - corners[0]).length() / 2.0;
+            if area <= 0.0 {
+                continue;
+            }
+            total += area;
+            triangles.push(corners);
+            accumulated_area.push(total);
+        }
+        if triangles.is_empty() {
+            return None;
+        }
+        Some(SurfaceSampler {
+            mesh: id,
+            triangles,
+            accumulated_area,
+        })
+    }
+
+    fn sample(&self) -> (Vec3, Vec3) {
+        let mut rng = rand::rng();
+        let total = self.accumulated_area[self.accumulated_area.len() - 1];
+        let pick = rng.random_range(0.0..total);
+        let index = self.accumulated_area.partition_point(|area| *area < pick)
+            .min(self.triangles.len() - 1);
+        let [a, b, c] = self.triangles[index];
+
+        let r1 = rng.random_range(0.0..=1.0_f32).sqrt();
+        let r2 = rng.random_range(0.0..=1.0);
+        let position = a * (1.0 - r1) + b * (r1 * (1.0 - r2)) + c * (r1 * r2);
+        let normal = (b - a).cross(c - a).normalize();
+        (position, normal)
+    }
+}
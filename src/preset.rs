@@ -0,0 +1,104 @@
+// shared by several examples, each one uses only a part of it
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// The weights of the steering behaviours and the limits of a flocking agent, written
+// by the flocking evolution of chapter 9 as assets/presets/*.preset.ron. The chapter 5
+// examples load it at startup and keep their own values without one.
+pub const PRESET_PATH: &str = "assets/presets/flocking.preset.ron";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct SteeringPreset {
+    pub align: f32,
+    pub separate: f32,
+    pub cohesion: f32,
+    pub seek: f32,
+    pub maximum_speed: f32,
+    pub maximum_force: f32,
+    //the fitness the preset reached and after how many generations
+    #[serde(default)]
+    pub fitness: f32,
+    #[serde(default)]
+    pub generation: usize
+}
+
+#[derive(Debug, Error)]
+pub enum PresetError {
+    #[error("could not access preset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse preset: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not write preset: {0}")]
+    Write(#[from] ron::Error)
+}
+
+impl SteeringPreset {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PresetError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PresetError> {
+        let path = path.as_ref();
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    // a missing file is no error, the example just keeps its own values
+    pub fn load_optional(path: &str) -> Option<Self> {
+        match Self::load(path) {
+            Ok(preset) => Some(preset),
+            Err(PresetError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => None,
+            Err(error) => {
+                bevy::log::warn!("{path}: {error}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset() -> SteeringPreset {
+        SteeringPreset {
+            align: 1.5,
+            separate: 2.25,
+            cohesion: 0.75,
+            seek: 0.5,
+            maximum_speed: 6.0,
+            maximum_force: 12.0,
+            fitness: 0.8,
+            generation: 30
+        }
+    }
+
+    #[test]
+    fn a_saved_preset_is_read_back_unchanged() {
+        let path = std::env::temp_dir().join(format!("preset-{}", std::process::id())).join("flocking.preset.ron");
+        preset().save(&path).expect("preset written");
+        assert_eq!(SteeringPreset::load(&path).expect("preset read"), preset());
+        std::fs::remove_dir_all(path.parent().expect("directory")).expect("cleaned up");
+    }
+
+    #[test]
+    fn fitness_and_generation_are_optional() {
+        let text = "(align: 1.0, separate: 2.0, cohesion: 3.0, seek: 4.0, maximum_speed: 5.0, maximum_force: 6.0)";
+        let preset: SteeringPreset = ron::from_str(text).expect("preset");
+        assert_eq!((preset.fitness, preset.generation), (0.0, 0));
+    }
+
+    #[test]
+    fn a_missing_preset_is_no_error() {
+        assert_eq!(SteeringPreset::load_optional("assets/presets/missing.preset.ron"), None);
+    }
+}
@@ -0,0 +1,104 @@
+// shared by several examples, each one uses only a part of it
+#![allow(dead_code)]
+
+use bevy::prelude::Vec3;
+
+// Axis aligned boxes and rays with the slab test, used by the fighters of chapter 5
+// to see the cruisers and by the sensors of the vehicles in chapter 10.
+pub struct AabBox {
+    pub min:Vec3,
+    pub max:Vec3
+}
+
+impl AabBox {
+    pub fn new(origin:Vec3, x_length:f32, y_length:f32, z_lenght:f32, distance:f32)->Self{
+        AabBox{
+            min: Vec3::new(
+                origin.x-x_length/2.0-distance,
+                origin.y-y_length/2.0-distance,
+                origin.z-z_lenght/2.0-distance
+            ),
+            max:Vec3::new(
+                origin.x+x_length/2.0+distance,
+                origin.y+y_length/2.0+distance,
+                origin.z+z_lenght/2.0+distance
+            )
+        }
+    }
+}
+
+pub struct Ray{
+    pub origin:Vec3,
+    pub direction:Vec3
+}
+
+impl Ray {
+    pub fn new(orign:Vec3, direction:Vec3)->Self{
+        Ray {
+            origin:orign,
+            direction
+        }
+    }
+
+    pub fn intersect_box(&self, aabb:&AabBox)->Option<(Vec3,Vec3)>{
+        let mut tmin = (aabb.min.x - self.origin.x) / self.direction.x;
+        let mut tmax = (aabb.max.x - self.origin.x) / self.direction.x;
+
+        if tmin > tmax {
+            std::mem::swap(&mut tmin, &mut tmax);
+        }
+
+        let mut tymin = (aabb.min.y - self.origin.y) / self.direction.y;
+        let mut tymax = (aabb.max.y - self.origin.y) / self.direction.y;
+
+        if tymin > tymax {
+            std::mem::swap(&mut tymin, &mut tymax);
+        }
+
+        if tmin > tymax || tymin > tmax {
+            return None
+        };
+
+        if tymin > tmin {
+            tmin = tymin;
+        }
+
+        if tymax < tmax {
+            tmax = tymax;
+        }
+
+        let mut tzmin = (aabb.min.z - self.origin.z) / self.direction.z;
+        let mut tzmax = (aabb.max.z - self.origin.z) / self.direction.z;
+
+        if tzmin > tzmax {
+            std::mem::swap(&mut tzmin, &mut tzmax);
+        }
+
+        if tmin > tzmax || tzmin > tmax {
+            return None;
+        }
+
+        if tzmin > tmin {
+            tmin = tzmin;
+        }
+
+        if tzmax < tmax {
+            tmax = tzmax;
+        }
+
+        Some((Vec3::new(tmin,tymin,tzmin),Vec3::new(tmax,tymax,tzmax)))
+    }
+
+    // the distance along the ray to the first surface of the box in front of the
+    // origin; from inside the box that is the way out. The direction has to be normalized.
+    pub fn hit_distance(&self, aabb:&AabBox)->Option<f32>{
+        let (entry, exit) = self.intersect_box(aabb)?;
+        if entry.x >= 0.0 {
+            Some(entry.x)
+        } else if exit.x >= 0.0 {
+            Some(exit.x)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::VecDeque;
+
+use bevy::color::palettes::basic::{BLUE, RED};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use automata::{Elementary, Life3d, LifeRule};
+use rand::Rng;
+
+mod orbitcamera;
+mod mesh;
+mod automata;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 8.0;
+const MIN_LIMIT: f32 = -8.0;
+
+const SCENES: [&str; 2] = ["Wolfram 1D", "Life 3D"];
+
+//cells of the elementary automaton and the generations shown below each other
+const ROW_WIDTH: usize = 64;
+const HISTORY: usize = 64;
+//cells along each side of the 3D automaton
+const SIZE: usize = 24;
+
+// start patterns of the 3D automaton; the oscillators come back after period steps
+struct Pattern {
+    name: &'static str,
+    rule: &'static str,
+    cells: &'static [[usize; 3]],
+    period: usize
+}
+
+const PATTERNS: [Pattern; 3] = [
+    Pattern { name: "Random", rule: "B5/S45", cells: &[], period: 0 },
+    Pattern { name: "Tetrahedron (period 2)", rule: "B4/S4", cells: &[[0, 0, 1], [0, 1, 0], [1, 0, 0], [1, 1, 1]], period: 2 },
+    Pattern {
+        name: "Ten cells (period 4)",
+        rule: "B5/S45",
+        cells: &[[0, 0, 0], [0, 0, 1], [0, 1, 0], [0, 1, 1], [1, 0, 0], [1, 0, 2], [1, 1, 0], [1, 1, 2], [2, 0, 1], [2, 1, 1]],
+        period: 4
+    },
+];
+
+#[derive(Resource)]
+struct UiValues{
+    scene: usize,
+    restart: bool,
+    running: bool,
+    step: bool,
+    steps_per_second: f32,
+    //wolfram
+    rule_number: u8,
+    //life
+    pattern: usize,
+    rule: String,
+    rule_error: Option<String>,
+    density: f32,
+    wrap: bool
+}
+
+#[derive(Resource)]
+struct Automata {
+    elementary: Elementary,
+    //newest generation first
+    history: VecDeque<Vec<bool>>,
+    life: Life3d,
+    generation: u32,
+    //seconds since the last step
+    elapsed: f32
+}
+
+#[derive(Component)]
+struct SevenScene;
+
+// the cell of generation row (0 is the newest) and column of the elementary automaton
+#[derive(Component)]
+struct RowCell {
+    row: usize,
+    column: usize
+}
+
+#[derive(Component)]
+struct LifeCell(usize);
+
+fn main() {
+    //cargo run --example seven -- --headless
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless();
+        return;
+    }
+
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(UiValues{
+            scene: 0,
+            restart: true,
+            running: true,
+            step: false,
+            steps_per_second: 10.0,
+            rule_number: 90,
+            pattern: 0,
+            rule: PATTERNS[0].rule.to_string(),
+            rule_error: None,
+            density: 0.3,
+            wrap: true
+        })
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 7".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              restart,
+                              step_automata,
+                              update_cells).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-std::f32::consts::PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 28.0,
+            ..default()
+        }
+    ));
+}
+
+fn new_life(ui_values: &UiValues, rule: LifeRule) -> Life3d {
+    let mut life = Life3d::new(SIZE, rule);
+    life.wrap = ui_values.wrap;
+    let pattern = &PATTERNS[ui_values.pattern];
+    if pattern.cells.is_empty() {
+        //a random block in the middle, the edges stay empty
+        let mut rng = rand::rng();
+        for x in SIZE / 4..SIZE * 3 / 4 {
+            for y in SIZE / 4..SIZE * 3 / 4 {
+                for z in SIZE / 4..SIZE * 3 / 4 {
+                    life.set(x, y, z, rng.random_bool(ui_values.density as f64));
+                }
+            }
+        }
+    } else {
+        life.insert([SIZE / 2 - 1; 3], pattern.cells);
+    }
+    life
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<SevenScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let rule = match LifeRule::parse(&ui_values.rule) {
+        Ok(rule) => {
+            ui_values.rule_error = None;
+            rule
+        }
+        Err(error) => {
+            ui_values.rule_error = Some(error);
+            LifeRule::parse(PATTERNS[0].rule).expect("default rule")
+        }
+    };
+    let elementary = Elementary::new(ui_values.rule_number, ROW_WIDTH);
+    commands.insert_resource(Automata {
+        history: VecDeque::from([elementary.cells.clone()]),
+        elementary,
+        life: new_life(&ui_values, rule),
+        generation: 0,
+        elapsed: 0.0
+    });
+
+    //all cells share one mesh and one material, so they are drawn instanced; a cell
+    //is shown or hidden with its visibility
+    match ui_values.scene {
+        0 => {
+            let size = (MAX_LIMIT - MIN_LIMIT) / ROW_WIDTH as f32;
+            let mesh = meshes.add(Mesh::from(Cuboid::from_length(size * 0.9)));
+            let material = materials.add(StandardMaterial {
+                base_color: Color::Srgba(BLUE),
+                ..default()
+            });
+            for row in 0..HISTORY {
+                for column in 0..ROW_WIDTH {
+                    let x = MIN_LIMIT + (column as f32 + 0.5) * size;
+                    let y = MAX_LIMIT - (row as f32 + 0.5) * size;
+                    commands.spawn((
+                        Mesh3d(mesh.clone()),
+                        MeshMaterial3d(material.clone()),
+                        Transform::from_xyz(x, y, 0.0),
+                        Visibility::Hidden,
+                        RowCell { row, column },
+                        SevenScene
+                    ));
+                }
+            }
+        }
+        _ => {
+            let size = (MAX_LIMIT - MIN_LIMIT) / SIZE as f32;
+            let mesh = meshes.add(Mesh::from(Cuboid::from_length(size * 0.9)));
+            let material = materials.add(StandardMaterial {
+                base_color: Color::Srgba(RED),
+                ..default()
+            });
+            for x in 0..SIZE {
+                for y in 0..SIZE {
+                    for z in 0..SIZE {
+                        let position = Vec3::new(x as f32, y as f32, z as f32) + 0.5;
+                        commands.spawn((
+                            Mesh3d(mesh.clone()),
+                            MeshMaterial3d(material.clone()),
+                            Transform::from_translation(Vec3::splat(MIN_LIMIT) + position * size),
+                            Visibility::Hidden,
+                            LifeCell((x * SIZE + y) * SIZE + z),
+                            SevenScene
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn step_automata(
+    time: Res<Time>,
+    mut ui_values: ResMut<UiValues>,
+    automata: Option<ResMut<Automata>>
+){
+    let Some(mut automata) = automata else {
+        return;
+    };
+    automata.elapsed += time.delta_secs();
+    let due = ui_values.running && automata.elapsed >= 1.0 / ui_values.steps_per_second;
+    if !due && !ui_values.step {
+        return;
+    }
+    ui_values.step = false;
+    automata.elapsed = 0.0;
+    automata.generation += 1;
+
+    if ui_values.scene == 0 {
+        automata.elementary.step();
+        let cells = automata.elementary.cells.clone();
+        automata.history.push_front(cells);
+        automata.history.truncate(HISTORY);
+    } else {
+        automata.life.step();
+    }
+}
+
+fn update_cells(
+    automata: Option<Res<Automata>>,
+    mut rows: Query<(&mut Visibility, &RowCell), Without<LifeCell>>,
+    mut cells: Query<(&mut Visibility, &LifeCell), Without<RowCell>>
+){
+    let Some(automata) = automata else {
+        return;
+    };
+    if !automata.is_changed() {
+        return;
+    }
+    let shown = |alive: bool| if alive { Visibility::Inherited } else { Visibility::Hidden };
+    for (mut visibility, cell) in rows.iter_mut() {
+        let alive = automata.history.get(cell.row).is_some_and(|row| row[cell.column]);
+        visibility.set_if_neq(shown(alive));
+    }
+    for (mut visibility, cell) in cells.iter_mut() {
+        visibility.set_if_neq(shown(automata.life.cells[cell.0]));
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    automata: Option<Res<Automata>>
+){
+    let mut scene = ui_values.scene;
+    let mut pattern = ui_values.pattern;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Scene")
+            .selected_text(SCENES[scene])
+            .show_ui(ui, |ui| {
+                for (index, name) in SCENES.iter().enumerate() {
+                    ui.selectable_value(&mut scene, index, *name);
+                }
+            });
+        if ui_values.scene == 0 {
+            ui.horizontal(|ui| {
+                ui.label("rule");
+                if ui.add(egui::DragValue::new(&mut ui_values.rule_number)).changed() {
+                    ui_values.restart = true;
+                }
+            });
+        } else {
+            egui::ComboBox::from_label("Pattern")
+                .selected_text(PATTERNS[pattern].name)
+                .show_ui(ui, |ui| {
+                    for (index, pattern_value) in PATTERNS.iter().enumerate() {
+                        ui.selectable_value(&mut pattern, index, pattern_value.name);
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.label("rule");
+                //the rule is used on restart
+                if ui.text_edit_singleline(&mut ui_values.rule).lost_focus() {
+                    ui_values.restart = true;
+                }
+            });
+            if let Some(error) = &ui_values.rule_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.add(egui::Slider::new(&mut ui_values.density, 0.0..=1.0).text("density"));
+            ui.add(egui::Checkbox::new(&mut ui_values.wrap, "wrap around"));
+            if let Some(automata) = &automata {
+                ui.label(format!("population: {}", automata.life.population()));
+            }
+        }
+        ui.add(egui::Slider::new(&mut ui_values.steps_per_second, 1.0..=60.0).text("steps p. sec."));
+        if let Some(automata) = &automata {
+            ui.label(format!("generation: {}", automata.generation));
+        }
+        ui.horizontal(|ui| {
+            let label = if ui_values.running { "Pause" } else { "Run" };
+            if ui.button(label).clicked() {
+                ui_values.running = !ui_values.running;
+            }
+            if ui.button("Step").clicked() {
+                ui_values.step = true;
+            }
+            if ui.button("Restart").clicked() {
+                ui_values.restart = true;
+            }
+        });
+    });
+    if pattern != ui_values.pattern {
+        ui_values.pattern = pattern;
+        ui_values.rule = PATTERNS[pattern].rule.to_string();
+        ui_values.restart = true;
+    }
+    if scene != ui_values.scene {
+        ui_values.scene = scene;
+        ui_values.restart = true;
+    }
+}
+
+// steps the automata without a window and prints them; the known results are
+// checked by the tests in src/automata.rs
+fn run_headless() {
+    for rule in [30, 90] {
+        println!("rule {rule}");
+        let mut elementary = Elementary::new(rule, ROW_WIDTH);
+        for _ in 0..ROW_WIDTH / 2 {
+            println!("  {}", elementary.cells.iter().map(|alive| if *alive { '#' } else { '.' }).collect::<String>());
+            elementary.step();
+        }
+    }
+
+    for pattern in PATTERNS.iter().filter(|pattern| pattern.period > 0) {
+        let mut life = Life3d::new(SIZE, LifeRule::parse(pattern.rule).expect("rule"));
+        life.insert([SIZE / 2 - 1; 3], pattern.cells);
+        let population = life.population();
+        let period = life.period(20).map_or("none within 20 steps".to_string(), |period| period.to_string());
+        println!("{} in {}: {population} cells, period {period}", pattern.name, pattern.rule);
+    }
+}
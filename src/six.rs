@@ -7,10 +7,13 @@ use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use bevy::window::WindowResolution;
 use bevy_rapier3d::prelude::*;
 use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use particle::{ParicleEmiter, ParticlePlugin};
+use particle::collision::ParticleBounds;
 use crate::mesh::spawn_limit_cube;
 
 mod orbitcamera;
 mod mesh;
+mod particle;
 use rand::Rng;
 
 const HEIGHT: f32 = 640.0;
@@ -60,7 +63,8 @@ fn main() {
         .add_plugins((
             EguiPlugin,
             //RapierDebugRenderPlugin::default(),
-            OrbitCameraPlugin
+            OrbitCameraPlugin,
+            ParticlePlugin { bounds: ParticleBounds::cube(MIN_LIMIT, MAX_LIMIT) }
         ))
         .add_systems(Startup, (
             spawn_camera,
@@ -81,7 +85,8 @@ fn main() {
 }
 
 fn spawn_scene(
-    mut commands:Commands
+    mut commands:Commands,
+    asset_server: Res<AssetServer>
 ){
     /* Light */
     commands.spawn((
@@ -101,6 +106,12 @@ fn spawn_scene(
         color: Color::WHITE,
         brightness: 0.02,
     });
+
+    // particles bouncing off the rapier colliders of board and pins
+    commands.spawn((
+        Transform::from_xyz(0.0, MAX_LIMIT, 0.0),
+        ParicleEmiter::new(asset_server.load("effects/board_rain.emitter.ron"))
+    ));
 }
 
 fn spawn_board(
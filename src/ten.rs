@@ -0,0 +1,622 @@
+use bevy::color::palettes::basic::{BLUE, GRAY, LIME, RED, YELLOW};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use ga::{Crossover, Dna, Population, Selection, Statistics};
+use nn::{Activation, Network, Perceptron, ACTIVATIONS};
+use ray::{AabBox, Ray};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::f32::consts::PI;
+
+mod orbitcamera;
+mod mesh;
+mod ga;
+mod nn;
+mod ray;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 16.0;
+const MIN_LIMIT: f32 = -16.0;
+
+//steps of a generation with a fixed time step
+const LIFETIME: usize = 1200;
+const DELTA: f32 = 1.0 / 30.0;
+
+const SPEED: f32 = 6.0;
+//radians per second at full output
+const TURN: f32 = 2.5;
+
+//the vehicles fly along their local y axis; the sensors look ahead and to the sides,
+//turned around z (left and right) and x (up and down) by these angles in degrees
+const SENSORS: [(f32, f32); 7] = [(0.0, 0.0), (30.0, 0.0), (-30.0, 0.0), (60.0, 0.0), (-60.0, 0.0), (0.0, 30.0), (0.0, -30.0)];
+const SENSOR_RANGE: f32 = 10.0;
+const HIDDEN: usize = 6;
+
+//the arena is divided into cells, the fitness counts the cells a vehicle visited
+const CELL_SIZE: f32 = 4.0;
+const CELLS: usize = ((MAX_LIMIT - MIN_LIMIT) / CELL_SIZE) as usize;
+
+const START: Vec3 = Vec3::new(0.0, -8.0, -12.0);
+
+const PLOT_WIDTH: f32 = 280.0;
+const PLOT_HEIGHT: f32 = 140.0;
+const NETWORK_HEIGHT: f32 = 180.0;
+
+const HEADLESS_SEED: u64 = 10;
+
+#[derive(Resource)]
+struct UiValues{
+    restart: bool,
+    running: bool,
+    population: usize,
+    steps_per_frame: usize,
+    sensors: bool,
+    //of the hidden layer, the outputs are always tanh
+    activation: Activation
+}
+
+// pillars from the floor to the ceiling and a beam across
+fn obstacles() -> Vec<AabBox> {
+    let height = MAX_LIMIT - MIN_LIMIT;
+    vec![
+        AabBox::new(Vec3::new(-8.0, 0.0, -4.0), 3.0, height, 3.0, 0.0),
+        AabBox::new(Vec3::new(8.0, 0.0, 4.0), 3.0, height, 3.0, 0.0),
+        AabBox::new(Vec3::new(0.0, 0.0, 8.0), 3.0, height, 3.0, 0.0),
+        AabBox::new(Vec3::new(4.0, 0.0, -8.0), 3.0, height, 3.0, 0.0),
+        AabBox::new(Vec3::new(-6.0, 0.0, 8.0), 3.0, height, 3.0, 0.0),
+        AabBox::new(Vec3::new(0.0, 4.0, 0.0), height, 2.0, 2.0, 0.0)
+    ]
+}
+
+fn contains(aabb: &AabBox, position: Vec3) -> bool {
+    position.cmpge(aabb.min).all() && position.cmple(aabb.max).all()
+}
+
+fn brain(activation: Activation, rng: &mut impl Rng) -> Network {
+    Network::new(&[SENSORS.len(), HIDDEN, 2], activation, Activation::Tanh, rng)
+}
+
+impl Dna for Network {
+    fn crossover(&self, other: &Self, crossover: Crossover, rng: &mut impl Rng) -> Self {
+        let mut child = self.clone();
+        child.set_genes(&crossover.genes(&self.genes(), &other.genes(), rng));
+        child
+    }
+
+    fn mutate(&mut self, rate: f32, rng: &mut impl Rng) {
+        let genes: Vec<f32> = self.genes().into_iter()
+            .map(|gene| if rng.random_bool(rate as f64) { gene + rng.random_range(-0.5..0.5) } else { gene })
+            .collect();
+        self.set_genes(&genes);
+    }
+}
+
+struct Vehicle {
+    position: Vec3,
+    rotation: Quat,
+    alive: bool,
+    //how close the sensors see something, 0 for nothing in range and 1 for touching
+    sensors: Vec<f32>,
+    visited: Vec<bool>,
+    steps: usize
+}
+
+impl Vehicle {
+    fn new() -> Self {
+        Vehicle {
+            position: START,
+            //heading along z
+            rotation: Quat::from_rotation_x(PI / 2.0),
+            alive: true,
+            sensors: vec![0.0; SENSORS.len()],
+            visited: vec![false; CELLS * CELLS * CELLS],
+            steps: 0
+        }
+    }
+
+    fn sensor_directions(&self) -> impl Iterator<Item = Vec3> + '_ {
+        SENSORS.iter().map(|(side, up)| {
+            self.rotation * Quat::from_rotation_z(side.to_radians()) * Quat::from_rotation_x(up.to_radians()) * Vec3::Y
+        })
+    }
+
+    // the closest hit of each sensor ray with the walls of the arena and the obstacles
+    fn sense(&mut self, arena: &AabBox, obstacles: &[AabBox]) {
+        self.sensors = self.sensor_directions().map(|direction| {
+            let ray = Ray::new(self.position, direction);
+            let distance = std::iter::once(arena).chain(obstacles.iter())
+                .filter_map(|aabb| ray.hit_distance(aabb))
+                .fold(SENSOR_RANGE, f32::min);
+            1.0 - distance / SENSOR_RANGE
+        }).collect();
+    }
+
+    fn step(&mut self, brain: &Network, arena: &AabBox, obstacles: &[AabBox]) {
+        if !self.alive {
+            return;
+        }
+        self.sense(arena, obstacles);
+        let output = brain.predict(&self.sensors);
+        self.rotation *= Quat::from_rotation_z(output[0] * TURN * DELTA) * Quat::from_rotation_x(output[1] * TURN * DELTA);
+        self.position += self.rotation * Vec3::Y * SPEED * DELTA;
+        self.steps += 1;
+        if !contains(arena, self.position) || obstacles.iter().any(|obstacle| contains(obstacle, self.position)) {
+            self.alive = false;
+            return;
+        }
+        let [x, y, z] = ((self.position - MIN_LIMIT) / CELL_SIZE).to_array().map(|value| (value as usize).min(CELLS - 1));
+        self.visited[(x * CELLS + y) * CELLS + z] = true;
+    }
+
+    // the explored cells, and a little for staying alive
+    fn fitness(&self) -> f32 {
+        self.visited.iter().filter(|visited| **visited).count() as f32 + self.steps as f32 / LIFETIME as f32
+    }
+}
+
+// the population with the flights of its current generation
+#[derive(Resource)]
+struct Evolution {
+    population: Population<Network>,
+    vehicles: Vec<Vehicle>,
+    arena: AabBox,
+    obstacles: Vec<AabBox>,
+    step: usize,
+    history: Vec<Statistics>,
+    //vehicles of the last generation that lived until the end
+    survivors: usize
+}
+
+impl Evolution {
+    fn new(size: usize, activation: Activation, rng: &mut impl Rng) -> Self {
+        let mut population = Population::new((0..size).map(|_| brain(activation, rng)).collect());
+        population.selection = Selection::Tournament(3);
+        population.mutation_rate = 0.05;
+        population.elitism = 2;
+        Evolution {
+            population,
+            vehicles: (0..size).map(|_| Vehicle::new()).collect(),
+            arena: AabBox::new(Vec3::ZERO, MAX_LIMIT - MIN_LIMIT, MAX_LIMIT - MIN_LIMIT, MAX_LIMIT - MIN_LIMIT, 0.0),
+            obstacles: obstacles(),
+            step: 0,
+            history: Vec::new(),
+            survivors: 0
+        }
+    }
+
+    fn step(&mut self, rng: &mut impl Rng) {
+        for (vehicle, brain) in self.vehicles.iter_mut().zip(self.population.members.iter()) {
+            vehicle.step(brain, &self.arena, &self.obstacles);
+        }
+        self.step += 1;
+        if self.step < LIFETIME && self.vehicles.iter().any(|vehicle| vehicle.alive) {
+            return;
+        }
+        let fitness: Vec<f32> = self.vehicles.iter().map(Vehicle::fitness).collect();
+        self.survivors = self.vehicles.iter().filter(|vehicle| vehicle.alive).count();
+        self.history.push(self.population.evolve(&fitness, rng));
+        self.vehicles = (0..self.population.members.len()).map(|_| Vehicle::new()).collect();
+        self.step = 0;
+    }
+
+    fn generation(&mut self, rng: &mut impl Rng) -> Statistics {
+        let generation = self.population.generation;
+        while self.population.generation == generation {
+            self.step(rng);
+        }
+        self.history[self.history.len() - 1]
+    }
+
+    // the living vehicle that explored most so far
+    fn leader(&self) -> Option<usize> {
+        self.vehicles.iter().enumerate()
+            .filter(|(_, vehicle)| vehicle.alive)
+            .max_by(|(_, a), (_, b)| a.fitness().total_cmp(&b.fitness()))
+            .map(|(index, _)| index)
+    }
+}
+
+#[derive(Component)]
+struct VehicleScene;
+
+#[derive(Component)]
+struct VehicleShip(usize);
+
+#[derive(Resource)]
+struct VehicleMaterials {
+    alive: Handle<StandardMaterial>,
+    crashed: Handle<StandardMaterial>,
+    leader: Handle<StandardMaterial>
+}
+
+fn main() {
+    //cargo run --example ten -- --headless
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless();
+        return;
+    }
+
+    let values = UiValues{
+        restart: true,
+        running: true,
+        population: 60,
+        steps_per_frame: 1,
+        sensors: true,
+        activation: Activation::Tanh
+    };
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(Evolution::new(values.population, values.activation, &mut rand::rng()))
+        .insert_resource(values)
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 10".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              brain_egui,
+                              restart,
+                              evolve,
+                              update_vehicles,
+                              draw_sensors).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+
+    let obstacle_material = materials.add(StandardMaterial {
+        base_color: Color::Srgba(RED).with_alpha(0.4),
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+    for obstacle in obstacles() {
+        commands.spawn((
+            Mesh3d(meshes.add(Mesh::from(Cuboid::from_corners(obstacle.min, obstacle.max)))),
+            MeshMaterial3d(obstacle_material.clone()),
+            Transform::from_translation((obstacle.min + obstacle.max) / 2.0)
+        ));
+    }
+
+    let mut material = |color| materials.add(StandardMaterial {
+        base_color: Color::Srgba(color),
+        ..default()
+    });
+    commands.insert_resource(VehicleMaterials {
+        alive: material(BLUE),
+        crashed: material(GRAY),
+        leader: material(YELLOW)
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 48.0,
+            ..default()
+        }
+    ));
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut evolution: ResMut<Evolution>,
+    vehicle_materials: Res<VehicleMaterials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<Entity, With<VehicleScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    //a new population that keeps the settings of the old one
+    let mut next = Evolution::new(ui_values.population, ui_values.activation, &mut rand::rng());
+    next.population = evolution.population.reseeded(next.population.members);
+    *evolution = next;
+
+    let mesh = meshes.add(Mesh::from(Cone { radius: 0.25, height: 0.8 }));
+    for index in 0..ui_values.population {
+        commands.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(vehicle_materials.alive.clone()),
+            Transform::from_translation(START),
+            VehicleShip(index),
+            VehicleScene
+        ));
+    }
+}
+
+fn evolve(
+    ui_values: Res<UiValues>,
+    mut evolution: ResMut<Evolution>
+){
+    if !ui_values.running {
+        return;
+    }
+    let mut rng = rand::rng();
+    for _ in 0..ui_values.steps_per_frame {
+        evolution.step(&mut rng);
+    }
+}
+
+fn update_vehicles(
+    evolution: Res<Evolution>,
+    vehicle_materials: Res<VehicleMaterials>,
+    mut query: Query<(&mut Transform, &mut MeshMaterial3d<StandardMaterial>, &VehicleShip)>
+){
+    let leader = evolution.leader();
+    for (mut transform, mut material, ship) in query.iter_mut() {
+        let Some(vehicle) = evolution.vehicles.get(ship.0) else {
+            continue;
+        };
+        //the cone points along the local y axis like the vehicle
+        transform.translation = vehicle.position;
+        transform.rotation = vehicle.rotation;
+        let handle = if leader == Some(ship.0) {
+            &vehicle_materials.leader
+        } else if vehicle.alive {
+            &vehicle_materials.alive
+        } else {
+            &vehicle_materials.crashed
+        };
+        if material.0 != *handle {
+            material.0 = handle.clone();
+        }
+    }
+}
+
+// the sensor rays of the leader, red when they see something close
+fn draw_sensors(
+    mut gizmos: Gizmos,
+    ui_values: Res<UiValues>,
+    evolution: Res<Evolution>
+){
+    if !ui_values.sensors {
+        return;
+    }
+    let Some(vehicle) = evolution.leader().map(|index| &evolution.vehicles[index]) else {
+        return;
+    };
+    for (direction, closeness) in vehicle.sensor_directions().zip(vehicle.sensors.iter()) {
+        let end = vehicle.position + direction * SENSOR_RANGE * (1.0 - closeness);
+        gizmos.line(vehicle.position, end, LIME.mix(&RED, *closeness));
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    mut evolution: ResMut<Evolution>
+){
+    let mut population = ui_values.population;
+    let mut activation = ui_values.activation;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        ui.add(egui::Slider::new(&mut population, 10..=200).text("population"));
+        let name = ACTIVATIONS.iter().find(|(value, _)| *value == activation).map_or("", |(_, name)| name);
+        egui::ComboBox::from_label("Hidden activation")
+            .selected_text(name)
+            .show_ui(ui, |ui| {
+                for (value, name) in ACTIVATIONS {
+                    ui.selectable_value(&mut activation, value, name);
+                }
+            });
+
+        ga::population_ui(ui, &mut evolution.population, 0.0..=0.3);
+
+        ui.add(egui::Slider::new(&mut ui_values.steps_per_frame, 1..=50).text("steps per frame"));
+        ui.checkbox(&mut ui_values.sensors, "sensors");
+        ui.horizontal(|ui| {
+            let label = if ui_values.running { "Pause" } else { "Run" };
+            if ui.button(label).clicked() {
+                ui_values.running = !ui_values.running;
+            }
+            if ui.button("Restart").clicked() {
+                ui_values.restart = true;
+            }
+        });
+    });
+    if population != ui_values.population || activation != ui_values.activation {
+        ui_values.population = population;
+        ui_values.activation = activation;
+        ui_values.restart = true;
+    }
+}
+
+fn brain_egui(
+    mut egui_contexts: EguiContexts,
+    evolution: Res<Evolution>
+){
+    egui::Window::new("Brain")
+        .default_pos(egui::pos2(WIDTH - PLOT_WIDTH - 40.0, 20.0))
+        .show(egui_contexts.ctx_mut(), |ui|{
+            ui.label(format!("generation: {}", evolution.population.generation));
+            ui.add(egui::ProgressBar::new(evolution.step as f32 / LIFETIME as f32).text("lifetime"));
+            let alive = evolution.vehicles.iter().filter(|vehicle| vehicle.alive).count();
+            ui.label(format!("alive: {alive}"));
+            if let Some(last) = evolution.history.last() {
+                ui.label(format!("survived: {} of {}", evolution.survivors, evolution.vehicles.len()));
+                ui.label(format!("cells best: {:.1}  mean: {:.1}", last.best, last.mean));
+            }
+            ga::fitness_plot(ui, &evolution.history, egui::vec2(PLOT_WIDTH, PLOT_HEIGHT), None);
+            ui.colored_label(egui::Color32::BLUE, "best");
+            ui.colored_label(egui::Color32::DARK_GREEN, "mean");
+
+            if let Some(index) = evolution.leader() {
+                ui.separator();
+                ui.label("the network of the leader: sensors, hidden layer, turn left/right and up/down");
+                let brain = &evolution.population.members[index];
+                network_plot(ui, brain, &brain.activations(&evolution.vehicles[index].sensors));
+            }
+        });
+}
+
+// blue for positive and red for negative values; the lines are the weights, the
+// thicker the stronger, the nodes show the current activations
+fn signed_color(value: f32) -> egui::Color32 {
+    let strength = (value.abs().min(1.0) * 255.0) as u8;
+    if value >= 0.0 {
+        egui::Color32::from_rgba_unmultiplied(0, 0, 255, strength)
+    } else {
+        egui::Color32::from_rgba_unmultiplied(255, 0, 0, strength)
+    }
+}
+
+fn network_plot(
+    ui: &mut egui::Ui,
+    network: &Network,
+    activations: &[Vec<f32>]
+){
+    let (response, painter) = ui.allocate_painter(egui::vec2(PLOT_WIDTH, NETWORK_HEIGHT), egui::Sense::hover());
+    let rect = response.rect.shrink(10.0);
+    painter.rect_filled(response.rect, 0.0, egui::Color32::WHITE);
+
+    let node = |layer: usize, index: usize, count: usize| egui::pos2(
+        rect.left() + layer as f32 / network.layers.len() as f32 * rect.width(),
+        rect.top() + (index as f32 + 0.5) / count as f32 * rect.height()
+    );
+    for (number, layer) in network.layers.iter().enumerate() {
+        for output in 0..layer.outputs {
+            for input in 0..layer.inputs {
+                let weight = layer.weight(input, output);
+                painter.line_segment(
+                    [node(number, input, layer.inputs), node(number + 1, output, layer.outputs)],
+                    egui::Stroke::new(0.5 + weight.abs().min(2.0), signed_color(weight / 2.0))
+                );
+            }
+        }
+    }
+    for (number, values) in activations.iter().enumerate() {
+        for (index, value) in values.iter().enumerate() {
+            let center = node(number, index, values.len());
+            painter.circle_filled(center, 6.0, egui::Color32::WHITE);
+            painter.circle(center, 6.0, signed_color(*value), egui::Stroke::new(1.0, egui::Color32::GRAY));
+        }
+    }
+}
+
+// trains a perceptron, evolves a network for xor and the vehicles without a window,
+// the same way every run, and prints how well they learned
+fn run_headless() {
+    let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+
+    //a perceptron learns on which side of a plane points lie
+    let plane = |point: &[f32]| if point[1] > 0.5 * point[0] - 0.3 * point[2] + 0.1 { 1.0 } else { -1.0 };
+    let mut perceptron = Perceptron::new(3, 0.01, &mut rng);
+    for _ in 0..20000 {
+        let point: Vec<f32> = (0..3).map(|_| rng.random_range(-1.0..1.0)).collect();
+        perceptron.train(&point, plane(&point));
+    }
+    let correct = (0..1000).filter(|_| {
+        let point: Vec<f32> = (0..3).map(|_| rng.random_range(-1.0..1.0)).collect();
+        perceptron.guess(&point) == plane(&point)
+    }).count();
+    println!("perceptron: {correct} of 1000 points on the right side of the plane");
+
+    //xor cannot be learned by a single perceptron, a small evolved network can
+    let best = evolve_xor(&mut rng);
+    let outputs: Vec<String> = XOR.iter().map(|(inputs, _)| format!("{inputs:?} -> {:.3}", best.predict(inputs)[0])).collect();
+    println!("network: xor {}, squared error {:.4}", outputs.join(", "), xor_error(&best));
+
+    //the vehicles learn to fly around the obstacles and explore the arena
+    let mut evolution = Evolution::new(60, Activation::Tanh, &mut rng);
+    let first = evolution.generation(&mut rng);
+    let mut last = first;
+    for _ in 1..30 {
+        last = evolution.generation(&mut rng);
+        if last.generation % 5 == 0 {
+            println!("vehicles generation {:>2}: best {:.1} mean {:.1} survived {}",
+                     last.generation, last.best, last.mean, evolution.survivors);
+        }
+    }
+    println!("vehicles: mean fitness {:.1} -> {:.1}", first.mean, last.mean);
+}
+
+const XOR: [([f32; 2], f32); 4] = [([0.0, 0.0], 0.0), ([0.0, 1.0], 1.0), ([1.0, 0.0], 1.0), ([1.0, 1.0], 0.0)];
+
+fn xor_error(network: &Network) -> f32 {
+    XOR.iter().map(|(inputs, target)| (network.predict(inputs)[0] - target).powi(2)).sum()
+}
+
+// the network of 300 generations with the smallest error
+fn evolve_xor(rng: &mut impl Rng) -> Network {
+    let mut population = Population::new((0..100).map(|_| Network::new(&[2, 3, 1], Activation::Tanh, Activation::Sigmoid, rng)).collect());
+    population.selection = Selection::Tournament(3);
+    population.mutation_rate = 0.1;
+    population.elitism = 2;
+    for _ in 0..300 {
+        let fitness: Vec<f32> = population.members.iter().map(|network| 4.0 - xor_error(network)).collect();
+        population.evolve(&fitness, rng);
+    }
+    population.members.into_iter().min_by(|a, b| xor_error(a).total_cmp(&xor_error(b))).expect("a network")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evolved_networks_learn_xor() {
+        let best = evolve_xor(&mut StdRng::seed_from_u64(HEADLESS_SEED));
+        for (inputs, target) in XOR {
+            let output = best.predict(&inputs)[0];
+            assert_eq!(output.round(), target, "xor {inputs:?} gives {output}");
+        }
+    }
+
+    #[test]
+    fn vehicles_explore_more() {
+        let mut rng = StdRng::seed_from_u64(HEADLESS_SEED);
+        let mut evolution = Evolution::new(30, Activation::Tanh, &mut rng);
+        let first = evolution.generation(&mut rng);
+        let mut last = first;
+        for _ in 1..10 {
+            last = evolution.generation(&mut rng);
+        }
+        assert!(last.mean > first.mean, "{first:?} -> {last:?}");
+    }
+}
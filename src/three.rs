@@ -3,67 +3,229 @@ use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use bevy::render::mesh::PrimitiveTopology;
 
 use orbitcamera::{OrbitCameraPlugin,OrbitCamera};
+use trail::{Trail, TrailMode, TrailPlugin};
 mod orbitcamera;
+mod trail;
 
+use std::collections::VecDeque;
 use std::f32::consts::PI;
-use bevy::color::palettes::basic::{BLUE, LIME};
+use bevy::color::palettes::basic::{BLUE, LIME, OLIVE, PURPLE, RED, TEAL, WHITE};
 use bevy::window::WindowResolution;
 
 const HEIGHT: f32 = 640.0;
 const WIDTH: f32 = 960.0;
 
-const RADIUS: f32 = 8.0;
+//length of all arms together
+const RADIUS: f32 = 7.0;
+const GRAVITY: f32 = 40.0;
+const START_ANGLE: f32 = PI * 0.6;
+//the second pendulum of the chaos mode starts that much further
+const CHAOS_OFFSET: f32 = 0.0001;
+//time steps of the integration per frame
+const SUBSTEPS: u32 = 8;
+const MAX_PENDULUMS: usize = 5;
+//distance of the pendulums along the z axis
+const SPACING: f32 = 3.0;
+const COLORS: [Srgba; MAX_PENDULUMS] = [BLUE, RED, PURPLE, TEAL, OLIVE];
+
+//frames of the phase space plot
+const PHASE_POINTS: usize = 600;
+const PLOT_WIDTH: f32 = 280.0;
+const PLOT_HEIGHT: f32 = 180.0;
 
 const MAX_LIMIT: f32 = 8.0;
 const MIN_LIMIT: f32 = -8.0;
 
+#[derive(Clone, PartialEq)]
+struct PendulumSettings {
+    links: usize,
+    length: f32,
+    gravity: f32,
+    damping: f32,
+    angle: f32,
+    mass: f32
+}
+
+impl Default for PendulumSettings {
+    fn default() -> Self {
+        PendulumSettings {
+            links: 2,
+            length: RADIUS,
+            gravity: GRAVITY,
+            damping: 0.0,
+            angle: START_ANGLE,
+            mass: 1.0
+        }
+    }
+}
+
 #[derive(Resource)]
 struct UiValues{
-    restart: bool
+    restart: bool,
+    pendulums: Vec<PendulumSettings>,
+    chaos: bool
 }
 
+// An N-link pendulum: point masses on massless arms, each angle measured from the
+// vertical. The equations of motion come from the Lagrangian and are integrated
+// with RK4.
 #[derive(Component)]
 struct Pendulum {
-    angle: f32,
-    position: Vec3,
+    angles: Vec<f32>,
+    velocities: Vec<f32>,
+    lengths: Vec<f32>,
+    masses: Vec<f32>,
     origin: Vec3,
-    circle_velocity: f32,
-    circle_acceleration: f32,
-    daming: f32,
+    gravity: f32,
+    damping: f32,
+    //index into the settings of the ui
+    settings: usize,
+    color: Srgba,
+    //angle and angular velocity of the first link
+    phase: VecDeque<Vec2>
 }
 
-impl Default for Pendulum {
-    fn default() -> Self {
+impl Pendulum {
+    fn new(settings: &PendulumSettings, start_angle: f32) -> Self {
+        let links = settings.links;
         Pendulum {
-            angle: PI/4.0,
-            origin: Vec3::new(0.0, MAX_LIMIT, 0.0),
-            position: Vec3::ZERO,
-            circle_velocity:0.0,
-            circle_acceleration:0.0,
-            daming : 0.998
+            angles: vec![start_angle; links],
+            velocities: vec![0.0; links],
+            lengths: vec![settings.length / links as f32; links],
+            masses: vec![settings.mass; links],
+            origin: Vec3::ZERO,
+            gravity: settings.gravity,
+            damping: settings.damping,
+            settings: 0,
+            color: BLUE,
+            phase: VecDeque::with_capacity(PHASE_POINTS)
+        }
+    }
+
+    // mass, gravity and damping change without a restart
+    fn apply(&mut self, settings: &PendulumSettings) {
+        self.masses.fill(settings.mass);
+        self.gravity = settings.gravity;
+        self.damping = settings.damping;
+    }
+
+    fn record_phase(&mut self) {
+        if self.phase.len() == PHASE_POINTS {
+            self.phase.pop_front();
+        }
+        //angles wrap around, the plot shows -π..π
+        let angle = (self.angles[0] + PI).rem_euclid(2.0 * PI) - PI;
+        self.phase.push_back(Vec2::new(angle, self.velocities[0]));
+    }
+
+    // solves M(θ) θ'' = f(θ, θ') for the angular accelerations with
+    // M_ij = μ_max(i,j) l_i l_j cos(θ_i - θ_j) and
+    // f_i = -Σ_j μ_max(i,j) l_i l_j sin(θ_i - θ_j) θ'_j² - μ_i g l_i sin θ_i,
+    // μ_i being the mass of link i and all links below it
+    fn accelerations(&self, angles: &[f32], velocities: &[f32]) -> Vec<f32> {
+        let n = angles.len();
+        let mu: Vec<f32> = (0..n).map(|i| self.masses[i..].iter().sum()).collect();
+        let mut matrix = vec![vec![0.0; n]; n];
+        let mut forces = vec![0.0; n];
+
+        for i in 0..n {
+            for j in 0..n {
+                let coupling = mu[i.max(j)] * self.lengths[i] * self.lengths[j];
+                let difference = angles[i] - angles[j];
+                matrix[i][j] = coupling * difference.cos();
+                forces[i] -= coupling * difference.sin() * velocities[j] * velocities[j];
+            }
+            forces[i] -= mu[i] * self.gravity * self.lengths[i] * angles[i].sin();
+            forces[i] -= self.damping * velocities[i];
         }
+        solve(matrix, forces)
+    }
+
+    fn step(&mut self, dt: f32) {
+        //classic Runge-Kutta on the state (angles, velocities)
+        let derivative = |angles: &[f32], velocities: &[f32]| {
+            (velocities.to_vec(), self.accelerations(angles, velocities))
+        };
+        let offset = |base: &[f32], slope: &[f32], h: f32| -> Vec<f32> {
+            base.iter().zip(slope).map(|(value, slope)| value + slope * h).collect()
+        };
+
+        let (k1_angles, k1_velocities) = derivative(&self.angles, &self.velocities);
+        let (k2_angles, k2_velocities) = derivative(&offset(&self.angles, &k1_angles, dt / 2.0),
+                                                    &offset(&self.velocities, &k1_velocities, dt / 2.0));
+        let (k3_angles, k3_velocities) = derivative(&offset(&self.angles, &k2_angles, dt / 2.0),
+                                                    &offset(&self.velocities, &k2_velocities, dt / 2.0));
+        let (k4_angles, k4_velocities) = derivative(&offset(&self.angles, &k3_angles, dt),
+                                                    &offset(&self.velocities, &k3_velocities, dt));
+
+        for i in 0..self.angles.len() {
+            self.angles[i] += dt / 6.0 * (k1_angles[i] + 2.0 * k2_angles[i] + 2.0 * k3_angles[i] + k4_angles[i]);
+            self.velocities[i] += dt / 6.0 * (k1_velocities[i] + 2.0 * k2_velocities[i]
+                                              + 2.0 * k3_velocities[i] + k4_velocities[i]);
+        }
+    }
+
+    // the end of every arm, starting with the first one
+    fn positions(&self) -> Vec<Vec3> {
+        let mut position = self.origin;
+        self.angles.iter().zip(&self.lengths).map(|(angle, length)| {
+            position += Vec3::new(length * angle.sin(), -length * angle.cos(), 0.0);
+            position
+        }).collect()
     }
 }
 
-impl Pendulum {
-    fn reset(&mut self) -> () {
-            self.angle =  PI/4.0;
-            self.origin =  Vec3::new(0.0, MAX_LIMIT, 0.0);
-            self.position =  Vec3::ZERO;
-            self.circle_velocity = 0.0;
-            self.circle_acceleration = 0.0;
-            self.daming = 0.998;
+// gaussian elimination with partial pivoting
+fn solve(mut matrix: Vec<Vec<f32>>, mut vector: Vec<f32>) -> Vec<f32> {
+    let n = vector.len();
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|a, b| matrix[*a][column].abs().total_cmp(&matrix[*b][column].abs()))
+            .unwrap_or(column);
+        matrix.swap(column, pivot);
+        vector.swap(column, pivot);
+
+        for row in column + 1..n {
+            let factor = matrix[row][column] / matrix[column][column];
+            let pivot_row = matrix[column].clone();
+            for (value, pivot) in matrix[row][column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot;
+            }
+            vector[row] -= factor * vector[column];
+        }
     }
+    let mut result = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f32 = (row + 1..n).map(|k| matrix[row][k] * result[k]).sum();
+        result[row] = (vector[row] - sum) / matrix[row][row];
+    }
+    result
+}
+
+// everything that is respawned on restart
+#[derive(Component)]
+struct PendulumPart;
+
+// the arm and the bob at the end of link index of a pendulum
+#[derive(Component)]
+struct PendulumLine{
+    pendulum: Entity,
+    index: usize
 }
 
 #[derive(Component)]
-struct PendulumLine{}
+struct PendulumBob{
+    pendulum: Entity,
+    index: usize
+}
 
 fn main() {
     App::new()
         .insert_resource(ClearColor(Color::WHITE))
         .insert_resource(UiValues{
-            restart: true
+            restart: true,
+            pendulums: vec![PendulumSettings::default()],
+            chaos: false
         })
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -76,15 +238,16 @@ fn main() {
         }))
         .add_plugins((
             EguiPlugin,
-            OrbitCameraPlugin
+            OrbitCameraPlugin,
+            TrailPlugin
         ))
         .add_systems(Startup, (spawn_camera,
                       spawn_scene,
-                      spawn_limit_cube,
-                      spawn_pendulum)
+                      spawn_limit_cube)
         )
         .add_systems(Update, (ui_egui,
-                              moving))
+                              spawn_pendulums,
+                              moving).chain())
         .run();
 }
 
@@ -124,72 +287,118 @@ fn spawn_camera(
     ));
 }
 
-fn spawn_pendulum(
+fn spawn_pendulums(
     mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<PendulumPart>>
 ){
-    //line
-    commands.spawn((
-        Mesh3d(meshes.add(Mesh::from(Cuboid::new(0.2, RADIUS, 0.2)))),
-        MeshMaterial3d( materials.add(StandardMaterial {
-            base_color: Color::Srgba(LIME),
-            emissive: Color::Srgba(LIME).into(),
-            ..Default::default()
-        })),
-        Transform {
-            translation: Vec3::new(0.0, RADIUS/2.0, 0.0),
-            ..default()
-        },
-        PendulumLine{}
-    ));
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 
-    //sphere
-        commands.spawn((
-           Mesh3d(meshes.add(Mesh::from(Sphere {
-                radius: 1.0,
-            }))),
-           MeshMaterial3d(materials.add( StandardMaterial {
-                base_color: Color::Srgba(BLUE),
+    let line_material = materials.add(StandardMaterial {
+        base_color: Color::Srgba(LIME),
+        emissive: Color::Srgba(LIME).into(),
+        ..Default::default()
+    });
+    let bob_mesh = meshes.add(Mesh::from(Sphere {
+        radius: 0.5,
+    }));
+    let count = ui_values.pendulums.len();
+
+    for (number, settings) in ui_values.pendulums.iter().enumerate() {
+        //side by side along the z axis
+        let origin = Vec3::new(0.0, 0.0, (number as f32 - (count - 1) as f32 / 2.0) * SPACING);
+        //in chaos mode every pendulum gets a lighter twin that starts a tiny bit further
+        let twins = if ui_values.chaos { 2 } else { 1 };
+
+        for twin in 0..twins {
+            let color = if twin == 0 { COLORS[number] } else { COLORS[number].mix(&WHITE, 0.5) };
+            let mut pendulum = Pendulum::new(settings, settings.angle + twin as f32 * CHAOS_OFFSET);
+            pendulum.origin = origin;
+            pendulum.settings = number;
+            pendulum.color = color;
+            let lengths = pendulum.lengths.clone();
+            let pendulum = commands.spawn((
+                Transform::default(),
+                Visibility::default(),
+                pendulum,
+                PendulumPart
+            )).id();
+            let bob_material = materials.add(StandardMaterial {
+                base_color: Color::Srgba(color),
                 ..Default::default()
-            })),
-           Pendulum{..default()}
-        ));
+            });
+
+            for (index, length) in lengths.iter().enumerate() {
+                //line
+                commands.spawn((
+                    Mesh3d(meshes.add(Mesh::from(Cuboid::new(0.2, *length, 0.2)))),
+                    MeshMaterial3d(line_material.clone()),
+                    Transform::default(),
+                    PendulumLine{ pendulum, index },
+                    PendulumPart
+                ));
+
+                //sphere
+                let bob = commands.spawn((
+                    Mesh3d(bob_mesh.clone()),
+                    MeshMaterial3d(bob_material.clone()),
+                    Transform::default(),
+                    PendulumBob{ pendulum, index },
+                    PendulumPart
+                )).id();
+                //the tip draws its path
+                if index == lengths.len() - 1 {
+                    commands.entity(bob).insert(Trail::new(240, Color::Srgba(color))
+                        .with_mode(TrailMode::Ribbon { width: 0.3 }));
+                }
+            }
+        }
+    }
 }
 
 fn moving(
     time:Res<Time>,
-    mut ui_values: ResMut<UiValues>,
-    mut query: Query<(&mut Transform, &mut Pendulum)>,
-    mut query_line: Query<&mut Transform, (With<PendulumLine>,Without<Pendulum>)>
+    ui_values: Res<UiValues>,
+    mut query: Query<&mut Pendulum>,
+    mut query_line: Query<(&mut Transform, &PendulumLine), Without<PendulumBob>>,
+    mut query_bob: Query<(&mut Transform, &PendulumBob), Without<PendulumLine>>
 ){
-    let (mut transform, mut pendulum) = query.single_mut();
-    let mut line_transform = query_line.single_mut();
-    let gravity = 40.0;
-
-    if ui_values.restart {
-        ui_values.restart = false;
-        pendulum.reset();
+    let dt = time.delta_secs() / SUBSTEPS as f32;
+    for mut pendulum in query.iter_mut() {
+        if let Some(settings) = ui_values.pendulums.get(pendulum.settings) {
+            pendulum.apply(settings);
+        }
+        for _ in 0..SUBSTEPS {
+            pendulum.step(dt);
+        }
+        pendulum.record_phase();
     }
-    pendulum.circle_acceleration = (-1.0 * gravity / RADIUS) * pendulum.angle.sin();
-
-    pendulum.circle_velocity += pendulum.circle_acceleration * time.delta_secs();
-    pendulum.angle += pendulum.circle_velocity * time.delta_secs();
-
-    pendulum.circle_velocity *= pendulum.daming;
-
-    pendulum.position = pendulum.origin -
-                        Vec3::new(RADIUS*pendulum.angle.sin(),
-                                  RADIUS*pendulum.angle.cos(),
-                                  0.0);
-
-    transform.translation = pendulum.position;
-
-    line_transform.rotation = Quat::from_rotation_z(-pendulum.angle);
-    line_transform.translation = pendulum.origin + Vec3::new(-pendulum.angle.sin() * RADIUS/2.0,
-                                                             -pendulum.angle.cos() * RADIUS/2.0,
-                                                             0.0);
 
+    for (mut transform, bob) in query_bob.iter_mut() {
+        if let Ok(pendulum) = query.get(bob.pendulum) {
+            transform.translation = pendulum.positions()[bob.index];
+            //the volume of a bob grows with its mass
+            transform.scale = Vec3::splat(pendulum.masses[bob.index].cbrt());
+        }
+    }
+    for (mut line_transform, line) in query_line.iter_mut() {
+        let Ok(pendulum) = query.get(line.pendulum) else {
+            continue;
+        };
+        let positions = pendulum.positions();
+        let start = if line.index == 0 { pendulum.origin } else { positions[line.index - 1] };
+        let end = positions[line.index];
+        line_transform.translation = (start + end) / 2.0;
+        line_transform.rotation = Quat::from_rotation_arc(Vec3::Y, (start - end).normalize_or(Vec3::Y));
+    }
 }
 
 fn spawn_limit_cube(
@@ -260,10 +469,95 @@ fn spawn_limit_cube(
 fn ui_egui(
     mut egui_contexts: EguiContexts,
     mut ui_values: ResMut<UiValues>,
+    query: Query<&Pendulum>
 ){
-    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+    let mut pendulums = ui_values.pendulums.clone();
+    let mut chaos = ui_values.chaos;
+    let mut restart = false;
+    let ctx = egui_contexts.ctx_mut();
+
+    egui::Window::new("Properties").show(ctx, |ui|{
+        let mut removed = None;
+        for (number, settings) in pendulums.iter_mut().enumerate() {
+            egui::CollapsingHeader::new(format!("Pendulum {}", number + 1))
+                .default_open(number == 0)
+                .show(ui, |ui| {
+                    ui.add(egui::Slider::new(&mut settings.links, 1..=5).text("links"));
+                    ui.add(egui::Slider::new(&mut settings.length, 2.0..=8.0).text("length"));
+                    ui.add(egui::Slider::new(&mut settings.angle, -PI..=PI).text("initial angle"));
+                    ui.add(egui::Slider::new(&mut settings.gravity, 0.0..=100.0).text("gravity"));
+                    ui.add(egui::Slider::new(&mut settings.damping, 0.0..=20.0).text("damping"));
+                    ui.add(egui::Slider::new(&mut settings.mass, 0.1..=5.0).text("mass"));
+                    if number > 0 && ui.button("Remove").clicked() {
+                        removed = Some(number);
+                    }
+                });
+        }
+        if let Some(number) = removed {
+            pendulums.remove(number);
+        }
+        if pendulums.len() < MAX_PENDULUMS && ui.button("Add pendulum").clicked() {
+            pendulums.push(PendulumSettings::default());
+        }
+        ui.add(egui::Checkbox::new(&mut chaos, "chaos (twins 0.0001 rad apart)"));
         if ui.button("Restart").clicked() {
-            ui_values.restart = true;
+            restart = true;
         }
     });
-}
\ No newline at end of file
+
+    egui::Window::new("Phase space").show(ctx, |ui|{
+        ui.label("angle (x) against angular velocity (y) of the first link");
+        phase_plot(ui, &query);
+    });
+
+    //the arms and the start of the motion need new pendulums, everything else is
+    //picked up while they swing
+    let changed_shape = pendulums.len() != ui_values.pendulums.len()
+        || pendulums.iter().zip(&ui_values.pendulums).any(|(new, old)| {
+            new.links != old.links || new.length != old.length || new.angle != old.angle
+        });
+    if restart || changed_shape || chaos != ui_values.chaos {
+        ui_values.restart = true;
+    }
+    if pendulums != ui_values.pendulums {
+        ui_values.pendulums = pendulums;
+    }
+    ui_values.chaos = chaos;
+}
+
+fn phase_plot(
+    ui: &mut egui::Ui,
+    query: &Query<&Pendulum>
+){
+    let (response, painter) = ui.allocate_painter(egui::vec2(PLOT_WIDTH, PLOT_HEIGHT), egui::Sense::hover());
+    let rect = response.rect;
+    let axis = egui::Stroke::new(1.0, egui::Color32::GRAY);
+    painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+    painter.line_segment([rect.left_center(), rect.right_center()], axis);
+    painter.line_segment([rect.center_top(), rect.center_bottom()], axis);
+
+    //the velocity axis fits the fastest pendulum
+    let max_velocity = query.iter()
+        .flat_map(|pendulum| pendulum.phase.iter())
+        .fold(1.0_f32, |max, point| max.max(point.y.abs()));
+    let to_screen = |point: &Vec2| egui::pos2(
+        rect.center().x + point.x / PI * rect.width() / 2.0,
+        rect.center().y - point.y / max_velocity * rect.height() / 2.0
+    );
+
+    for pendulum in query.iter() {
+        let [r, g, b, _] = pendulum.color.to_u8_array();
+        let stroke = egui::Stroke::new(1.5, egui::Color32::from_rgb(r, g, b));
+        let mut points = Vec::new();
+        let mut previous: Option<&Vec2> = None;
+        for point in pendulum.phase.iter() {
+            //do not connect the points where the angle wraps around
+            if previous.is_some_and(|previous| (point.x - previous.x).abs() > PI) {
+                painter.add(egui::Shape::line(std::mem::take(&mut points), stroke));
+            }
+            points.push(to_screen(point));
+            previous = Some(point);
+        }
+        painter.add(egui::Shape::line(points, stroke));
+    }
+}
@@ -0,0 +1,277 @@
+use bevy::color::palettes::basic::{BLUE, LIME, RED};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use oscillator::{Oscillator, OscillatorPlugin, Wave, WaveComponent};
+use trail::{Trail, TrailPlugin};
+
+use std::f32::consts::PI;
+
+mod orbitcamera;
+mod mesh;
+mod oscillator;
+mod trail;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 8.0;
+const MIN_LIMIT: f32 = -8.0;
+
+const SCENES: [&str; 3] = ["Harmonic motion", "Wave", "Lissajous 3D"];
+
+const HARMONIC_BALLS: usize = 9;
+const WAVE_SPACING: f32 = 0.4;
+const LISSAJOUS_SIZE: f32 = 6.0;
+
+#[derive(Resource)]
+struct UiValues{
+    scene: usize,
+    restart: bool,
+    //harmonic motion
+    amplitude: f32,
+    frequency: f32,
+    phase_step: f32,
+    //wave
+    balls: usize,
+    components: [WaveComponent; 3],
+    //lissajous, the frequencies of the axes relative to the base frequency
+    ratios: Vec3,
+    base_frequency: f32,
+    phase: f32
+}
+
+#[derive(Component)]
+struct OscillationScene;
+
+// the ball of the harmonic motion with its place in the row
+#[derive(Component)]
+struct HarmonicBall(usize);
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(UiValues{
+            scene: 0,
+            restart: true,
+            amplitude: 5.0,
+            frequency: 0.5,
+            phase_step: 0.4,
+            balls: 40,
+            components: [
+                WaveComponent { amplitude: 2.0, wavelength: 8.0, speed: 2.0 },
+                WaveComponent { amplitude: 1.0, wavelength: 3.0, speed: -1.0 },
+                WaveComponent { amplitude: 0.0, wavelength: 1.5, speed: 0.5 },
+            ],
+            ratios: Vec3::new(3.0, 2.0, 1.0),
+            base_frequency: 0.1,
+            phase: PI / 2.0
+        })
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 3.1 - 3.9".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin,
+            OscillatorPlugin,
+            TrailPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              restart,
+                              apply_ui_values,
+                              draw_arms).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 28.0,
+            ..default()
+        }
+    ));
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<OscillationScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::Srgba(BLUE),
+        ..default()
+    });
+
+    match ui_values.scene {
+        //a row of balls on springs, each one a little later than its neighbour
+        0 => {
+            let mesh = meshes.add(Mesh::from(Sphere { radius: 0.5 }));
+            for index in 0..HARMONIC_BALLS {
+                let x = (index as f32 - (HARMONIC_BALLS as f32 - 1.0) / 2.0) * 1.5;
+                commands.spawn((
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform::from_xyz(x, 0.0, 0.0),
+                    Oscillator::new(Vec3::new(0.0, ui_values.amplitude, 0.0), Vec3::splat(ui_values.frequency))
+                        .with_phase(Vec3::splat(index as f32 * ui_values.phase_step))
+                        .with_center(Vec3::new(x, 0.0, 0.0)),
+                    HarmonicBall(index),
+                    OscillationScene
+                ));
+            }
+        }
+        1 => {
+            let mesh = meshes.add(Mesh::from(Sphere { radius: WAVE_SPACING * 0.6 }));
+            let wave = Wave {
+                components: ui_values.components.to_vec(),
+                count: ui_values.balls,
+                spacing: WAVE_SPACING
+            }.spawn(&mut commands, mesh, material, Transform::default());
+            commands.entity(wave).insert(OscillationScene);
+        }
+        //one ball oscillating on all three axes draws its figure
+        _ => {
+            commands.spawn((
+                Mesh3d(meshes.add(Mesh::from(Sphere { radius: 0.3 }))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: Color::Srgba(RED),
+                    ..default()
+                })),
+                Transform::default(),
+                Oscillator::new(Vec3::splat(LISSAJOUS_SIZE), Vec3::ZERO),
+                Trail::new(1000, Color::Srgba(BLUE)).with_spacing(0.05),
+                OscillationScene
+            ));
+        }
+    }
+}
+
+// the sliders change the running scene, only the number of wave balls needs a restart
+fn apply_ui_values(
+    ui_values: Res<UiValues>,
+    mut oscillators: Query<(&mut Oscillator, Option<&HarmonicBall>)>,
+    mut waves: Query<&mut Wave>
+){
+    for (mut oscillator, ball) in oscillators.iter_mut() {
+        match ball {
+            Some(ball) => {
+                oscillator.amplitude = Vec3::new(0.0, ui_values.amplitude, 0.0);
+                oscillator.frequency = Vec3::splat(ui_values.frequency);
+                oscillator.phase = Vec3::splat(ball.0 as f32 * ui_values.phase_step);
+            }
+            None => {
+                oscillator.frequency = ui_values.ratios * ui_values.base_frequency;
+                oscillator.phase = Vec3::new(ui_values.phase, 0.0, 0.0);
+            }
+        }
+    }
+    for mut wave in waves.iter_mut() {
+        if wave.components != ui_values.components {
+            wave.components = ui_values.components.to_vec();
+        }
+    }
+}
+
+// the arms of the harmonic balls, from the center line to the ball
+fn draw_arms(
+    mut gizmos: Gizmos,
+    query: Query<(&Transform, &Oscillator), With<HarmonicBall>>
+){
+    for (transform, oscillator) in query.iter() {
+        gizmos.line(oscillator.center, transform.translation, LIME);
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+){
+    let mut scene = ui_values.scene;
+    let mut balls = ui_values.balls;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Scene")
+            .selected_text(SCENES[scene])
+            .show_ui(ui, |ui| {
+                for (index, name) in SCENES.iter().enumerate() {
+                    ui.selectable_value(&mut scene, index, *name);
+                }
+            });
+        match ui_values.scene {
+            0 => {
+                ui.add(egui::Slider::new(&mut ui_values.amplitude, 0.0..=MAX_LIMIT).text("amplitude"));
+                ui.add(egui::Slider::new(&mut ui_values.frequency, 0.0..=2.0).text("frequency"));
+                ui.add(egui::Slider::new(&mut ui_values.phase_step, 0.0..=PI).text("phase step"));
+            }
+            1 => {
+                ui.add(egui::Slider::new(&mut balls, 2..=80).text("balls"));
+                for (index, component) in ui_values.components.iter_mut().enumerate() {
+                    ui.label(format!("Wave {}", index + 1));
+                    ui.add(egui::Slider::new(&mut component.amplitude, 0.0..=4.0).text("amplitude"));
+                    ui.add(egui::Slider::new(&mut component.wavelength, 0.5..=16.0).text("wavelength"));
+                    ui.add(egui::Slider::new(&mut component.speed, -4.0..=4.0).text("speed"));
+                }
+            }
+            _ => {
+                ui.add(egui::Slider::new(&mut ui_values.ratios.x, 1.0..=7.0).step_by(1.0).text("x ratio"));
+                ui.add(egui::Slider::new(&mut ui_values.ratios.y, 1.0..=7.0).step_by(1.0).text("y ratio"));
+                ui.add(egui::Slider::new(&mut ui_values.ratios.z, 1.0..=7.0).step_by(1.0).text("z ratio"));
+                ui.add(egui::Slider::new(&mut ui_values.base_frequency, 0.01..=0.5).text("base frequency"));
+                ui.add(egui::Slider::new(&mut ui_values.phase, 0.0..=PI).text("phase of x"));
+            }
+        }
+        if ui.button("Restart").clicked() {
+            ui_values.restart = true;
+        }
+    });
+    if scene != ui_values.scene || balls != ui_values.balls {
+        ui_values.scene = scene;
+        ui_values.balls = balls;
+        ui_values.restart = true;
+    }
+}
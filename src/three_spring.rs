@@ -0,0 +1,379 @@
+use bevy::color::palettes::basic::{BLUE, LIME, RED};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+
+mod orbitcamera;
+mod mesh;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 8.0;
+const MIN_LIMIT: f32 = -8.0;
+
+const GRAVITY: f32 = 10.0;
+//stiff springs need small time steps, so every frame is split into substeps
+const SUBSTEPS: u32 = 16;
+const MAX_FRAME_TIME: f32 = 1.0 / 30.0;
+const NODE_RADIUS: f32 = 0.15;
+const RESTITUTION: f32 = 0.3;
+const FRICTION: f32 = 0.2;
+
+const SCENES: [&str; 3] = ["Chain", "Cloth", "Soft body cube"];
+
+#[derive(Resource)]
+struct UiValues{
+    scene: usize,
+    stiffness: f32,
+    damping: f32,
+    restart: bool
+}
+
+#[derive(Component)]
+struct Moveable {
+    velocity:Vec3,
+    acceleration: Vec3,
+    mass: f32
+}
+
+impl Default for Moveable {
+    fn default() -> Self {
+        Moveable {
+            velocity: Vec3::ZERO,
+            acceleration: Vec3::ZERO,
+            mass: 1.0
+        }
+    }
+}
+
+impl Moveable {
+    fn apply_force(&mut self, force:Vec3){
+       self.acceleration += force/self.mass;
+    }
+}
+
+// an anchored node is moved by nobody
+#[derive(Component)]
+struct Anchor;
+
+// Hooke's law: pulls a and b together when the spring is longer than its rest
+// length and pushes them apart when it is shorter; damping works against the
+// speed the two ends move apart with
+#[derive(Component)]
+struct Spring {
+    a: Entity,
+    b: Entity,
+    rest_length: f32,
+    stiffness: f32,
+    damping: f32
+}
+
+impl Spring {
+    // force on a, b gets the opposite one
+    fn force(&self, position_a: Vec3, velocity_a: Vec3, position_b: Vec3, velocity_b: Vec3) -> Vec3 {
+        let offset = position_b - position_a;
+        let length = offset.length();
+        if length == 0.0 {
+            return Vec3::ZERO;
+        }
+        let direction = offset / length;
+        let stretch = length - self.rest_length;
+        let speed = (velocity_b - velocity_a).dot(direction);
+        direction * (self.stiffness * stretch + self.damping * speed)
+    }
+}
+
+#[derive(Component)]
+struct SpringScene;
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(UiValues{
+            scene: 0,
+            stiffness: 200.0,
+            damping: 2.0,
+            restart: true
+        })
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 3.10 3.11".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              restart,
+                              moving,
+                              draw_springs).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-std::f32::consts::PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 28.0,
+            ..default()
+        }
+    ));
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<SpringScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let mesh = meshes.add(Mesh::from(Sphere { radius: NODE_RADIUS }));
+    let node_material = materials.add(StandardMaterial {
+        base_color: Color::Srgba(BLUE),
+        ..default()
+    });
+    let anchor_material = materials.add(StandardMaterial {
+        base_color: Color::Srgba(RED),
+        ..default()
+    });
+    let spawn_node = |commands: &mut Commands, position: Vec3, anchored: bool| {
+        let material = if anchored { anchor_material.clone() } else { node_material.clone() };
+        let mut node = commands.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material),
+            Transform::from_translation(position),
+            Moveable::default(),
+            SpringScene
+        ));
+        if anchored {
+            node.insert(Anchor);
+        }
+        node.id()
+    };
+    let spawn_spring = |commands: &mut Commands, a: Entity, b: Entity, rest_length: f32| {
+        commands.spawn((
+            Spring {
+                a,
+                b,
+                rest_length,
+                stiffness: ui_values.stiffness,
+                damping: ui_values.damping
+            },
+            SpringScene
+        ));
+    };
+
+    match ui_values.scene {
+        //a chain hanging from the top of the limit cube, released sideways
+        0 => {
+            let length = 1.0;
+            let mut previous = spawn_node(&mut commands, Vec3::new(0.0, MAX_LIMIT, 0.0), true);
+            for i in 1..=10 {
+                let position = Vec3::new(i as f32 * length * 0.7, MAX_LIMIT - i as f32 * length * 0.7, 0.0);
+                let node = spawn_node(&mut commands, position, false);
+                spawn_spring(&mut commands, previous, node, length);
+                previous = node;
+            }
+        }
+        //a cloth hanging from two corners; structural springs keep the grid, shear
+        //springs across the cells keep it from folding
+        1 => {
+            let size = 12;
+            let spacing = 0.8;
+            let start = Vec3::new(-((size - 1) as f32) * spacing / 2.0, MAX_LIMIT, 0.0);
+            let mut grid = Vec::new();
+            for row in 0..size {
+                for column in 0..size {
+                    let anchored = row == 0 && (column == 0 || column == size - 1);
+                    let position = start + Vec3::new(column as f32 * spacing, 0.0, row as f32 * spacing);
+                    grid.push(spawn_node(&mut commands, position, anchored));
+                }
+            }
+            let diagonal = spacing * std::f32::consts::SQRT_2;
+            for row in 0..size {
+                for column in 0..size {
+                    let node = grid[row * size + column];
+                    if column + 1 < size {
+                        spawn_spring(&mut commands, node, grid[row * size + column + 1], spacing);
+                    }
+                    if row + 1 < size {
+                        spawn_spring(&mut commands, node, grid[(row + 1) * size + column], spacing);
+                    }
+                    if row + 1 < size && column + 1 < size {
+                        spawn_spring(&mut commands, node, grid[(row + 1) * size + column + 1], diagonal);
+                        spawn_spring(&mut commands, grid[row * size + column + 1], grid[(row + 1) * size + column], diagonal);
+                    }
+                }
+            }
+        }
+        //a lattice of nodes, each connected to all neighbours of its cell, dropped
+        //tilted onto the floor of the limit cube
+        _ => {
+            let size = 4;
+            let spacing = 1.0;
+            let rotation = Quat::from_euler(EulerRot::XYZ, 0.4, 0.3, 0.2);
+            let center = Vec3::new(0.0, MAX_LIMIT / 2.0, 0.0);
+            let offset = (size - 1) as f32 * spacing / 2.0;
+            let mut nodes = Vec::new();
+            for x in 0..size {
+                for y in 0..size {
+                    for z in 0..size {
+                        let local = Vec3::new(x as f32, y as f32, z as f32) * spacing - Vec3::splat(offset);
+                        let position = center + rotation * local;
+                        nodes.push((IVec3::new(x, y, z), spawn_node(&mut commands, position, false)));
+                    }
+                }
+            }
+            for (i, (cell_a, a)) in nodes.iter().enumerate() {
+                for (cell_b, b) in nodes.iter().skip(i + 1) {
+                    let difference = (*cell_b - *cell_a).abs();
+                    if difference.max_element() == 1 {
+                        spawn_spring(&mut commands, *a, *b, difference.as_vec3().length() * spacing);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn moving(
+    time:Res<Time>,
+    ui_values: Res<UiValues>,
+    mut springs: Query<&mut Spring>,
+    mut query: Query<(&mut Transform, &mut Moveable, Has<Anchor>)>
+){
+    for mut spring in springs.iter_mut() {
+        spring.stiffness = ui_values.stiffness;
+        spring.damping = ui_values.damping;
+    }
+    //a long frame would make the springs explode
+    let delta = time.delta_secs().min(MAX_FRAME_TIME) / SUBSTEPS as f32;
+
+    for _ in 0..SUBSTEPS {
+        for spring in springs.iter() {
+            let Ok([(transform_a, mut moveable_a, _), (transform_b, mut moveable_b, _)]) =
+                query.get_many_mut([spring.a, spring.b]) else {
+                continue;
+            };
+            let force = spring.force(transform_a.translation, moveable_a.velocity,
+                                     transform_b.translation, moveable_b.velocity);
+            moveable_a.apply_force(force);
+            moveable_b.apply_force(-force);
+        }
+
+        for (mut transform, mut moveable, anchored) in query.iter_mut() {
+            if anchored {
+                moveable.acceleration = Vec3::ZERO;
+                continue;
+            }
+            let gravity = Vec3::new(0.0, -GRAVITY, 0.0) * moveable.mass;
+            moveable.apply_force(gravity);
+
+            //semi implicit euler, the new velocity moves the node
+            let acceleration = moveable.acceleration;
+            moveable.velocity += acceleration * delta;
+            transform.translation += moveable.velocity * delta;
+            moveable.acceleration = Vec3::ZERO;
+
+            //bounce off the walls of the limit cube
+            for axis in 0..3 {
+                let position = transform.translation[axis];
+                let (limit, inwards) = if position < MIN_LIMIT + NODE_RADIUS {
+                    (MIN_LIMIT + NODE_RADIUS, 1.0)
+                } else if position > MAX_LIMIT - NODE_RADIUS {
+                    (MAX_LIMIT - NODE_RADIUS, -1.0)
+                } else {
+                    continue;
+                };
+                transform.translation[axis] = limit;
+                if moveable.velocity[axis] * inwards < 0.0 {
+                    moveable.velocity[axis] *= -RESTITUTION;
+                    for other in (0..3).filter(|other| *other != axis) {
+                        moveable.velocity[other] *= 1.0 - FRICTION;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn draw_springs(
+    mut gizmos: Gizmos,
+    springs: Query<&Spring>,
+    nodes: Query<&Transform, With<Moveable>>
+){
+    for spring in springs.iter() {
+        let Ok([a, b]) = nodes.get_many([spring.a, spring.b]) else {
+            continue;
+        };
+        gizmos.line(a.translation, b.translation, LIME);
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+){
+    let mut scene = ui_values.scene;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Scene")
+            .selected_text(SCENES[scene])
+            .show_ui(ui, |ui| {
+                for (index, name) in SCENES.iter().enumerate() {
+                    ui.selectable_value(&mut scene, index, *name);
+                }
+            });
+        ui.add(egui::Slider::new(&mut ui_values.stiffness, 10.0..=1000.0).text("stiffness"));
+        ui.add(egui::Slider::new(&mut ui_values.damping, 0.0..=10.0).text("damping"));
+        if ui.button("Restart").clicked() {
+            ui_values.restart = true;
+        }
+    });
+    if scene != ui_values.scene {
+        ui_values.scene = scene;
+        ui_values.restart = true;
+    }
+}
@@ -0,0 +1,220 @@
+// shared by several examples, each one uses only a part of it
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::NoFrustumCulling;
+use bevy::transform::TransformSystem;
+
+// Draws the recent path of any entity with a Trail. The positions are kept in a ring
+// buffer and turned into a line strip or a ribbon facing the camera, fading out
+// towards the oldest position.
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<TrailMaterial>()
+            .add_systems(PostUpdate, (spawn_trail_meshes,
+                                      record_trails,
+                                      build_trail_meshes,
+                                      despawn_trail_meshes).chain()
+                .after(TransformSystem::TransformPropagate));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TrailMode {
+    Line,
+    Ribbon { width: f32 }
+}
+
+#[derive(Component)]
+pub struct Trail {
+    pub length: usize,
+    //a new position is recorded after the entity moved that far
+    pub spacing: f32,
+    pub color: Color,
+    pub mode: TrailMode,
+    points: VecDeque<Vec3>,
+    mesh: Option<Entity>
+}
+
+impl Trail {
+    pub fn new(length: usize, color: Color) -> Self {
+        Trail {
+            length,
+            spacing: 0.1,
+            color,
+            mode: TrailMode::Line,
+            points: VecDeque::with_capacity(length),
+            mesh: None
+        }
+    }
+
+    pub fn with_mode(mut self, mode: TrailMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    fn record(&mut self, position: Vec3) {
+        if self.points.front().is_some_and(|last| last.distance(position) < self.spacing) {
+            return;
+        }
+        if self.points.len() >= self.length {
+            self.points.pop_back();
+        }
+        self.points.push_front(position);
+    }
+
+    // newest first, starting at the current position
+    fn path(&self, position: Vec3) -> Vec<Vec3> {
+        std::iter::once(position).chain(self.points.iter().copied()).collect()
+    }
+}
+
+// the trail is drawn by its own entity in world space, so it does not move with its owner
+#[derive(Component)]
+struct TrailMesh {
+    owner: Entity
+}
+
+// one unlit material for all trails, the colour is in the vertices
+#[derive(Resource)]
+struct TrailMaterial(Handle<StandardMaterial>);
+
+impl FromWorld for TrailMaterial {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        TrailMaterial(materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        }))
+    }
+}
+
+fn spawn_trail_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    material: Res<TrailMaterial>,
+    mut query: Query<(Entity, &mut Trail), Added<Trail>>
+){
+    for (entity, mut trail) in query.iter_mut() {
+        let mesh = commands.spawn((
+            Mesh3d(meshes.add(Mesh::new(PrimitiveTopology::LineStrip, RenderAssetUsages::default()))),
+            MeshMaterial3d(material.0.clone()),
+            Transform::default(),
+            Visibility::Hidden,
+            //the bounds change every frame
+            NoFrustumCulling,
+            TrailMesh { owner: entity }
+        )).id();
+        trail.mesh = Some(mesh);
+    }
+}
+
+fn record_trails(
+    mut query: Query<(&mut Trail, &GlobalTransform, Option<&Visibility>)>
+){
+    for (mut trail, transform, visibility) in query.iter_mut() {
+        //hidden entities, like particles back in their pool, start a new trail
+        if visibility == Some(&Visibility::Hidden) {
+            trail.clear();
+            continue;
+        }
+        trail.record(transform.translation());
+    }
+}
+
+fn build_trail_meshes(
+    mut meshes: ResMut<Assets<Mesh>>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    query: Query<(&Trail, &GlobalTransform)>,
+    mut trail_meshes: Query<(&Mesh3d, &mut Visibility), With<TrailMesh>>
+){
+    let eye = camera.get_single().ok().map(|transform| transform.translation());
+
+    for (trail, transform) in query.iter() {
+        let Some(Ok((mesh, mut visibility))) = trail.mesh.map(|entity| trail_meshes.get_mut(entity)) else {
+            continue;
+        };
+        if trail.points.is_empty() {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            *mesh = trail_mesh(trail, &trail.path(transform.translation()), eye);
+        }
+    }
+}
+
+fn despawn_trail_meshes(
+    mut commands: Commands,
+    trails: Query<&Trail>,
+    query: Query<(Entity, &TrailMesh)>
+){
+    for (entity, trail_mesh) in query.iter() {
+        if !trails.contains(trail_mesh.owner) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn trail_mesh(trail: &Trail, path: &[Vec3], eye: Option<Vec3>) -> Mesh {
+    let color = trail.color.to_linear();
+    let last = (path.len() - 1) as f32;
+    //alpha fades from the head of the trail to its end
+    let fade = |index: usize| {
+        let alpha = color.alpha * (1.0 - index as f32 / last);
+        [color.red, color.green, color.blue, alpha]
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+
+    let topology = match trail.mode {
+        TrailMode::Line => {
+            for (index, point) in path.iter().enumerate() {
+                positions.push(point.to_array());
+                normals.push([0.0, 1.0, 0.0]);
+                colors.push(fade(index));
+            }
+            PrimitiveTopology::LineStrip
+        }
+        TrailMode::Ribbon { width } => {
+            for (index, point) in path.iter().enumerate() {
+                let tangent = path[(index + 1).min(path.len() - 1)] - path[index.saturating_sub(1)];
+                let to_eye = eye.map_or(Vec3::Z, |eye| (eye - *point).normalize_or(Vec3::Z));
+                let side = tangent.cross(to_eye).normalize_or_zero() * width / 2.0;
+                for vertex in [*point + side, *point - side] {
+                    positions.push(vertex.to_array());
+                    normals.push(to_eye.to_array());
+                    colors.push(fade(index));
+                }
+            }
+            PrimitiveTopology::TriangleStrip
+        }
+    };
+
+    Mesh::new(topology, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+}
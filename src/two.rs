@@ -1,14 +1,17 @@
 use std::time::Duration;
 use bevy::prelude::*;
-use bevy::color::palettes::basic::BLUE;
+use bevy::color::palettes::basic::{AQUA, BLUE, OLIVE, YELLOW};
 use bevy::time::common_conditions::on_timer;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use bevy::window::WindowResolution;
 use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
 use crate::mesh::spawn_limit_cube;
+use wind::{wind_ui, Wind, WindPlugin};
 
 mod orbitcamera;
 mod mesh;
+mod noise;
+mod wind;
 use rand::Rng;
 
 const HEIGHT: f32 = 640.0;
@@ -17,10 +20,15 @@ const WIDTH: f32 = 960.0;
 const MAX_LIMIT: f32 = 8.0;
 const MIN_LIMIT: f32 = -8.0;
 
+const GRAVITY: f32 = 10.0;
+//share of the vertical speed a sphere keeps when it bounces off the floor
+const RESTITUTION: f32 = 0.5;
+
 #[derive(Resource)]
 struct UiValues{
-    wind: bool,
-    num_of_spheres: u8
+    num_of_spheres: u8,
+    friction: f32,
+    fluids: bool
 }
 
 #[derive(Component)]
@@ -44,15 +52,60 @@ impl Moveable {
     fn apply_force(&mut self, force:Vec3)->(){
        self.acceleration = self.acceleration +  force/self.mass;
     }
+
+    fn radius(&self) -> f32 {
+        0.5 * self.mass
+    }
+}
+
+// slows a sphere down while it rolls on the floor of the limit cube, the force
+// is coefficient * normal force against the direction of the velocity
+#[derive(Component)]
+struct Friction {
+    coefficient: f32
 }
 
+// a box of liquid, everything inside is slowed down by quadratic drag
+// -1/2 * density * v² * drag_coefficient * area in the direction of the velocity
+#[derive(Component)]
+struct FluidVolume {
+    density: f32,
+    drag_coefficient: f32,
+    half_extents: Vec3
+}
+
+impl FluidVolume {
+    fn contains(&self, transform: &Transform, point: Vec3) -> bool {
+        let local = (point - transform.translation).abs();
+        local.cmple(self.half_extents).all()
+    }
+
+    fn drag(&self, velocity: Vec3, area: f32) -> Vec3 {
+        let speed = velocity.length();
+        -0.5 * self.density * speed * speed * self.drag_coefficient * area * velocity.normalize_or_zero()
+    }
+}
+
+// layers of liquid from thin at the top to thick at the bottom
+const FLUIDS: [(f32, f32, Srgba); 3] = [
+    //top, density, color
+    (1.0, 0.2, YELLOW),
+    (-2.0, 1.0, AQUA),
+    (-5.0, 4.0, OLIVE),
+];
+
 fn main() {
     App::new()
         .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(Wind {
+            enabled: true,
+            ..default()
+        })
 
         .insert_resource(UiValues{
-            wind: true,
-            num_of_spheres: 1
+            num_of_spheres: 1,
+            friction: 0.3,
+            fluids: false
         })
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -65,7 +118,8 @@ fn main() {
         }))
         .add_plugins((
             EguiPlugin,
-            OrbitCameraPlugin
+            OrbitCameraPlugin,
+            WindPlugin
         ))
         .add_systems(Startup, (spawn_camera,
                                spawn_scene,spawn_limit_cube)
@@ -75,6 +129,7 @@ fn main() {
             spawn_sphere.run_if(on_timer(Duration::from_secs(1))),
         )
         .add_systems(Update, (ui_egui,
+                              update_fluids,
                               despawn_sphere,
                               moving)
         )
@@ -140,6 +195,9 @@ fn spawn_sphere(
                 velocity: Vec3::new(0.0, 0.0, 0.0),
                 mass: mass,
                 ..default()
+            },
+            Friction {
+                coefficient: ui_values.friction
             }
         ));
     }
@@ -156,23 +214,91 @@ fn despawn_sphere(
     }
 }
 
+fn update_fluids(
+    mut commands:Commands,
+    ui_values: Res<UiValues>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<FluidVolume>>
+){
+    if !ui_values.is_changed() {
+        return;
+    }
+    if ui_values.fluids && query.is_empty() {
+        let mut bottom = MIN_LIMIT;
+        for (top, density, color) in FLUIDS.into_iter().rev() {
+            let half_extents = Vec3::new(MAX_LIMIT, (top - bottom) / 2.0, MAX_LIMIT);
+            commands.spawn((
+                Mesh3d(meshes.add(Mesh::from(Cuboid::from_size(half_extents * 2.0)))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: Color::Srgba(color.with_alpha(0.25)),
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                })),
+                Transform::from_xyz(0.0, (top + bottom) / 2.0, 0.0),
+                FluidVolume {
+                    density,
+                    drag_coefficient: 0.47,
+                    half_extents
+                }
+            ));
+            bottom = top;
+        }
+    }
+    if !ui_values.fluids {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
 fn moving(
     time:Res<Time>,
-    ui_values: Res<UiValues>,
-    mut query: Query<(&mut Transform, &mut Moveable)>
+    wind: Res<Wind>,
+    mut query: Query<(&mut Transform, &mut Moveable, Option<&Friction>)>,
+    fluids: Query<(&Transform, &FluidVolume), Without<Moveable>>
 ){
-    let wind = Vec3::new(8.0,0.0,0.0);
+    let delta = time.delta_secs();
 
-    for (mut transform, mut moveable) in &mut query {
+    for (mut transform, mut moveable, friction) in &mut query {
 
-        let gravity = Vec3::new(0.0,-10.0,0.0) * moveable.mass;
-        if ui_values.wind {
-            moveable.apply_force(wind);
-        }
+        let gravity = Vec3::new(0.0,-GRAVITY,0.0) * moveable.mass;
+        moveable.apply_force(wind.force_at(transform.translation));
         moveable.apply_force(gravity);
 
-        moveable.velocity = moveable.velocity + moveable.acceleration * time.delta_secs();
-        transform.translation = transform.translation + moveable.velocity * time.delta_secs();
+        //drag and friction only slow down, they must not turn the sphere around
+        //within one frame
+        let max_brake = moveable.mass * moveable.velocity.length() / delta.max(f32::EPSILON);
+        let radius = moveable.radius();
+        for (fluid_transform, fluid) in fluids.iter() {
+            if fluid.contains(fluid_transform, transform.translation) {
+                let area = std::f32::consts::PI * radius * radius;
+                let drag = fluid.drag(moveable.velocity, area);
+                moveable.apply_force(drag.clamp_length_max(max_brake));
+            }
+        }
+
+        //the floor only reaches as far as the limit cube, beyond it the spheres fall
+        let above_floor = transform.translation.x.abs() <= MAX_LIMIT && transform.translation.z.abs() <= MAX_LIMIT;
+        let on_floor = above_floor && transform.translation.y - radius <= MIN_LIMIT;
+        if let (Some(friction), true) = (friction, on_floor) {
+            let sliding = Vec3::new(moveable.velocity.x, 0.0, moveable.velocity.z);
+            let normal = moveable.mass * GRAVITY;
+            let force = -friction.coefficient * normal * sliding.normalize_or_zero();
+            let max_brake = moveable.mass * sliding.length() / delta.max(f32::EPSILON);
+            moveable.apply_force(force.clamp_length_max(max_brake));
+        }
+
+        let acceleration = moveable.acceleration;
+        moveable.velocity += acceleration * delta;
+        transform.translation += moveable.velocity * delta;
+
+        if on_floor && transform.translation.y - radius < MIN_LIMIT {
+            transform.translation.y = MIN_LIMIT + radius;
+            if moveable.velocity.y < 0.0 {
+                moveable.velocity.y *= -RESTITUTION;
+            }
+        }
 
         moveable.acceleration *= 0.0;
     }
@@ -182,9 +308,21 @@ fn moving(
 fn ui_egui(
     mut egui_contexts: EguiContexts,
     mut ui_values: ResMut<UiValues>,
+    mut wind: ResMut<Wind>,
+    mut query: Query<&mut Friction>
 ){
+    let mut friction = ui_values.friction;
     egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
         ui.add(egui::Slider::new(&mut (ui_values.num_of_spheres), 1..=20).text("Spheres p. Sec."));
-        ui.add(egui::Checkbox::new(&mut (ui_values.wind),"wind"));
+        ui.add(egui::Slider::new(&mut friction, 0.0..=1.0).text("friction"));
+        ui.add(egui::Checkbox::new(&mut (ui_values.fluids),"fluids"));
+        ui.separator();
+        wind_ui(ui, &mut wind);
     });
-}
\ No newline at end of file
+    if friction != ui_values.friction {
+        ui_values.friction = friction;
+        for mut sphere in query.iter_mut() {
+            sphere.coefficient = friction;
+        }
+    }
+}
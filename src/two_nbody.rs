@@ -0,0 +1,343 @@
+use bevy::color::palettes::basic::{BLUE, GRAY, RED, YELLOW};
+use bevy::prelude::*;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use octree::{direct_accelerations, Octree};
+use trail::{Trail, TrailPlugin};
+use rand::Rng;
+
+use std::f32::consts::{PI, TAU};
+
+mod orbitcamera;
+mod mesh;
+mod octree;
+mod trail;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 8.0;
+const MIN_LIMIT: f32 = -8.0;
+
+const GRAVITY: f32 = 1.0;
+//keeps the force finite when two bodies come very close
+const SOFTENING: f32 = 0.05;
+const SUBSTEPS: u32 = 8;
+const MAX_FRAME_TIME: f32 = 1.0 / 30.0;
+
+const SCENES: [&str; 2] = ["Sun, planets and moons", "Galaxy"];
+const GALAXY_STARS: usize = 800;
+
+#[derive(Resource)]
+struct UiValues{
+    scene: usize,
+    restart: bool,
+    barnes_hut: bool,
+    theta: f32,
+    time_scale: f32
+}
+
+// what the readout shows, the start energy tells how far the simulation drifted
+#[derive(Resource, Default)]
+struct Conservation {
+    start_energy: Option<f32>,
+    energy: f32,
+    momentum: Vec3,
+    angular_momentum: Vec3,
+    force_time: f32
+}
+
+#[derive(Component)]
+struct Moveable {
+    velocity:Vec3,
+    acceleration: Vec3,
+    mass: f32
+}
+
+// every moveable with an attractor pulls every other one and is pulled by them
+#[derive(Component)]
+struct Attractor;
+
+#[derive(Component)]
+struct NBodyScene;
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(UiValues{
+            scene: 0,
+            restart: true,
+            barnes_hut: true,
+            theta: 0.5,
+            time_scale: 1.0
+        })
+        .init_resource::<Conservation>()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 2.7 2.8".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin,
+            TrailPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              restart,
+                              moving,
+                              measure).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 28.0,
+            ..default()
+        }
+    ));
+}
+
+// speed of a circular orbit at distance around mass
+fn orbit_speed(mass: f32, distance: f32) -> f32 {
+    (GRAVITY * mass / distance).sqrt()
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut conservation: ResMut<Conservation>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<NBodyScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    *conservation = Conservation::default();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let mesh = meshes.add(Mesh::from(Sphere { radius: 1.0 }));
+    let mut spawn_body = |commands: &mut Commands, position: Vec3, velocity: Vec3, mass: f32, radius: f32, color: Srgba| {
+        commands.spawn((
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::Srgba(color),
+                ..default()
+            })),
+            Transform::from_translation(position).with_scale(Vec3::splat(radius)),
+            Moveable {
+                velocity,
+                acceleration: Vec3::ZERO,
+                mass
+            },
+            Attractor,
+            NBodyScene
+        )).id()
+    };
+
+    match ui_values.scene {
+        //planets on circular orbits, each with moons on circular orbits around it
+        0 => {
+            let sun_mass = 1000.0;
+            spawn_body(&mut commands, Vec3::ZERO, Vec3::ZERO, sun_mass, 0.8, YELLOW);
+            //distance, mass, moon distances
+            let planets: [(f32, f32, &[f32]); 3] = [
+                (3.0, 10.0, &[0.35]),
+                (5.0, 30.0, &[0.4, 0.65]),
+                (7.0, 20.0, &[0.5]),
+            ];
+            for (number, (distance, mass, moons)) in planets.into_iter().enumerate() {
+                let angle = number as f32 * TAU / 3.0;
+                let direction = Vec3::new(angle.cos(), 0.0, angle.sin());
+                let tangent = Vec3::Y.cross(direction);
+                let position = direction * distance;
+                let velocity = tangent * orbit_speed(sun_mass, distance);
+                let planet = spawn_body(&mut commands, position, velocity, mass, 0.25, BLUE);
+                commands.entity(planet).insert(Trail::new(200, Color::Srgba(BLUE)));
+
+                for moon_distance in moons {
+                    let moon_position = position + tangent * *moon_distance;
+                    let moon_velocity = velocity - direction * orbit_speed(mass, *moon_distance);
+                    let moon = spawn_body(&mut commands, moon_position, moon_velocity, 0.01, 0.08, GRAY);
+                    commands.entity(moon).insert(Trail::new(60, Color::Srgba(GRAY)));
+                }
+            }
+        }
+        //a disk of stars around a heavy center, every star on the orbit that fits
+        //the mass inside of it
+        _ => {
+            let center_mass = 2000.0;
+            let star_mass = 0.5;
+            spawn_body(&mut commands, Vec3::ZERO, Vec3::ZERO, center_mass, 0.5, RED);
+            let mut rng = rand::rng();
+            let mut distances: Vec<f32> = (0..GALAXY_STARS).map(|_| rng.random_range(1.0..MAX_LIMIT - 1.0)).collect();
+            distances.sort_by(f32::total_cmp);
+            for (inside, distance) in distances.into_iter().enumerate() {
+                let angle = rng.random_range(0.0..TAU);
+                let direction = Vec3::new(angle.cos(), 0.0, angle.sin());
+                let height = rng.random_range(-0.2..0.2);
+                let speed = orbit_speed(center_mass + inside as f32 * star_mass, distance);
+                spawn_body(&mut commands, direction * distance + Vec3::Y * height,
+                           Vec3::Y.cross(direction) * speed, star_mass, 0.06, BLUE);
+            }
+        }
+    }
+}
+
+fn accelerations(bodies: &[(Vec3, f32)], ui_values: &UiValues) -> Vec<Vec3> {
+    if !ui_values.barnes_hut {
+        return direct_accelerations(bodies, GRAVITY, SOFTENING);
+    }
+    let octree = Octree::new(bodies);
+    bodies.iter().enumerate()
+        .map(|(index, (position, _))| octree.acceleration(*position, index, ui_values.theta, GRAVITY, SOFTENING))
+        .collect()
+}
+
+fn moving(
+    time:Res<Time>,
+    ui_values: Res<UiValues>,
+    mut conservation: ResMut<Conservation>,
+    mut query: Query<(&mut Transform, &mut Moveable), With<Attractor>>
+){
+    let mut bodies: Vec<(Vec3, f32)> = query.iter().map(|(transform, moveable)| (transform.translation, moveable.mass)).collect();
+    let mut velocities: Vec<Vec3> = query.iter().map(|(_, moveable)| moveable.velocity).collect();
+    let dt = time.delta_secs().min(MAX_FRAME_TIME) * ui_values.time_scale / SUBSTEPS as f32;
+    let start = std::time::Instant::now();
+
+    //leapfrog: half a kick, a drift, the new accelerations and another half kick,
+    //it keeps the energy of orbits from drifting away
+    let mut acceleration = accelerations(&bodies, &ui_values);
+    for _ in 0..SUBSTEPS {
+        for ((position, _), (velocity, acceleration)) in bodies.iter_mut().zip(velocities.iter_mut().zip(&acceleration)) {
+            *velocity += *acceleration * dt / 2.0;
+            *position += *velocity * dt;
+        }
+        acceleration = accelerations(&bodies, &ui_values);
+        for (velocity, acceleration) in velocities.iter_mut().zip(&acceleration) {
+            *velocity += *acceleration * dt / 2.0;
+        }
+    }
+    conservation.force_time = start.elapsed().as_secs_f32();
+
+    for ((mut transform, mut moveable), ((position, _), (velocity, acceleration))) in
+        query.iter_mut().zip(bodies.iter().zip(velocities.iter().zip(&acceleration))) {
+        transform.translation = *position;
+        moveable.velocity = *velocity;
+        moveable.acceleration = *acceleration;
+    }
+}
+
+// total energy, momentum and angular momentum; without outside forces all three
+// stay the same
+fn measure(
+    ui_values: Res<UiValues>,
+    mut conservation: ResMut<Conservation>,
+    query: Query<(&Transform, &Moveable), With<Attractor>>
+){
+    let bodies: Vec<(Vec3, f32)> = query.iter().map(|(transform, moveable)| (transform.translation, moveable.mass)).collect();
+    if bodies.is_empty() {
+        return;
+    }
+    let octree = Octree::new(&bodies);
+    //the direct sum is exact with theta 0
+    let theta = if ui_values.barnes_hut { ui_values.theta } else { 0.0 };
+
+    let mut kinetic = 0.0;
+    let mut potential = 0.0;
+    let mut momentum = Vec3::ZERO;
+    let mut angular_momentum = Vec3::ZERO;
+    for (index, (transform, moveable)) in query.iter().enumerate() {
+        let position = transform.translation;
+        kinetic += 0.5 * moveable.mass * moveable.velocity.length_squared();
+        //every pair is counted twice
+        potential += 0.5 * moveable.mass * octree.potential(position, index, theta, GRAVITY, SOFTENING);
+        momentum += moveable.mass * moveable.velocity;
+        angular_momentum += position.cross(moveable.mass * moveable.velocity);
+    }
+    conservation.energy = kinetic + potential;
+    conservation.momentum = momentum;
+    conservation.angular_momentum = angular_momentum;
+    if conservation.start_energy.is_none() {
+        conservation.start_energy = Some(conservation.energy);
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    conservation: Res<Conservation>,
+    query: Query<(), With<Attractor>>
+){
+    let mut scene = ui_values.scene;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Scene")
+            .selected_text(SCENES[scene])
+            .show_ui(ui, |ui| {
+                for (index, name) in SCENES.iter().enumerate() {
+                    ui.selectable_value(&mut scene, index, *name);
+                }
+            });
+        ui.add(egui::Checkbox::new(&mut ui_values.barnes_hut, "Barnes-Hut octree"));
+        ui.add_enabled(ui_values.barnes_hut, egui::Slider::new(&mut ui_values.theta, 0.0..=1.5).text("theta"));
+        ui.add(egui::Slider::new(&mut ui_values.time_scale, 0.1..=2.0).text("time scale"));
+        if ui.button("Restart").clicked() {
+            ui_values.restart = true;
+        }
+        ui.separator();
+        ui.label(format!("bodies: {}", query.iter().count()));
+        ui.label(format!("forces: {:.1} ms per frame", conservation.force_time * 1000.0));
+        ui.label(format!("energy: {:.2}", conservation.energy));
+        if let Some(start_energy) = conservation.start_energy {
+            let drift = (conservation.energy - start_energy) / start_energy.abs().max(f32::EPSILON);
+            ui.label(format!("energy drift: {:.4} %", drift * 100.0));
+        }
+        let momentum = conservation.momentum;
+        ui.label(format!("momentum: ({:.3}, {:.3}, {:.3})", momentum.x, momentum.y, momentum.z));
+        let angular_momentum = conservation.angular_momentum;
+        ui.label(format!("angular momentum: ({:.1}, {:.1}, {:.1})",
+                         angular_momentum.x, angular_momentum.y, angular_momentum.z));
+    });
+    if scene != ui_values.scene {
+        ui_values.scene = scene;
+        ui_values.restart = true;
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use rand::Rng;
+
+use crate::noise::perlin;
+
+// A wind blowing everywhere: a steady part along direction, gusts that come and go
+// at random times and turbulence from 3D Perlin noise over position and time.
+pub struct WindPlugin;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App){
+        app
+            .init_resource::<Wind>()
+            .init_resource::<GustSchedule>()
+            .add_systems(PreUpdate, update_wind);
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct Wind {
+    pub enabled: bool,
+    pub direction: Vec3,
+    pub strength: f32,
+    //extra strength at the peak of a gust
+    pub gust_strength: f32,
+    //mean seconds between two gusts
+    pub gust_interval: f32,
+    pub gust_duration: f32,
+    pub turbulence: f32,
+    //size of the eddies, small values give large eddies
+    pub turbulence_scale: f32,
+    //how fast the eddies change
+    pub turbulence_speed: f32,
+    //0 between gusts, 1 at the peak of one
+    pub gust: f32,
+    pub time: f32
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Wind {
+            enabled: false,
+            direction: Vec3::X,
+            strength: 8.0,
+            gust_strength: 8.0,
+            gust_interval: 4.0,
+            gust_duration: 1.5,
+            turbulence: 4.0,
+            turbulence_scale: 0.3,
+            turbulence_speed: 0.5,
+            gust: 0.0,
+            time: 0.0
+        }
+    }
+}
+
+impl Wind {
+    pub fn force_at(&self, position: Vec3) -> Vec3 {
+        if !self.enabled {
+            return Vec3::ZERO;
+        }
+        let steady = self.direction.normalize_or_zero() * (self.strength + self.gust * self.gust_strength);
+
+        //one noise value per axis, sampled far apart so the axes do not follow each other
+        let sample = position * self.turbulence_scale + Vec3::splat(self.time * self.turbulence_speed);
+        let turbulence = Vec3::new(
+            perlin([sample.x, sample.y, sample.z]),
+            perlin([sample.x + 31.4, sample.y + 47.2, sample.z + 12.9]),
+            perlin([sample.x + 73.1, sample.y + 5.3, sample.z + 91.7])
+        );
+        steady + turbulence * self.turbulence
+    }
+}
+
+#[derive(Resource, Default)]
+struct GustSchedule {
+    next: f32,
+    start: Option<f32>
+}
+
+fn update_wind(
+    time: Res<Time>,
+    mut wind: ResMut<Wind>,
+    mut schedule: ResMut<GustSchedule>
+){
+    wind.time += time.delta_secs();
+    let now = wind.time;
+
+    match schedule.start {
+        //a gust swells and fades along half a sine
+        Some(start) if now - start < wind.gust_duration => {
+            wind.gust = (std::f32::consts::PI * (now - start) / wind.gust_duration).sin();
+        }
+        Some(_) => {
+            wind.gust = 0.0;
+            schedule.start = None;
+            schedule.next = now + wind.gust_interval * rand::rng().random_range(0.5..1.5);
+        }
+        None if now >= schedule.next && wind.gust_strength > 0.0 => {
+            schedule.start = Some(now);
+        }
+        None => {}
+    }
+}
+
+// the controls of the wind, shared by the examples
+pub fn wind_ui(ui: &mut egui::Ui, wind: &mut Wind) {
+    ui.add(egui::Checkbox::new(&mut wind.enabled, "wind"));
+    ui.add_enabled_ui(wind.enabled, |ui| {
+        //the direction as compass angle and elevation
+        let direction = wind.direction.normalize_or(Vec3::X);
+        let mut heading = direction.z.atan2(direction.x).to_degrees();
+        let mut elevation = direction.y.clamp(-1.0, 1.0).asin().to_degrees();
+        ui.add(egui::Slider::new(&mut heading, -180.0..=180.0).text("direction °"));
+        ui.add(egui::Slider::new(&mut elevation, -90.0..=90.0).text("elevation °"));
+        let (heading, elevation) = (heading.to_radians(), elevation.to_radians());
+        let direction = Vec3::new(heading.cos() * elevation.cos(), elevation.sin(), heading.sin() * elevation.cos());
+        if direction.distance(wind.direction) > 0.0001 {
+            wind.direction = direction;
+        }
+
+        ui.add(egui::Slider::new(&mut wind.strength, 0.0..=30.0).text("strength"));
+        ui.add(egui::Slider::new(&mut wind.gust_strength, 0.0..=30.0).text("gust strength"));
+        ui.add(egui::Slider::new(&mut wind.gust_interval, 0.5..=10.0).text("gust interval"));
+        ui.add(egui::Slider::new(&mut wind.gust_duration, 0.1..=5.0).text("gust duration"));
+        ui.add(egui::ProgressBar::new(wind.gust).text("gust"));
+        ui.add(egui::Slider::new(&mut wind.turbulence, 0.0..=20.0).text("turbulence"));
+        ui.add(egui::Slider::new(&mut wind.turbulence_scale, 0.05..=2.0).text("eddy scale"));
+        ui.add(egui::Slider::new(&mut wind.turbulence_speed, 0.0..=3.0).text("eddy speed"));
+    });
+}
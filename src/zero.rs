@@ -0,0 +1,752 @@
+use bevy::color::palettes::basic::{BLUE, GREEN, RED, WHITE, YELLOW};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::window::WindowResolution;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use orbitcamera::{OrbitCameraPlugin, OrbitCamera};
+use noise::{perlin, simplex, Fbm};
+use trail::{Trail, TrailPlugin};
+use rand::Rng;
+
+use std::f32::consts::{PI, TAU};
+
+mod orbitcamera;
+mod mesh;
+mod noise;
+mod trail;
+
+const HEIGHT: f32 = 640.0;
+const WIDTH: f32 = 960.0;
+
+const MAX_LIMIT: f32 = 8.0;
+const MIN_LIMIT: f32 = -8.0;
+
+const SCENES: [&str; 3] = ["Random walkers", "Gaussian histogram", "Noise terrain"];
+const NOISES: [&str; 2] = ["Perlin", "Simplex"];
+
+const WALKER_STEP: f32 = 0.2;
+//the tail of the lévy flight, smaller values give more long jumps
+const LEVY_ALPHA: f32 = 1.5;
+//the longest step of the lévy flight and the monte carlo walk
+const MAX_STEP: f32 = (MAX_LIMIT - MIN_LIMIT) / 4.0;
+//cells of the heatmap along each axis
+const VOXELS: usize = 16;
+const HEAT_COLORS: usize = 8;
+const HEADLESS_STEPS: u32 = 200_000;
+//bins of the occupancy histogram printed in headless mode
+const OCCUPANCY_BINS: usize = 10;
+//the histogram covers four standard deviations to each side
+const BINS: usize = 41;
+const SIGMAS: f32 = 4.0;
+//vertices along each side of the terrain
+const RESOLUTION: usize = 96;
+
+#[derive(Resource)]
+struct UiValues{
+    scene: usize,
+    restart: bool,
+    //walker
+    steps: u32,
+    distribution: StepDistribution,
+    walkers: usize,
+    heatmap: bool,
+    //histogram
+    samples: u32,
+    //terrain
+    noise: usize,
+    fbm: Fbm,
+    scale: f32,
+    height: f32,
+    speed: f32
+}
+
+#[derive(Resource, Default)]
+struct Histogram {
+    counts: Vec<u32>,
+    sum: f32,
+    sum_squared: f32,
+    total: u32
+}
+
+#[derive(Component)]
+struct ZeroScene;
+
+// how long a step of a random walker is
+#[derive(Clone, Copy, PartialEq)]
+enum StepDistribution {
+    //each axis uniformly within ±WALKER_STEP
+    Uniform,
+    //each axis normally distributed with WALKER_STEP as standard deviation
+    Gaussian,
+    //a random direction and a length with a power law tail, many short steps and a
+    //few long jumps
+    Levy,
+    //accept-reject: a random length r is kept with the probability r², so long steps
+    //are more likely
+    MonteCarlo
+}
+
+const DISTRIBUTIONS: [(StepDistribution, &str); 4] = [
+    (StepDistribution::Uniform, "Uniform"),
+    (StepDistribution::Gaussian, "Gaussian"),
+    (StepDistribution::Levy, "Lévy flight"),
+    (StepDistribution::MonteCarlo, "Monte Carlo"),
+];
+
+impl StepDistribution {
+    fn name(&self) -> &'static str {
+        DISTRIBUTIONS.iter().find(|(distribution, _)| distribution == self).map_or("", |(_, name)| *name)
+    }
+
+    fn step(&self, rng: &mut impl Rng) -> Vec3 {
+        match self {
+            StepDistribution::Uniform => {
+                Vec3::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0))
+                    * WALKER_STEP
+            }
+            StepDistribution::Gaussian => {
+                Vec3::new(gaussian(rng), gaussian(rng), gaussian(rng)) * WALKER_STEP
+            }
+            StepDistribution::Levy => {
+                //pareto distributed length, the inverse of its cumulative distribution
+                let uniform: f32 = rng.random_range(f32::EPSILON..1.0);
+                let length = (WALKER_STEP * uniform.powf(-1.0 / LEVY_ALPHA)).min(MAX_STEP);
+                random_direction(rng) * length
+            }
+            StepDistribution::MonteCarlo => {
+                loop {
+                    let candidate: f32 = rng.random_range(0.0..1.0);
+                    let probability = candidate * candidate;
+                    if rng.random_range(0.0..1.0) < probability {
+                        return random_direction(rng) * candidate * MAX_STEP;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn random_direction(rng: &mut impl Rng) -> Vec3 {
+    let z: f32 = rng.random_range(-1.0..1.0);
+    let angle = rng.random_range(0.0..TAU);
+    let radius = (1.0 - z * z).sqrt();
+    Vec3::new(radius * angle.cos(), radius * angle.sin(), z)
+}
+
+#[derive(Component)]
+struct RandomWalker {
+    distribution: StepDistribution
+}
+
+impl RandomWalker {
+    // the walker stays inside the limit cube
+    fn walk(&self, position: Vec3, rng: &mut impl Rng) -> Vec3 {
+        (position + self.distribution.step(rng)).map(reflect)
+    }
+}
+
+// mirrors a coordinate that left the cube back at the wall it crossed; clamping
+// instead would pile the visits up on the walls
+fn reflect(mut value: f32) -> f32 {
+    loop {
+        if value > MAX_LIMIT {
+            value = 2.0 * MAX_LIMIT - value;
+        } else if value < MIN_LIMIT {
+            value = 2.0 * MIN_LIMIT - value;
+        } else {
+            return value;
+        }
+    }
+}
+
+// how often the walkers visited each voxel of the limit cube
+#[derive(Resource)]
+struct Occupancy {
+    counts: Vec<u32>,
+    total: u32
+}
+
+impl Default for Occupancy {
+    fn default() -> Self {
+        Occupancy {
+            counts: vec![0; VOXELS * VOXELS * VOXELS],
+            total: 0
+        }
+    }
+}
+
+impl Occupancy {
+    fn cell(position: Vec3) -> UVec3 {
+        let cell = (position - MIN_LIMIT) / (MAX_LIMIT - MIN_LIMIT) * VOXELS as f32;
+        cell.as_uvec3().min(UVec3::splat(VOXELS as u32 - 1))
+    }
+
+    fn index(cell: UVec3) -> usize {
+        (cell.x as usize * VOXELS + cell.y as usize) * VOXELS + cell.z as usize
+    }
+
+    fn center(index: usize) -> Vec3 {
+        let cell = Vec3::new((index / (VOXELS * VOXELS)) as f32, (index / VOXELS % VOXELS) as f32, (index % VOXELS) as f32);
+        Vec3::splat(MIN_LIMIT) + (cell + 0.5) * (MAX_LIMIT - MIN_LIMIT) / VOXELS as f32
+    }
+
+    fn record(&mut self, position: Vec3) {
+        self.counts[Self::index(Self::cell(position))] += 1;
+        self.total += 1;
+    }
+
+    fn occupied(&self) -> usize {
+        self.counts.iter().filter(|count| **count > 0).count()
+    }
+
+    // the visits summed over the other two axes
+    fn marginal(&self, axis: usize) -> Vec<u32> {
+        let mut marginal = vec![0; VOXELS];
+        for (index, count) in self.counts.iter().enumerate() {
+            let cell = [index / (VOXELS * VOXELS), index / VOXELS % VOXELS, index % VOXELS];
+            marginal[cell[axis]] += count;
+        }
+        marginal
+    }
+
+    // how many voxels got each number of visits, in bins from none to the most
+    fn histogram(&self, bins: usize) -> Vec<u32> {
+        let most = self.counts.iter().copied().max().unwrap_or(0);
+        let mut histogram = vec![0; bins];
+        for count in &self.counts {
+            histogram[(*count as usize * bins / (most as usize + 1)).min(bins - 1)] += 1;
+        }
+        histogram
+    }
+
+    // chi squared against visits spread evenly over all voxels, the larger the less
+    // evenly the walkers cover the cube
+    fn chi_squared(&self) -> f32 {
+        let expected = self.total as f32 / self.counts.len() as f32;
+        if expected == 0.0 {
+            return 0.0;
+        }
+        self.counts.iter().map(|count| (*count as f32 - expected).powi(2) / expected).sum()
+    }
+}
+
+#[derive(Component)]
+struct Voxel(usize);
+
+// materials of the heatmap from rarely to often visited
+#[derive(Resource)]
+struct HeatPalette(Vec<Handle<StandardMaterial>>);
+
+#[derive(Component)]
+struct HistogramBar(usize);
+
+#[derive(Component)]
+struct Terrain;
+
+fn main() {
+    //cargo run --example zero -- --headless
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless();
+        return;
+    }
+
+    App::new()
+        .insert_resource(ClearColor(Color::WHITE))
+        .insert_resource(UiValues{
+            scene: 0,
+            restart: true,
+            steps: 10,
+            distribution: StepDistribution::Uniform,
+            walkers: 1,
+            heatmap: true,
+            samples: 100,
+            noise: 0,
+            fbm: Fbm::default(),
+            scale: 0.15,
+            height: 4.0,
+            speed: 0.0
+        })
+        .init_resource::<Histogram>()
+        .init_resource::<Occupancy>()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "Example 0".to_string(),
+                resolution: WindowResolution::new(WIDTH,  HEIGHT),
+                resizable: false,
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((
+            EguiPlugin,
+            OrbitCameraPlugin,
+            TrailPlugin
+        ))
+        .add_systems(Startup, (spawn_camera,
+                               spawn_scene,
+                               mesh::spawn_limit_cube))
+        .add_systems(Update, (ui_egui,
+                              restart,
+                              walk,
+                              update_heatmap,
+                              update_histogram,
+                              update_terrain).chain())
+        .run();
+}
+
+fn spawn_scene(
+    mut commands:Commands,
+){
+    //light
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform {
+            translation: Vec3::new(0.0, 4.0, 0.0),
+            rotation: Quat::from_rotation_x(-PI / 3.0),
+            ..default()
+        }
+    ));
+    // ambient light
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.2,
+    });
+}
+
+fn spawn_camera(
+    mut commands:Commands
+){
+    commands.spawn((
+        Camera3d::default(),
+        Msaa::Sample4,
+        OrbitCamera{
+            distance : 28.0,
+            ..default()
+        }
+    ));
+}
+
+// Box-Muller: two uniform numbers give one normally distributed one
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = rng.random_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.random_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+fn restart(
+    mut commands:Commands,
+    mut ui_values: ResMut<UiValues>,
+    mut histogram: ResMut<Histogram>,
+    mut occupancy: ResMut<Occupancy>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, With<ZeroScene>>
+){
+    if !ui_values.restart {
+        return;
+    }
+    ui_values.restart = false;
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    match ui_values.scene {
+        0 => {
+            *occupancy = Occupancy::default();
+            let mesh = meshes.add(Mesh::from(Sphere { radius: 0.2 }));
+            let material = materials.add(StandardMaterial {
+                base_color: Color::Srgba(RED),
+                ..default()
+            });
+            for _ in 0..ui_values.walkers {
+                commands.spawn((
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform::default(),
+                    RandomWalker { distribution: ui_values.distribution },
+                    Trail::new(500, Color::Srgba(BLUE)).with_spacing(0.01),
+                    ZeroScene
+                ));
+            }
+
+            //the voxels grow with the number of visits, empty ones have no size
+            let palette: Vec<_> = (0..HEAT_COLORS).map(|step| {
+                let color = BLUE.mix(&YELLOW, step as f32 / (HEAT_COLORS - 1) as f32);
+                materials.add(StandardMaterial {
+                    base_color: Color::Srgba(color.with_alpha(0.35)),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                })
+            }).collect();
+            let size = (MAX_LIMIT - MIN_LIMIT) / VOXELS as f32;
+            let mesh = meshes.add(Mesh::from(Cuboid::from_length(size)));
+            for index in 0..occupancy.counts.len() {
+                commands.spawn((
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(palette[0].clone()),
+                    Transform::from_translation(Occupancy::center(index)).with_scale(Vec3::ZERO),
+                    Voxel(index),
+                    ZeroScene
+                ));
+            }
+            commands.insert_resource(HeatPalette(palette));
+        }
+        1 => {
+            *histogram = Histogram {
+                counts: vec![0; BINS],
+                ..default()
+            };
+            let width = (MAX_LIMIT - MIN_LIMIT) / BINS as f32;
+            //unit high, the height is the scale
+            let mesh = meshes.add(Mesh::from(Cuboid::new(width * 0.9, 1.0, width * 0.9)));
+            let material = materials.add(StandardMaterial {
+                base_color: Color::Srgba(BLUE),
+                ..default()
+            });
+            for bin in 0..BINS {
+                let x = MIN_LIMIT + (bin as f32 + 0.5) * width;
+                commands.spawn((
+                    Mesh3d(mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform::from_xyz(x, MIN_LIMIT, 0.0).with_scale(Vec3::new(1.0, 0.0, 1.0)),
+                    HistogramBar(bin),
+                    ZeroScene
+                ));
+            }
+        }
+        _ => {
+            let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+            let mut indices = Vec::new();
+            for row in 0..RESOLUTION - 1 {
+                for column in 0..RESOLUTION - 1 {
+                    let corner = (row * RESOLUTION + column) as u32;
+                    let below = corner + RESOLUTION as u32;
+                    indices.extend_from_slice(&[corner, below, corner + 1, corner + 1, below, below + 1]);
+                }
+            }
+            mesh.insert_indices(Indices::U32(indices));
+            fill_terrain(&mut mesh, &ui_values, 0.0);
+            commands.spawn((
+                Mesh3d(meshes.add(mesh)),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: Color::WHITE,
+                    perceptual_roughness: 0.9,
+                    ..default()
+                })),
+                Transform::default(),
+                Terrain,
+                ZeroScene
+            ));
+        }
+    }
+}
+
+fn walk(
+    ui_values: Res<UiValues>,
+    mut occupancy: ResMut<Occupancy>,
+    mut query: Query<(&mut Transform, &RandomWalker)>
+){
+    let mut rng = rand::rng();
+    for (mut transform, walker) in query.iter_mut() {
+        for _ in 0..ui_values.steps {
+            transform.translation = walker.walk(transform.translation, &mut rng);
+            occupancy.record(transform.translation);
+        }
+    }
+}
+
+fn update_heatmap(
+    ui_values: Res<UiValues>,
+    occupancy: Res<Occupancy>,
+    palette: Option<Res<HeatPalette>>,
+    mut query: Query<(&mut Transform, &mut MeshMaterial3d<StandardMaterial>, &Voxel)>
+){
+    let Some(palette) = palette else {
+        return;
+    };
+    let highest = occupancy.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+    for (mut transform, mut material, voxel) in query.iter_mut() {
+        let density = occupancy.counts[voxel.0] as f32 / highest;
+        let scale = if ui_values.heatmap { density.cbrt() } else { 0.0 };
+        if transform.scale.x != scale {
+            transform.scale = Vec3::splat(scale);
+        }
+        let color = ((density * (HEAT_COLORS - 1) as f32).round() as usize).min(HEAT_COLORS - 1);
+        if material.0 != palette.0[color] {
+            material.0 = palette.0[color].clone();
+        }
+    }
+}
+
+fn update_histogram(
+    ui_values: Res<UiValues>,
+    mut histogram: ResMut<Histogram>,
+    mut query: Query<(&mut Transform, &HistogramBar)>
+){
+    if query.is_empty() {
+        return;
+    }
+    let mut rng = rand::rng();
+    for _ in 0..ui_values.samples {
+        let value = gaussian(&mut rng);
+        histogram.sum += value;
+        histogram.sum_squared += value * value;
+        histogram.total += 1;
+        let bin = ((value / SIGMAS + 1.0) / 2.0 * BINS as f32).floor();
+        if bin >= 0.0 && (bin as usize) < BINS {
+            histogram.counts[bin as usize] += 1;
+        }
+    }
+
+    //the highest bar reaches the top of the limit cube
+    let highest = histogram.counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+    for (mut transform, bar) in query.iter_mut() {
+        let height = histogram.counts[bar.0] as f32 / highest * (MAX_LIMIT - MIN_LIMIT);
+        transform.scale.y = height;
+        transform.translation.y = MIN_LIMIT + height / 2.0;
+    }
+}
+
+// heights from fractal noise; the third noise dimension is time, so the terrain
+// flows when the speed is above zero
+fn fill_terrain(mesh: &mut Mesh, ui_values: &UiValues, time: f32) {
+    let noise = if ui_values.noise == 0 { perlin::<3> } else { simplex::<3> };
+    let size = MAX_LIMIT - MIN_LIMIT;
+    let mut positions = Vec::with_capacity(RESOLUTION * RESOLUTION);
+    let mut colors = Vec::with_capacity(RESOLUTION * RESOLUTION);
+
+    for row in 0..RESOLUTION {
+        for column in 0..RESOLUTION {
+            let x = MIN_LIMIT + column as f32 / (RESOLUTION - 1) as f32 * size;
+            let z = MIN_LIMIT + row as f32 / (RESOLUTION - 1) as f32 * size;
+            let value = ui_values.fbm.sample(noise, [x * ui_values.scale, z * ui_values.scale, time * ui_values.speed]);
+            positions.push([x, MIN_LIMIT + ui_values.height * (value + 1.0), z]);
+            //water blue in the valleys, green slopes, white peaks
+            let color = if value < 0.0 {
+                BLUE.mix(&GREEN, (value + 1.0).clamp(0.0, 1.0))
+            } else {
+                GREEN.mix(&WHITE, value.min(1.0))
+            };
+            colors.push(LinearRgba::from(color).to_f32_array());
+        }
+    }
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.compute_smooth_normals();
+}
+
+// the egui window takes the values mutably every frame, so they always look changed;
+// the terrain is only rebuilt when the values it depends on differ from the last ones
+fn update_terrain(
+    time: Res<Time>,
+    ui_values: Res<UiValues>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut previous: Local<Option<(usize, Fbm, f32, f32)>>,
+    query: Query<&Mesh3d, With<Terrain>>
+){
+    let current = (ui_values.noise, ui_values.fbm, ui_values.scale, ui_values.height);
+    if *previous == Some(current) && ui_values.speed == 0.0 {
+        return;
+    }
+    *previous = Some(current);
+    for mesh in query.iter() {
+        if let Some(mesh) = meshes.get_mut(&mesh.0) {
+            fill_terrain(mesh, &ui_values, time.elapsed_secs());
+        }
+    }
+}
+
+fn ui_egui(
+    mut egui_contexts: EguiContexts,
+    mut ui_values: ResMut<UiValues>,
+    histogram: Res<Histogram>,
+    occupancy: Res<Occupancy>
+){
+    let mut scene = ui_values.scene;
+    let mut distribution = ui_values.distribution;
+    let mut walkers = ui_values.walkers;
+    egui::Window::new("Properties").show(egui_contexts.ctx_mut(), |ui|{
+        egui::ComboBox::from_label("Scene")
+            .selected_text(SCENES[scene])
+            .show_ui(ui, |ui| {
+                for (index, name) in SCENES.iter().enumerate() {
+                    ui.selectable_value(&mut scene, index, *name);
+                }
+            });
+        match ui_values.scene {
+            0 => {
+                egui::ComboBox::from_label("Step")
+                    .selected_text(distribution.name())
+                    .show_ui(ui, |ui| {
+                        for (value, name) in DISTRIBUTIONS {
+                            ui.selectable_value(&mut distribution, value, name);
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut walkers, 1..=50).text("walkers"));
+                ui.add(egui::Slider::new(&mut ui_values.steps, 1..=100).text("steps p. frame"));
+                ui.add(egui::Checkbox::new(&mut ui_values.heatmap, "heatmap"));
+                ui.label(format!("steps: {}", occupancy.total));
+                ui.label(format!("visited voxels: {} of {}", occupancy.occupied(), occupancy.counts.len()));
+                ui.label(format!("chi² against even: {:.0}", occupancy.chi_squared()));
+            }
+            1 => {
+                ui.add(egui::Slider::new(&mut ui_values.samples, 1..=1000).text("samples p. frame"));
+                if histogram.total > 0 {
+                    let mean = histogram.sum / histogram.total as f32;
+                    let variance = histogram.sum_squared / histogram.total as f32 - mean * mean;
+                    ui.label(format!("samples: {}", histogram.total));
+                    ui.label(format!("mean: {:.3}  standard deviation: {:.3}", mean, variance.max(0.0).sqrt()));
+                }
+            }
+            _ => {
+                egui::ComboBox::from_label("Noise")
+                    .selected_text(NOISES[ui_values.noise])
+                    .show_ui(ui, |ui| {
+                        for (index, name) in NOISES.iter().enumerate() {
+                            ui.selectable_value(&mut ui_values.noise, index, *name);
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut ui_values.fbm.octaves, 1..=8).text("octaves"));
+                ui.add(egui::Slider::new(&mut ui_values.fbm.lacunarity, 1.0..=4.0).text("lacunarity"));
+                ui.add(egui::Slider::new(&mut ui_values.fbm.gain, 0.0..=1.0).text("gain"));
+                ui.add(egui::Slider::new(&mut ui_values.scale, 0.01..=1.0).text("scale"));
+                ui.add(egui::Slider::new(&mut ui_values.height, 0.0..=MAX_LIMIT).text("height"));
+                ui.add(egui::Slider::new(&mut ui_values.speed, 0.0..=2.0).text("speed"));
+            }
+        }
+        if ui.button("Restart").clicked() {
+            ui_values.restart = true;
+        }
+    });
+    if scene != ui_values.scene || distribution != ui_values.distribution || walkers != ui_values.walkers {
+        ui_values.scene = scene;
+        ui_values.distribution = distribution;
+        ui_values.walkers = walkers;
+        ui_values.restart = true;
+    }
+}
+
+// walks one walker of every distribution from the center and prints the occupancy
+// histogram, the visits along x and the statistics, to compare the distributions
+fn run_headless() {
+    let mut rng = rand::rng();
+    for (distribution, name) in DISTRIBUTIONS {
+        let walker = RandomWalker { distribution };
+        let mut occupancy = Occupancy::default();
+        let mut position = Vec3::ZERO;
+        let mut length = 0.0;
+        for _ in 0..HEADLESS_STEPS {
+            let next = walker.walk(position, &mut rng);
+            length += next.distance(position);
+            position = next;
+            occupancy.record(position);
+        }
+
+        println!("{name}");
+        println!("  mean step: {:.3}", length / HEADLESS_STEPS as f32);
+        println!("  visited voxels: {} of {}", occupancy.occupied(), occupancy.counts.len());
+        let most = occupancy.counts.iter().max().unwrap_or(&0);
+        println!("  voxels by visits (0 to {most} in {OCCUPANCY_BINS} bins):");
+        for (bin, voxels) in occupancy.histogram(OCCUPANCY_BINS).iter().enumerate() {
+            println!("    {:>6}+ visits: {voxels} voxels", (bin as u32 * (most + 1)).div_ceil(OCCUPANCY_BINS as u32));
+        }
+        println!("  chi² against even ({} degrees of freedom): {:.1}", occupancy.counts.len() - 1, occupancy.chi_squared());
+        let marginal: Vec<String> = occupancy.marginal(0).iter().map(|count| count.to_string()).collect();
+        println!("  visits along x: {}", marginal.join(" "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const SAMPLES: usize = 100_000;
+
+    fn steps(distribution: StepDistribution) -> Vec<Vec3> {
+        let mut rng = StdRng::seed_from_u64(44);
+        (0..SAMPLES).map(|_| distribution.step(&mut rng)).collect()
+    }
+
+    fn mean(values: impl Iterator<Item = f32>) -> f32 {
+        values.sum::<f32>() / SAMPLES as f32
+    }
+
+    #[test]
+    fn uniform_steps_stay_within_the_step_on_every_axis() {
+        let steps = steps(StepDistribution::Uniform);
+        assert!(steps.iter().all(|step| step.abs().max_element() <= WALKER_STEP));
+        //a uniform distribution over ±a has the variance a²/3
+        for axis in 0..3 {
+            assert!(mean(steps.iter().map(|step| step[axis])).abs() < 0.005);
+            let variance = mean(steps.iter().map(|step| step[axis] * step[axis]));
+            assert!((variance / (WALKER_STEP * WALKER_STEP / 3.0) - 1.0).abs() < 0.02, "variance {variance}");
+        }
+    }
+
+    #[test]
+    fn gaussian_steps_have_the_step_as_standard_deviation() {
+        let steps = steps(StepDistribution::Gaussian);
+        for axis in 0..3 {
+            assert!(mean(steps.iter().map(|step| step[axis])).abs() < 0.005);
+            let deviation = mean(steps.iter().map(|step| step[axis] * step[axis])).sqrt();
+            assert!((deviation / WALKER_STEP - 1.0).abs() < 0.02, "standard deviation {deviation}");
+        }
+    }
+
+    #[test]
+    fn levy_steps_follow_the_power_law() {
+        let mut lengths: Vec<f32> = steps(StepDistribution::Levy).iter().map(|step| step.length()).collect();
+        assert!(lengths.iter().all(|length| (WALKER_STEP * 0.999..=MAX_STEP * 1.001).contains(length)));
+        //half of a pareto distribution lies below WALKER_STEP * 2^(1/alpha)
+        lengths.sort_by(f32::total_cmp);
+        let median = lengths[SAMPLES / 2];
+        assert!((median / (WALKER_STEP * 2.0_f32.powf(1.0 / LEVY_ALPHA)) - 1.0).abs() < 0.02, "median {median}");
+    }
+
+    #[test]
+    fn monte_carlo_steps_prefer_long_lengths() {
+        let lengths: Vec<f32> = steps(StepDistribution::MonteCarlo).iter().map(|step| step.length()).collect();
+        assert!(lengths.iter().all(|length| *length <= MAX_STEP * 1.001));
+        //accepting r with the probability r² gives the density 3r², its mean is 3/4
+        let mean = mean(lengths.iter().copied());
+        assert!((mean / (0.75 * MAX_STEP) - 1.0).abs() < 0.01, "mean {mean}");
+    }
+
+    #[test]
+    fn reflection_mirrors_at_the_walls() {
+        assert_eq!(reflect(MAX_LIMIT + 1.0), MAX_LIMIT - 1.0);
+        assert_eq!(reflect(MIN_LIMIT - 1.0), MIN_LIMIT + 1.0);
+        assert_eq!(reflect(1.0), 1.0);
+    }
+
+    // the long monte carlo steps cross the cube often, so after a while every slice
+    // along x should be visited about as often as the others, walls included
+    #[test]
+    fn walls_get_no_more_visits_than_the_inside() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let walker = RandomWalker { distribution: StepDistribution::MonteCarlo };
+        let mut occupancy = Occupancy::default();
+        let mut position = Vec3::ZERO;
+        for _ in 0..SAMPLES {
+            position = walker.walk(position, &mut rng);
+            assert!(position.min_element() >= MIN_LIMIT && position.max_element() <= MAX_LIMIT);
+            occupancy.record(position);
+        }
+
+        let expected = SAMPLES as f32 / VOXELS as f32;
+        for axis in 0..3 {
+            let marginal = occupancy.marginal(axis);
+            for count in [marginal[0], marginal[VOXELS - 1]] {
+                assert!((count as f32 / expected - 1.0).abs() < 0.1, "{count} visits on a wall of axis {axis}");
+            }
+        }
+        assert_eq!(occupancy.histogram(OCCUPANCY_BINS).iter().sum::<u32>() as usize, occupancy.counts.len());
+    }
+}